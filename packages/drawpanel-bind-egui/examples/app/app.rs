@@ -1,8 +1,8 @@
 use std::{any::Any, borrow::Borrow, cell::RefCell, rc::Rc};
 
-use drawpanel_bind_egui::{EguiBinder, EguiHookEvent};
+use drawpanel_bind_egui::{cursor_icon, EguiBinder, EguiHookEvent};
 use drawpanel_core::{
-    binder::{EventMouseButton, EventRect, EventType, EventZoom},
+    binder::{EventKey, EventMouseButton, EventRect, EventType, EventZoom},
     drawpanel::{Drawpanel, Mode},
 };
 use eframe::emath;
@@ -89,6 +89,13 @@ impl eframe::App for TemplateApp {
                         println!("Text");
                         ui.close_menu();
                     }
+                    if ui.button("Dimension").clicked() {
+                        self.drawpanel.set_mode(Mode::Creating(Some(Box::new(
+                            drawpanel_core::elem::dimension::Dimension::default(),
+                        ))));
+                        println!("Dimension");
+                        ui.close_menu();
+                    }
                 });
             });
         });
@@ -120,12 +127,52 @@ impl eframe::App for TemplateApp {
                 //     Box::new(ui),
                 // );
 
-                if let Some(pointer_pos) = response.interact_pointer_pos() {
-                    if response.dragged_by(PointerButton::Primary) {
+                // Fingers go to the panel's gesture recognizer, which makes
+                // its own clicks, drags and pinches. The pointer egui
+                // emulates from the first finger is left out.
+                let touching = ctx.input().any_touches()
+                    || ctx
+                        .input()
+                        .events
+                        .iter()
+                        .any(|event| matches!(event, egui::Event::Touch { .. }));
+                ctx.input().events.iter().for_each(|event| {
+                    if let egui::Event::Touch { id, phase, pos, .. } = event {
+                        let event_type = match phase {
+                            egui::TouchPhase::Start if response.rect.contains(*pos) => {
+                                EventType::TouchStart(id.0)
+                            }
+                            egui::TouchPhase::Start => return,
+                            egui::TouchPhase::Move => EventType::TouchMove(id.0),
+                            egui::TouchPhase::End | egui::TouchPhase::Cancel => {
+                                EventType::TouchEnd(id.0)
+                            }
+                        };
+                        panel.trigger_event(
+                            event_type,
+                            coord! {
+                                x: pos.x as f64,
+                                y: pos.y as f64
+                            },
+                        );
+                    }
+                });
+
+                if let Some(pointer_pos) = response.interact_pointer_pos().filter(|_| !touching) {
+                    let button = if response.dragged_by(PointerButton::Primary) {
+                        Some(EventMouseButton::Left)
+                    } else if response.dragged_by(PointerButton::Middle) {
+                        Some(EventMouseButton::Middle)
+                    } else if response.dragged_by(PointerButton::Secondary) {
+                        Some(EventMouseButton::Right)
+                    } else {
+                        None
+                    };
+                    if let Some(button) = button {
                         if response.drag_started() {
                             // println!("Drag Started {:?}", pointer_pos);
                             panel.trigger_event(
-                                EventType::Push(EventMouseButton::Left),
+                                EventType::Push(button),
                                 coord! {
                                     x: pointer_pos.x as f64,
                                     y: pointer_pos.y as f64
@@ -145,7 +192,9 @@ impl eframe::App for TemplateApp {
                     }
                 }
 
-                if response.dragged() {
+                if touching {
+                    // Left to the gesture recognizer.
+                } else if response.dragged() {
                     if let Some(pointer_pos) = response.hover_pos() {
                         panel.trigger_event(
                             EventType::Drag(EventMouseButton::Left),
@@ -165,6 +214,17 @@ impl eframe::App for TemplateApp {
                     );
                 }
                 if let Some(pointer_pos) = response.hover_pos() {
+                    // Pinch on touchpads.
+                    let zoom_delta = ui.ctx().input().zoom_delta();
+                    if zoom_delta != 1.0 && !touching {
+                        panel.trigger_event(
+                            EventType::Zoom(EventZoom::Factor(zoom_delta as f64)),
+                            coord! {
+                                x: pointer_pos.x as f64,
+                                y: pointer_pos.y as f64
+                            },
+                        );
+                    }
                     ui.ctx().input().events.iter().for_each(|event| {
                         if let egui::Event::Scroll(v) = event {
                             panel.trigger_event(
@@ -179,6 +239,29 @@ impl eframe::App for TemplateApp {
                                 },
                             );
                         }
+                        if let egui::Event::Key { key, pressed, .. } = event {
+                            let key = match key {
+                                egui::Key::Escape => Some(EventKey::Escape),
+                                egui::Key::Delete => Some(EventKey::Delete),
+                                egui::Key::Backspace => Some(EventKey::Backspace),
+                                egui::Key::Enter => Some(EventKey::Enter),
+                                egui::Key::Space => Some(EventKey::Space),
+                                _ => None,
+                            };
+                            if let Some(key) = key {
+                                panel.trigger_event(
+                                    if *pressed {
+                                        EventType::KeyDown(key)
+                                    } else {
+                                        EventType::KeyUp(key)
+                                    },
+                                    coord! {
+                                        x: pointer_pos.x as f64,
+                                        y: pointer_pos.y as f64
+                                    },
+                                );
+                            }
+                        }
                     });
                 }
 
@@ -194,6 +277,11 @@ impl eframe::App for TemplateApp {
                     }
                 }
 
+                // A released pan glides on.
+                if panel.tick(ctx.input().stable_dt as f64) {
+                    ctx.request_repaint();
+                }
+
                 // 绘图
                 let shapes: Box<RefCell<Option<Vec<egui::Shape>>>> = panel
                     .trigger_draw2(Box::new(ctx.clone()))
@@ -206,6 +294,9 @@ impl eframe::App for TemplateApp {
                 // 输入框处理
                 if let Some(hook_event) = panel.hook_event.as_mut() {
                     let hook: EguiHookEvent = *hook_event.get_state().downcast().unwrap();
+                    if response.hovered() || response.dragged() {
+                        ui.ctx().output().cursor_icon = cursor_icon(hook.cursor);
+                    }
                     if let Some(input_rect) = hook.input_rect {
                         self.my_string = hook.input_text.unwrap();
                         println!("input_rect: {:?}", input_rect);
@@ -224,6 +315,7 @@ impl eframe::App for TemplateApp {
                         hook_event.set_state(Box::new(EguiHookEvent {
                             input_rect: Some(input_rect),
                             input_text: Some(self.my_string.clone()),
+                            cursor: hook.cursor,
                         }));
                     }
                 }
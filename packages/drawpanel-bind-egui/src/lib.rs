@@ -3,8 +3,8 @@ use std::{fmt::Debug, rc::Weak};
 
 use drawpanel_core::{
     binder::{
-        Binder, Draw, DrawCircleOpts, DrawLineOpts, DrawRectOpts, EventMouseButton, EventRect,
-        EventType, EventZoom, HookEvent, IDraw, IHookEvent,
+        Binder, Cursor, Draw, DrawCircleOpts, DrawLineOpts, DrawPathOpts, DrawRectOpts,
+        EventMouseButton, EventRect, EventType, EventZoom, HookEvent, IDraw, IHookEvent,
     },
     drawpanel::Drawpanel,
     elem::{rect::Rect, Elem, IElem},
@@ -118,6 +118,24 @@ impl Draw for EguiDraw {
         }
     }
 
+    fn draw_path(&self, opts: DrawPathOpts) {
+        let mut shapes = self.shapes.borrow_mut();
+
+        if let Some(shapes) = shapes.as_mut() {
+            let points: Vec<Pos2> = opts
+                .coords
+                .iter()
+                .map(|coord| Pos2::new(coord.x as f32, coord.y as f32))
+                .collect();
+            let stroke = egui::Stroke::new(opts.line_size as f32, egui::Color32::RED);
+            if opts.closed {
+                shapes.push(egui::Shape::closed_line(points, stroke));
+            } else {
+                shapes.push(egui::Shape::line(points, stroke));
+            }
+        }
+    }
+
     fn draw_end(&self) -> Box<dyn std::any::Any> {
         // println!("[DEBUG] draw_end");
         return Box::new(self.shapes.clone());
@@ -128,10 +146,30 @@ impl Draw for EguiDraw {
 pub struct EguiHookEvent {
     pub input_rect: Option<EventRect>,
     pub input_text: Option<String>,
+    /// Set it with `ctx.output().cursor_icon = cursor_icon(cursor)` while
+    /// the pointer is over the panel.
+    pub cursor: Cursor,
 }
 
 impl EguiHookEvent {}
 
+pub fn cursor_icon(cursor: Cursor) -> egui::CursorIcon {
+    use egui::CursorIcon;
+    match cursor {
+        Cursor::Default => CursorIcon::Default,
+        Cursor::Move => CursorIcon::Move,
+        Cursor::ResizeNorth | Cursor::ResizeSouth => CursorIcon::ResizeVertical,
+        Cursor::ResizeEast | Cursor::ResizeWest => CursorIcon::ResizeHorizontal,
+        Cursor::ResizeNorthEast | Cursor::ResizeSouthWest => CursorIcon::ResizeNeSw,
+        Cursor::ResizeNorthWest | Cursor::ResizeSouthEast => CursorIcon::ResizeNwSe,
+        Cursor::Crosshair => CursorIcon::Crosshair,
+        Cursor::Text => CursorIcon::Text,
+        Cursor::NotAllowed => CursorIcon::NotAllowed,
+        Cursor::Grab => CursorIcon::Grab,
+        Cursor::Grabbing => CursorIcon::Grabbing,
+    }
+}
+
 impl IHookEvent for EguiHookEvent {}
 
 impl HookEvent for EguiHookEvent {
@@ -155,6 +193,10 @@ impl HookEvent for EguiHookEvent {
 
     fn flush(&mut self) {}
 
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+    }
+
     fn get_state(&self) -> Box<dyn std::any::Any> {
         Box::new(self.clone())
     }
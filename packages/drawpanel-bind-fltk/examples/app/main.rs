@@ -117,7 +117,7 @@ impl AppView {
             let mut win = win.clone();
             move |btn| {
                 let scale = drawpanel.borrow_mut().scale();
-                (*drawpanel).borrow_mut().set_scale(scale + 0.1, 350., 300.);
+                (*drawpanel).borrow_mut().set_scale(scale * 1.1, 350., 300.);
             }
         });
 
@@ -126,7 +126,7 @@ impl AppView {
             let mut win = win.clone();
             move |btn| {
                 let scale = drawpanel.borrow_mut().scale();
-                (*drawpanel).borrow_mut().set_scale(scale - 0.1, 350., 300.);
+                (*drawpanel).borrow_mut().set_scale(scale / 1.1, 350., 300.);
             }
         });
         let data = Rc::new(RefCell::new(String::from("")));
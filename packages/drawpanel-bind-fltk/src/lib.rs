@@ -3,8 +3,8 @@ use std::{fmt::Debug, rc::Weak};
 
 use drawpanel_core::{
     binder::{
-        Binder, Draw, DrawCircleOpts, DrawLineOpts, DrawRectOpts, EventMouseButton, EventRect,
-        EventType, EventZoom, HookEvent, IDraw, IHookEvent,
+        Binder, Cursor, Draw, DrawCircleOpts, DrawLineOpts, DrawPathOpts, DrawRectOpts, EventKey,
+        EventMouseButton, EventRect, EventType, EventZoom, HookEvent, IDraw, IHookEvent,
     },
     drawpanel::Drawpanel,
     elem::{Elem, IElem},
@@ -13,10 +13,10 @@ use drawpanel_core::{
 use fltk::{
     app,
     draw::{self, LineStyle},
-    enums::{Align, CallbackTrigger, Color, Event, Font, FrameType},
+    enums::{Align, CallbackTrigger, Color, Event, Font, FrameType, Key},
     frame::{self, Frame},
     input,
-    prelude::{GroupExt, InputExt, WidgetBase, WidgetExt},
+    prelude::{GroupExt, InputExt, WidgetBase, WidgetExt, WindowExt},
     window,
 };
 use geo::{coord, Coordinate};
@@ -53,6 +53,25 @@ impl FltkBinder {
     }
 }
 
+/// Seconds between two steps of a gliding pan.
+const FRAME: f64 = 1. / 60.;
+
+fn event_key(key: Key) -> Option<EventKey> {
+    match key {
+        Key::Escape => Some(EventKey::Escape),
+        Key::Delete => Some(EventKey::Delete),
+        Key::BackSpace => Some(EventKey::Backspace),
+        Key::Enter => Some(EventKey::Enter),
+        Key::ShiftL | Key::ShiftR => Some(EventKey::Shift),
+        Key::ControlL | Key::ControlR => Some(EventKey::Ctrl),
+        _ => match key.to_char() {
+            Some(' ') => Some(EventKey::Space),
+            Some(c) => Some(EventKey::Char(c)),
+            None => None,
+        },
+    }
+}
+
 impl Binder for FltkBinder {
     fn init(&mut self, panel: Weak<RefCell<Panel>>) {
         self.frame.draw({
@@ -78,6 +97,8 @@ impl Binder for FltkBinder {
                     app::MouseButton::Right => EventMouseButton::Right,
                     _ => EventMouseButton::None,
                 };
+                // FLTK has no touch events, it hands fingers over as the
+                // mouse, so no `EventType::TouchStart` and no gestures here.
                 match e {
                     Event::Move => {
                         (*drawpanel)
@@ -87,9 +108,11 @@ impl Binder for FltkBinder {
                         true
                     }
                     Event::Push => {
+                        // For the key events.
+                        let _ = frm.take_focus();
                         (*drawpanel)
                             .borrow_mut()
-                            .trigger_event(EventType::Push(EventMouseButton::None), mouse_coord);
+                            .trigger_event(EventType::Push(mouse_button), mouse_coord);
                         true
                     }
                     Event::Drag => {
@@ -107,6 +130,17 @@ impl Binder for FltkBinder {
                             (*drawpanel)
                                 .borrow_mut()
                                 .trigger_event(EventType::Released(mouse_button), mouse_coord);
+                            // Lets a pan glide on until it stops.
+                            app::add_timeout3(FRAME, {
+                                let drawpanel = drawpanel.clone();
+                                let mut frm = frm.clone();
+                                move |handle| {
+                                    if (*drawpanel).borrow_mut().tick(FRAME) {
+                                        frm.redraw();
+                                        app::repeat_timeout3(FRAME, handle);
+                                    }
+                                }
+                            });
                         }
                         true
                     }
@@ -121,6 +155,21 @@ impl Binder for FltkBinder {
                         );
                         true
                     }
+                    Event::Focus | Event::Unfocus => true,
+                    Event::KeyDown | Event::KeyUp => match event_key(app::event_key()) {
+                        Some(key) => {
+                            (*drawpanel).borrow_mut().trigger_event(
+                                if e == Event::KeyDown {
+                                    EventType::KeyDown(key)
+                                } else {
+                                    EventType::KeyUp(key)
+                                },
+                                mouse_coord,
+                            );
+                            true
+                        }
+                        None => false,
+                    },
                     _ => false,
                 }
             }
@@ -222,6 +271,35 @@ impl Draw for FltkDraw {
             Align::Center,
         )
     }
+
+    fn draw_path(&self, opts: DrawPathOpts) {
+        if let Some(fill_color) = opts.fill_color {
+            draw::set_draw_color(Color::from_hex(fill_color));
+            draw::begin_complex_polygon();
+            for coord in opts.coords {
+                draw::vertex(coord.x, coord.y);
+            }
+            draw::end_complex_polygon();
+        }
+        draw::set_draw_color(Color::from_hex(opts.line_color));
+        draw::set_line_style(
+            LineStyle::Solid | LineStyle::CapRound | LineStyle::JoinRound,
+            opts.line_size as i32,
+        );
+        if opts.closed {
+            draw::begin_loop();
+        } else {
+            draw::begin_line();
+        }
+        for coord in opts.coords {
+            draw::vertex(coord.x, coord.y);
+        }
+        if opts.closed {
+            draw::end_loop();
+        } else {
+            draw::end_line();
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -274,4 +352,31 @@ impl HookEvent for FltkHookEvent {
     fn flush(&mut self) {
         self.frame.redraw();
     }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        if let Some(mut window) = self.frame.window() {
+            window.set_cursor(fltk_cursor(cursor));
+        }
+    }
+}
+
+fn fltk_cursor(cursor: Cursor) -> fltk::enums::Cursor {
+    use fltk::enums::Cursor as C;
+    match cursor {
+        Cursor::Default => C::Default,
+        Cursor::Move => C::Move,
+        Cursor::ResizeNorth => C::N,
+        Cursor::ResizeNorthEast => C::NE,
+        Cursor::ResizeEast => C::E,
+        Cursor::ResizeSouthEast => C::SE,
+        Cursor::ResizeSouth => C::S,
+        Cursor::ResizeSouthWest => C::SW,
+        Cursor::ResizeWest => C::W,
+        Cursor::ResizeNorthWest => C::NW,
+        Cursor::Crosshair => C::Cross,
+        Cursor::Text => C::Insert,
+        // FLTK has no not-allowed cursor.
+        Cursor::NotAllowed => C::Default,
+        Cursor::Grab | Cursor::Grabbing => C::Hand,
+    }
 }
@@ -0,0 +1,556 @@
+use std::fmt::{Debug, Display};
+
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use base64::Engine;
+use drawpanel_core::{
+    binder::{
+        Draw, DrawCircleOpts, DrawImageOpts, DrawLineOpts, DrawPathOpts, DrawRectOpts,
+        DrawTextOpts, IDraw,
+    },
+    drawpanel::Drawpanel,
+    elem::rect::LineStyle,
+};
+use geo::coord;
+use printpdf::{
+    calculate_points_for_circle, image_crate,
+    path::{PaintMode, WindingOrder},
+    Color, Image, ImageTransform, IndirectFontRef, Line, LineCapStyle, LineDashPattern,
+    LineJoinStyle, Mm, PdfDocument, PdfLayerReference, Point, Polygon, Pt, Rgb,
+};
+
+static DEFAULT_FONT: &[u8] = include_bytes!("../assets/SmileySans.ttf");
+
+/// Points per panel unit at a `scale` of 1: a unit is a CSS pixel (1/96 in).
+const PT_PER_UNIT: f64 = 72. / 96.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageSize {
+    A3,
+    A4,
+    A5,
+    Letter,
+    Legal,
+    /// Width and height in millimeters, as in portrait orientation.
+    Custom(f64, f64),
+}
+
+impl PageSize {
+    /// Portrait width and height in millimeters.
+    pub fn mm(&self) -> (f64, f64) {
+        match self {
+            PageSize::A3 => (297., 420.),
+            PageSize::A4 => (210., 297.),
+            PageSize::A5 => (148., 210.),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+            PageSize::Custom(width, height) => (*width, *height),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+/// How the board is laid out on the pages.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PdfPaging {
+    /// Scales the board to fit a single page.
+    Fit,
+    /// Prints the board at `scale` (1 = 96 units per inch) and tiles it
+    /// across as many pages as needed, left to right, then top to bottom.
+    Tile { scale: f64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct PdfExportOpts {
+    pub title: String,
+    pub page_size: PageSize,
+    pub orientation: Orientation,
+    /// Blank border on every page, in millimeters.
+    pub margin: f64,
+    /// Prints only the bounding box of the elements instead of the whole
+    /// page of the panel.
+    pub fit_content: bool,
+    pub paging: PdfPaging,
+    /// A TrueType font to embed instead of the bundled SmileySans.
+    pub font: Option<Vec<u8>>,
+}
+
+impl Default for PdfExportOpts {
+    fn default() -> Self {
+        PdfExportOpts {
+            title: "drawpanel".to_string(),
+            page_size: PageSize::A4,
+            orientation: Orientation::Portrait,
+            margin: 10.,
+            fit_content: false,
+            paging: PdfPaging::Fit,
+            font: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PdfExportError {
+    /// `PdfExportOpts::font` is not a TrueType or OpenType font.
+    InvalidFont,
+    Pdf(printpdf::Error),
+}
+
+impl Display for PdfExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdfExportError::InvalidFont => write!(f, "invalid font"),
+            PdfExportError::Pdf(err) => write!(f, "cannot write pdf: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PdfExportError {}
+
+impl From<printpdf::Error> for PdfExportError {
+    fn from(err: printpdf::Error) -> Self {
+        PdfExportError::Pdf(err)
+    }
+}
+
+/// Draws into one page of a PDF document. Coordinates are in points from
+/// the top left corner, as `Panel::render` hands them out.
+pub struct PdfDraw {
+    layer: PdfLayerReference,
+    page_height: f64,
+    font: IndirectFontRef,
+    metrics: FontArc,
+}
+
+impl PdfDraw {
+    pub fn new(
+        layer: PdfLayerReference,
+        page_height: f64,
+        font: IndirectFontRef,
+        metrics: FontArc,
+    ) -> Self {
+        PdfDraw {
+            layer,
+            page_height,
+            font,
+            metrics,
+        }
+    }
+
+    fn point(&self, x: f64, y: f64) -> Point {
+        Point {
+            x: Pt(x as f32),
+            y: Pt((self.page_height - y) as f32),
+        }
+    }
+
+    fn set_stroke(&self, line_size: f64, line_color: u32, dash: bool) {
+        self.layer.set_outline_color(color(line_color));
+        self.layer.set_outline_thickness(line_size as f32);
+        self.layer.set_line_dash_pattern(if dash {
+            LineDashPattern {
+                dash_1: Some(line_size.ceil().max(1.) as i64),
+                gap_1: Some((line_size * 2.).ceil().max(1.) as i64),
+                ..LineDashPattern::default()
+            }
+        } else {
+            LineDashPattern::default()
+        });
+    }
+
+    fn paint(&self, rings: Vec<Vec<(Point, bool)>>, stroke: bool, fill_color: Option<u32>) {
+        let mode = match (stroke, fill_color) {
+            (true, Some(_)) => PaintMode::FillStroke,
+            (true, None) => PaintMode::Stroke,
+            (false, Some(_)) => PaintMode::Fill,
+            (false, None) => return,
+        };
+        if let Some(fill_color) = fill_color {
+            self.layer.set_fill_color(color(fill_color));
+        }
+        self.layer.add_polygon(Polygon {
+            rings,
+            mode,
+            winding_order: WindingOrder::NonZero,
+        });
+    }
+
+    fn text_width(&self, line: &str, font_size: f64, font_space: f64) -> f64 {
+        let font = self.metrics.as_scaled(PxScale::from(font_size as f32));
+        let mut width = 0.;
+        let mut prev = None;
+        for c in line.chars() {
+            let id = font.glyph_id(c);
+            if let Some(prev) = prev {
+                width += font.kern(prev, id) as f64;
+            }
+            width += font.h_advance(id) as f64 + font_space;
+            prev = Some(id);
+        }
+        width
+    }
+}
+
+impl Debug for PdfDraw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PdfDraw")
+            .field("page_height", &self.page_height)
+            .finish()
+    }
+}
+
+impl IDraw for PdfDraw {}
+
+impl Draw for PdfDraw {
+    fn draw_begin(&self, _ctx: Box<dyn std::any::Any>) {
+        self.layer.set_line_cap_style(LineCapStyle::Round);
+        self.layer.set_line_join_style(LineJoinStyle::Round);
+    }
+
+    fn draw_line(&self, opts: DrawLineOpts) {
+        if opts.line_size <= 0. {
+            return;
+        }
+        self.set_stroke(opts.line_size, opts.line_color, false);
+        self.layer.add_line(Line {
+            points: vec![
+                (self.point(opts.from_coord.x, opts.from_coord.y), false),
+                (self.point(opts.end_coord.x, opts.end_coord.y), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    fn draw_rect(&self, opts: DrawRectOpts) {
+        let (x, y) = (opts.left_top_coord.x, opts.left_top_coord.y);
+        let ring = vec![
+            (self.point(x, y), false),
+            (self.point(x + opts.width, y), false),
+            (self.point(x + opts.width, y + opts.height), false),
+            (self.point(x, y + opts.height), false),
+        ];
+        let stroke = opts.line_size > 0.;
+        if stroke {
+            let dash = matches!(opts.line_style, LineStyle::Dotted);
+            self.set_stroke(opts.line_size, opts.line_color, dash);
+        }
+        self.paint(vec![ring], stroke, opts.fill_color);
+    }
+
+    fn draw_circle(&self, opts: DrawCircleOpts) {
+        let center = self.point(opts.center_coord.x, opts.center_coord.y);
+        let ring = calculate_points_for_circle(Pt(opts.r as f32), center.x, center.y);
+        let stroke = opts.line_size > 0.;
+        if stroke {
+            self.set_stroke(opts.line_size, opts.line_color, false);
+        }
+        self.paint(vec![ring], stroke, Some(opts.fill_color));
+    }
+
+    fn draw_text(&self, opts: DrawTextOpts) {
+        let font = self.metrics.as_scaled(PxScale::from(opts.font_size as f32));
+        let line_height = (font.height() + font.line_gap()) as f64;
+        let lines: Vec<&str> = opts.content.split('\n').collect();
+
+        // Centered in the box, like the other binders.
+        let text_height = line_height * lines.len() as f64;
+        let mut baseline =
+            opts.left_top_coord.y + (opts.height - text_height) / 2. + font.ascent() as f64;
+        // Other binders size text by its height, PDF by the em square.
+        let em_size = match self.metrics.units_per_em() {
+            Some(units_per_em) => {
+                opts.font_size * units_per_em as f64 / self.metrics.height_unscaled() as f64
+            }
+            None => opts.font_size,
+        };
+        self.layer.set_fill_color(color(opts.font_color));
+        self.layer.set_character_spacing(opts.font_space as f32);
+        for line in lines {
+            let width = self.text_width(line, opts.font_size, opts.font_space as f64);
+            let x = opts.left_top_coord.x + (opts.width - width) / 2.;
+            let origin = self.point(x, baseline);
+            self.layer.use_text(
+                line,
+                em_size as f32,
+                origin.x.into(),
+                origin.y.into(),
+                &self.font,
+            );
+            baseline += line_height;
+        }
+        self.layer.set_character_spacing(0.);
+    }
+
+    fn draw_path(&self, opts: DrawPathOpts) {
+        if opts.coords.len() < 2 {
+            return;
+        }
+        let ring: Vec<(Point, bool)> = opts
+            .coords
+            .iter()
+            .map(|c| (self.point(c.x, c.y), false))
+            .collect();
+        let stroke = opts.line_size > 0.;
+        if stroke {
+            self.set_stroke(opts.line_size, opts.line_color, false);
+        }
+        match (opts.closed, opts.fill_color) {
+            (true, _) | (_, Some(_)) => self.paint(vec![ring], stroke, opts.fill_color),
+            _ if stroke => self.layer.add_line(Line {
+                points: ring,
+                is_closed: false,
+            }),
+            _ => {}
+        }
+    }
+
+    fn draw_image(&self, opts: DrawImageOpts) {
+        let image = opts
+            .data
+            .strip_prefix("data:")
+            .and_then(|data| data.split_once(";base64,"))
+            .and_then(|(_, data)| base64::engine::general_purpose::STANDARD.decode(data).ok())
+            .and_then(|data| image_crate::load_from_memory(&data).ok());
+        let image = match image {
+            Some(image) => image,
+            // Formats the image crate can't read get the placeholder.
+            None => {
+                return self.draw_rect(DrawRectOpts {
+                    left_top_coord: opts.left_top_coord,
+                    width: opts.width,
+                    height: opts.height,
+                    line_size: 1.,
+                    line_color: 0x999999,
+                    fill_color: Some(0xeeeeee),
+                    line_style: LineStyle::Solid,
+                })
+            }
+        };
+        let (width, height) = (image.width() as f64, image.height() as f64);
+        if width == 0. || height == 0. {
+            return;
+        }
+        let bottom_left = self.point(opts.left_top_coord.x, opts.left_top_coord.y + opts.height);
+        // At 72 dpi one image pixel is one point.
+        Image::from_dynamic_image(&image).add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(bottom_left.x.into()),
+                translate_y: Some(bottom_left.y.into()),
+                scale_x: Some((opts.width / width) as f32),
+                scale_y: Some((opts.height / height) as f32),
+                dpi: Some(72.),
+                ..ImageTransform::default()
+            },
+        );
+    }
+
+    fn draw_end(&self) -> Box<dyn std::any::Any> {
+        Box::new(())
+    }
+}
+
+fn color(color: u32) -> Color {
+    Color::Rgb(Rgb::new(
+        ((color >> 16) & 0xff) as f32 / 255.,
+        ((color >> 8) & 0xff) as f32 / 255.,
+        (color & 0xff) as f32 / 255.,
+        None,
+    ))
+}
+
+pub trait RenderPdf {
+    /// Writes the board as a vector PDF. Text stays selectable, set in the
+    /// embedded font. The view of any bound binder is left untouched.
+    fn render_pdf(&self, opts: &PdfExportOpts) -> Result<Vec<u8>, PdfExportError>;
+}
+
+impl RenderPdf for Drawpanel {
+    fn render_pdf(&self, opts: &PdfExportOpts) -> Result<Vec<u8>, PdfExportError> {
+        let panel = self.panel().upgrade().unwrap();
+        let panel = panel.borrow();
+
+        let (mut page_w, mut page_h) = opts.page_size.mm();
+        if let Orientation::Landscape = opts.orientation {
+            std::mem::swap(&mut page_w, &mut page_h);
+        }
+        let margin = Mm(opts.margin as f32).into_pt().0 as f64;
+        let page_w_pt = Mm(page_w as f32).into_pt().0 as f64;
+        let page_h_pt = Mm(page_h as f32).into_pt().0 as f64;
+        let print_w = (page_w_pt - margin * 2.).max(1.);
+        let print_h = (page_h_pt - margin * 2.).max(1.);
+
+        let all: Vec<usize> = (0..panel.elems.len()).collect();
+        // An infinite canvas has no page, so the content is all there is.
+        let (page, fit_content) = match panel.page_rect() {
+            Some(page) => (page, opts.fit_content),
+            None => (
+                geo::Rect::new(coord! { x: 0., y: 0. }, coord! { x: 0., y: 0. }),
+                true,
+            ),
+        };
+        let area = match (panel.bounds(&all), fit_content) {
+            (Some(bounds), true) => bounds,
+            (Some(bounds), false) => geo::Rect::new(
+                coord! {
+                    x: page.min().x.min(bounds.min().x),
+                    y: page.min().y.min(bounds.min().y),
+                },
+                coord! {
+                    x: page.max().x.max(bounds.max().x),
+                    y: page.max().y.max(bounds.max().y),
+                },
+            ),
+            (None, _) => page,
+        };
+        let area_w = area.width().max(1.);
+        let area_h = area.height().max(1.);
+
+        let (scale, cols, rows, offset) = match opts.paging {
+            PdfPaging::Fit => {
+                let scale = (print_w / area_w).min(print_h / area_h);
+                // Centered on the page.
+                let offset = coord! {
+                    x: (print_w - area_w * scale) / 2.,
+                    y: (print_h - area_h * scale) / 2.,
+                };
+                (scale, 1, 1, offset)
+            }
+            PdfPaging::Tile { scale } => {
+                let scale = scale * PT_PER_UNIT;
+                let cols = (area_w * scale / print_w).ceil().max(1.) as usize;
+                let rows = (area_h * scale / print_h).ceil().max(1.) as usize;
+                (scale, cols, rows, coord! { x: 0., y: 0. })
+            }
+        };
+
+        let (doc, page_index, layer_index) =
+            PdfDocument::new(&opts.title, Mm(page_w as f32), Mm(page_h as f32), "Layer 1");
+        let font_data = opts.font.as_deref().unwrap_or(DEFAULT_FONT);
+        let metrics =
+            FontArc::try_from_vec(font_data.to_vec()).map_err(|_| PdfExportError::InvalidFont)?;
+        let font = doc.add_external_font(font_data)?;
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let layer = if row == 0 && col == 0 {
+                    doc.get_page(page_index).get_layer(layer_index)
+                } else {
+                    let (page_index, layer_index) =
+                        doc.add_page(Mm(page_w as f32), Mm(page_h as f32), "Layer 1");
+                    doc.get_page(page_index).get_layer(layer_index)
+                };
+
+                // Keeps each tile inside its margins.
+                layer.save_graphics_state();
+                layer.add_polygon(Polygon {
+                    rings: vec![vec![
+                        (
+                            Point {
+                                x: Pt(margin as f32),
+                                y: Pt(margin as f32),
+                            },
+                            false,
+                        ),
+                        (
+                            Point {
+                                x: Pt((margin + print_w) as f32),
+                                y: Pt(margin as f32),
+                            },
+                            false,
+                        ),
+                        (
+                            Point {
+                                x: Pt((margin + print_w) as f32),
+                                y: Pt((margin + print_h) as f32),
+                            },
+                            false,
+                        ),
+                        (
+                            Point {
+                                x: Pt(margin as f32),
+                                y: Pt((margin + print_h) as f32),
+                            },
+                            false,
+                        ),
+                    ]],
+                    mode: PaintMode::Clip,
+                    winding_order: WindingOrder::NonZero,
+                });
+
+                let lt_coord = coord! {
+                    x: margin + offset.x - col as f64 * print_w - area.min().x * scale,
+                    y: margin + offset.y - row as f64 * print_h - area.min().y * scale,
+                };
+                let draw: Box<dyn IDraw> = Box::new(PdfDraw::new(
+                    layer.clone(),
+                    page_h_pt,
+                    font.clone(),
+                    metrics.clone(),
+                ));
+                panel.render(&draw, Box::new(()), lt_coord, scale);
+                layer.restore_graphics_state();
+            }
+        }
+
+        Ok(doc.save_to_bytes()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drawpanel_core::{panel::ImportMode, recording::RecordingBinder};
+    use printpdf::lopdf;
+
+    use super::*;
+
+    fn board() -> Drawpanel {
+        let mut drawpanel = Drawpanel::new(RecordingBinder::new(400., 300.));
+        drawpanel
+            .import_svg(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="2000" height="1000">
+                    <rect x="0" y="0" width="2000" height="1000" fill="#1e90ff"/>
+                    <text x="100" y="100" font-size="16">Ab</text>
+                </svg>"##,
+                ImportMode::Replace,
+            )
+            .unwrap();
+        drawpanel
+    }
+
+    fn pages(pdf: &[u8]) -> usize {
+        lopdf::Document::load_mem(pdf).unwrap().get_pages().len()
+    }
+
+    #[test]
+    fn it_fits_the_board_on_one_page() {
+        let pdf = board().render_pdf(&PdfExportOpts::default()).unwrap();
+        assert_eq!(pages(&pdf), 1);
+    }
+
+    #[test]
+    fn it_tiles_the_board_across_pages() {
+        let pdf = board()
+            .render_pdf(&PdfExportOpts {
+                fit_content: true,
+                paging: PdfPaging::Tile { scale: 1. },
+                ..Default::default()
+            })
+            .unwrap();
+        // 1500pt by 750pt over A4 portrait: three columns, one row.
+        assert_eq!(pages(&pdf), 3);
+    }
+
+    #[test]
+    fn it_refuses_an_invalid_font() {
+        let result = board().render_pdf(&PdfExportOpts {
+            font: Some(vec![0; 16]),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(PdfExportError::InvalidFont)));
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    rc::Weak,
+};
+
+use ab_glyph::{Font, FontArc, InvalidFont, PxScale, ScaleFont};
+use base64::Engine;
+use drawpanel_core::{
+    binder::{
+        Binder, Draw, DrawCircleOpts, DrawImageOpts, DrawLineOpts, DrawPathOpts, DrawRectOpts,
+        DrawTextOpts, HookEvent, IDraw, IHookEvent,
+    },
+    drawpanel::Drawpanel,
+    elem::rect::LineStyle,
+    panel::Panel,
+};
+use geo::coord;
+use tiny_skia::{
+    Color, FillRule, FilterQuality, LineCap, LineJoin, Paint, PathBuilder, Pixmap, PixmapPaint,
+    PremultipliedColorU8, Stroke, StrokeDash, Transform,
+};
+
+static DEFAULT_FONT: &[u8] = include_bytes!("../assets/SmileySans.ttf");
+
+pub fn default_font() -> FontArc {
+    FontArc::try_from_slice(DEFAULT_FONT).unwrap()
+}
+
+/// A binder without any window. Every `trigger_draw` paints into a fresh
+/// `tiny_skia::Pixmap`, which `draw_end` hands back to the caller.
+#[derive(Debug, Clone)]
+pub struct RasterBinder {
+    width: u32,
+    height: u32,
+    font: FontArc,
+}
+
+impl RasterBinder {
+    pub fn new(width: u32, height: u32) -> Self {
+        RasterBinder {
+            width,
+            height,
+            font: default_font(),
+        }
+    }
+
+    /// Replaces the bundled SmileySans font used by `draw_text`.
+    pub fn with_font(mut self, data: Vec<u8>) -> Result<Self, InvalidFont> {
+        self.font = FontArc::try_from_vec(data)?;
+        Ok(self)
+    }
+}
+
+impl Binder for RasterBinder {
+    fn init(&mut self, _panel: Weak<RefCell<Panel>>) {}
+
+    fn draw(&self, _panel: Weak<RefCell<Panel>>) -> Box<dyn IDraw> {
+        Box::new(RasterDraw::new(self.width, self.height, self.font.clone()))
+    }
+
+    fn hook_event(&self) -> Box<dyn IHookEvent> {
+        Box::new(RasterHookEvent)
+    }
+
+    fn region(&self) -> geo::Rect<f64> {
+        geo::Rect::new(
+            coord!(x: 0.0, y: 0.0),
+            coord!(x: self.width as f64, y: self.height as f64),
+        )
+    }
+}
+
+pub struct RasterDraw {
+    width: u32,
+    height: u32,
+    font: FontArc,
+    pixmap: RefCell<Option<Pixmap>>,
+    opacity: Cell<f32>,
+}
+
+impl RasterDraw {
+    pub fn new(width: u32, height: u32, font: FontArc) -> Self {
+        RasterDraw {
+            width,
+            height,
+            font,
+            pixmap: RefCell::new(None),
+            opacity: Cell::new(1.),
+        }
+    }
+
+    fn stroke_path(&self, path: &tiny_skia::Path, line_size: f64, color: u32, dash: bool) {
+        if line_size <= 0. {
+            return;
+        }
+        let mut pixmap = self.pixmap.borrow_mut();
+        if let Some(pixmap) = pixmap.as_mut() {
+            let stroke = Stroke {
+                width: line_size as f32,
+                line_cap: LineCap::Round,
+                line_join: LineJoin::Round,
+                dash: if dash {
+                    StrokeDash::new(vec![line_size as f32, line_size as f32 * 2.], 0.)
+                } else {
+                    None
+                },
+                ..Stroke::default()
+            };
+            let paint = paint(color, self.opacity.get());
+            pixmap.stroke_path(path, &paint, &stroke, Transform::identity(), None);
+        }
+    }
+
+    fn fill_path(&self, path: &tiny_skia::Path, color: u32) {
+        let mut pixmap = self.pixmap.borrow_mut();
+        if let Some(pixmap) = pixmap.as_mut() {
+            pixmap.fill_path(
+                path,
+                &paint(color, self.opacity.get()),
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+    }
+}
+
+impl Debug for RasterDraw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RasterDraw")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl IDraw for RasterDraw {}
+
+impl Draw for RasterDraw {
+    fn draw_begin(&self, _ctx: Box<dyn std::any::Any>) {
+        let mut pixmap = self.pixmap.borrow_mut();
+        *pixmap = Pixmap::new(self.width.max(1), self.height.max(1));
+        self.opacity.set(1.);
+    }
+
+    fn draw_line(&self, opts: DrawLineOpts) {
+        let mut pb = PathBuilder::new();
+        pb.move_to(opts.from_coord.x as f32, opts.from_coord.y as f32);
+        pb.line_to(opts.end_coord.x as f32, opts.end_coord.y as f32);
+        if let Some(path) = pb.finish() {
+            self.stroke_path(&path, opts.line_size, opts.line_color, false);
+        }
+    }
+
+    fn draw_rect(&self, opts: DrawRectOpts) {
+        let rect = tiny_skia::Rect::from_xywh(
+            opts.left_top_coord.x as f32,
+            opts.left_top_coord.y as f32,
+            opts.width as f32,
+            opts.height as f32,
+        );
+        if let Some(rect) = rect {
+            let path = PathBuilder::from_rect(rect);
+            if let Some(fill_color) = opts.fill_color {
+                self.fill_path(&path, fill_color);
+            }
+            let dash = matches!(opts.line_style, LineStyle::Dotted);
+            self.stroke_path(&path, opts.line_size, opts.line_color, dash);
+        }
+    }
+
+    fn draw_circle(&self, opts: DrawCircleOpts) {
+        let path = PathBuilder::from_circle(
+            opts.center_coord.x as f32,
+            opts.center_coord.y as f32,
+            opts.r as f32,
+        );
+        if let Some(path) = path {
+            self.fill_path(&path, opts.fill_color);
+            self.stroke_path(&path, opts.line_size, opts.line_color, false);
+        }
+    }
+
+    fn draw_text(&self, opts: DrawTextOpts) {
+        let mut pixmap = self.pixmap.borrow_mut();
+        let pixmap = match pixmap.as_mut() {
+            Some(pixmap) => pixmap,
+            None => return,
+        };
+        let font = self.font.as_scaled(PxScale::from(opts.font_size as f32));
+        let line_height = font.height() + font.line_gap();
+        let lines: Vec<&str> = opts.content.split('\n').collect();
+
+        // Centered in the box, like the fltk and egui binders.
+        let text_height = line_height * lines.len() as f32;
+        let mut baseline =
+            opts.left_top_coord.y as f32 + (opts.height as f32 - text_height) / 2. + font.ascent();
+        for line in lines {
+            let mut width = 0.;
+            let mut prev = None;
+            for c in line.chars() {
+                let id = font.glyph_id(c);
+                if let Some(prev) = prev {
+                    width += font.kern(prev, id);
+                }
+                width += font.h_advance(id) + opts.font_space as f32;
+                prev = Some(id);
+            }
+            let mut x = opts.left_top_coord.x as f32 + (opts.width as f32 - width) / 2.;
+            let mut prev = None;
+            for c in line.chars() {
+                let id = font.glyph_id(c);
+                if let Some(prev) = prev {
+                    x += font.kern(prev, id);
+                }
+                let glyph = id.with_scale_and_position(font.scale(), ab_glyph::point(x, baseline));
+                if let Some(outlined) = font.outline_glyph(glyph) {
+                    let bounds = outlined.px_bounds();
+                    outlined.draw(|gx, gy, coverage| {
+                        blend_pixel(
+                            pixmap,
+                            bounds.min.x as i32 + gx as i32,
+                            bounds.min.y as i32 + gy as i32,
+                            opts.font_color,
+                            coverage * self.opacity.get(),
+                        );
+                    });
+                }
+                x += font.h_advance(id) + opts.font_space as f32;
+                prev = Some(id);
+            }
+            baseline += line_height;
+        }
+    }
+
+    fn draw_path(&self, opts: DrawPathOpts) {
+        let mut pb = PathBuilder::new();
+        for (i, coord) in opts.coords.iter().enumerate() {
+            if i == 0 {
+                pb.move_to(coord.x as f32, coord.y as f32);
+            } else {
+                pb.line_to(coord.x as f32, coord.y as f32);
+            }
+        }
+        if opts.closed {
+            pb.close();
+        }
+        if let Some(path) = pb.finish() {
+            if let Some(fill_color) = opts.fill_color {
+                self.fill_path(&path, fill_color);
+            }
+            self.stroke_path(&path, opts.line_size, opts.line_color, false);
+        }
+    }
+
+    fn draw_image(&self, opts: DrawImageOpts) {
+        let image = opts
+            .data
+            .strip_prefix("data:image/png;base64,")
+            .and_then(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+            .and_then(|data| Pixmap::decode_png(&data).ok());
+        let image = match image {
+            Some(image) => image,
+            // Only PNG can be decoded; anything else gets the placeholder.
+            None => {
+                return self.draw_rect(DrawRectOpts {
+                    left_top_coord: opts.left_top_coord,
+                    width: opts.width,
+                    height: opts.height,
+                    line_size: 1.,
+                    line_color: 0x999999,
+                    fill_color: Some(0xeeeeee),
+                    line_style: LineStyle::Solid,
+                })
+            }
+        };
+        let mut pixmap = self.pixmap.borrow_mut();
+        if let Some(pixmap) = pixmap.as_mut() {
+            let transform = Transform::from_row(
+                opts.width as f32 / image.width() as f32,
+                0.,
+                0.,
+                opts.height as f32 / image.height() as f32,
+                opts.left_top_coord.x as f32,
+                opts.left_top_coord.y as f32,
+            );
+            pixmap.draw_pixmap(
+                0,
+                0,
+                image.as_ref(),
+                &PixmapPaint {
+                    quality: FilterQuality::Bilinear,
+                    opacity: self.opacity.get(),
+                    ..PixmapPaint::default()
+                },
+                transform,
+                None,
+            );
+        }
+    }
+
+    fn set_opacity(&self, opacity: f64) {
+        self.opacity.set(opacity.clamp(0., 1.) as f32);
+    }
+
+    fn draw_end(&self) -> Box<dyn std::any::Any> {
+        match self.pixmap.borrow_mut().take() {
+            Some(pixmap) => Box::new(pixmap),
+            None => Box::new(()),
+        }
+    }
+}
+
+fn color(color: u32) -> Color {
+    Color::from_rgba8(
+        ((color >> 16) & 0xff) as u8,
+        ((color >> 8) & 0xff) as u8,
+        (color & 0xff) as u8,
+        0xff,
+    )
+}
+
+fn paint(c: u32, opacity: f32) -> Paint<'static> {
+    let mut paint = Paint::default();
+    let mut color = color(c);
+    color.apply_opacity(opacity);
+    paint.set_color(color);
+    paint.anti_alias = true;
+    paint
+}
+
+fn blend_pixel(pixmap: &mut Pixmap, x: i32, y: i32, c: u32, coverage: f32) {
+    if x < 0 || y < 0 || x >= pixmap.width() as i32 || y >= pixmap.height() as i32 {
+        return;
+    }
+    let index = (y as u32 * pixmap.width() + x as u32) as usize;
+    let dst = pixmap.pixels()[index];
+    let src = color(c).premultiply().to_color_u8();
+    let a = coverage.clamp(0., 1.);
+    let mix = |s: u8, d: u8| (s as f32 * a + d as f32 * (1. - a)).round() as u8;
+    let alpha = mix(src.alpha(), dst.alpha());
+    let pixel = PremultipliedColorU8::from_rgba(
+        mix(src.red(), dst.red()).min(alpha),
+        mix(src.green(), dst.green()).min(alpha),
+        mix(src.blue(), dst.blue()).min(alpha),
+        alpha,
+    );
+    if let Some(pixel) = pixel {
+        pixmap.pixels_mut()[index] = pixel;
+    }
+}
+
+#[derive(Debug)]
+struct RasterHookEvent;
+
+impl IHookEvent for RasterHookEvent {}
+
+impl HookEvent for RasterHookEvent {}
+
+pub trait RenderPng {
+    /// Renders the `width`×`height` page at `scale` pixels per unit and
+    /// encodes it as PNG. The view of any bound binder is left untouched.
+    /// An empty page gives a single transparent pixel.
+    fn render_png(&self, width: u32, height: u32, scale: f64) -> Vec<u8> {
+        self.render_png_with_font(width, height, scale, default_font())
+    }
+
+    /// Like `render_png`, but draws text with `font`.
+    fn render_png_with_font(&self, width: u32, height: u32, scale: f64, font: FontArc) -> Vec<u8>;
+}
+
+impl RenderPng for Drawpanel {
+    fn render_png_with_font(&self, width: u32, height: u32, scale: f64, font: FontArc) -> Vec<u8> {
+        let panel = self.panel().upgrade().unwrap();
+        let panel = panel.borrow();
+        let draw: Box<dyn IDraw> = Box::new(RasterDraw::new(
+            (width as f64 * scale).ceil() as u32,
+            (height as f64 * scale).ceil() as u32,
+            font,
+        ));
+        let pixmap: Box<Pixmap> = panel
+            .render(&draw, Box::new(()), coord! { x: 0., y: 0. }, scale)
+            .downcast()
+            .unwrap();
+        pixmap.encode_png().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drawpanel_core::panel::ImportMode;
+
+    use super::*;
+
+    static SNAPSHOT: &[u8] = include_bytes!("../assets/snapshots/shapes.png");
+
+    fn shapes() -> Drawpanel {
+        let mut drawpanel = Drawpanel::new(RasterBinder::new(80, 60));
+        drawpanel
+            .import_svg(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="80" height="60">
+                    <rect x="10" y="10" width="40" height="30" fill="#1e90ff" stroke="#333333" stroke-width="2"/>
+                    <line x1="10" y1="50" x2="70" y2="50" stroke="#ff0000" stroke-width="3"/>
+                    <text x="55" y="30" font-size="16" fill="#000000">Ab</text>
+                </svg>"##,
+                ImportMode::Replace,
+            )
+            .unwrap();
+        drawpanel
+    }
+
+    #[test]
+    fn it_matches_the_png_snapshot() {
+        let png = shapes().render_png(80, 60, 1.);
+        // To update, write `png` over assets/snapshots/shapes.png.
+        let actual = Pixmap::decode_png(&png).unwrap();
+        let expected = Pixmap::decode_png(SNAPSHOT).unwrap();
+        assert_eq!((actual.width(), actual.height()), (80, 60));
+        assert!(actual.data() == expected.data());
+    }
+
+    #[test]
+    fn it_renders_an_empty_page() {
+        let png = shapes().render_png(0, 0, 1.);
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (1, 1));
+    }
+
+    #[test]
+    fn it_refuses_an_invalid_font() {
+        assert!(RasterBinder::new(10, 10).with_font(vec![0; 16]).is_err());
+        let font = FontArc::try_from_slice(DEFAULT_FONT).unwrap();
+        assert_eq!(
+            shapes().render_png_with_font(80, 60, 1., font),
+            shapes().render_png(80, 60, 1.)
+        );
+    }
+}
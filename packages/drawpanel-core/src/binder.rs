@@ -2,21 +2,25 @@ use std::{any::Any, fmt::Debug, rc::Weak};
 use std::{cell::RefCell, rc::Rc};
 
 use geo::Coordinate;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     drawpanel::Drawpanel,
     elem::{rect::LineStyle, Elem, IElem},
+    gesture::TouchId,
     panel::Panel,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum EventZoom {
     None,
     Grow,
     Dwindle,
+    /// Multiplies the scale, e.g. for a touchpad pinch.
+    Factor(f64),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum EventMouseButton {
     None,
     Left,
@@ -24,7 +28,19 @@ pub enum EventMouseButton {
     Right,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EventKey {
+    Escape,
+    Delete,
+    Backspace,
+    Enter,
+    Space,
+    Shift,
+    Ctrl,
+    Char(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum EventType {
     Move(EventMouseButton),
     Push(EventMouseButton), // Click, Dblclick, Mouseup(Left\Right\Mid), Mousedown
@@ -32,6 +48,12 @@ pub enum EventType {
     Released(EventMouseButton),
     Drag(EventMouseButton),
     Zoom(EventZoom),
+    KeyDown(EventKey),
+    KeyUp(EventKey),
+    /// Finger events, see `GestureRecognizer`.
+    TouchStart(TouchId),
+    TouchMove(TouchId),
+    TouchEnd(TouchId),
 }
 
 pub struct DrawLineOpts {
@@ -59,6 +81,22 @@ pub struct DrawCircleOpts {
     pub fill_color: u32,
 }
 
+pub struct DrawPathOpts<'a> {
+    pub coords: &'a [Coordinate],
+    pub line_size: f64,
+    pub line_color: u32,
+    pub fill_color: Option<u32>,
+    pub closed: bool,
+}
+
+pub struct DrawImageOpts<'a> {
+    pub left_top_coord: Coordinate,
+    pub width: f64,
+    pub height: f64,
+    /// A data URI such as `data:image/png;base64,...`, or a plain URL.
+    pub data: &'a str,
+}
+
 pub struct DrawTextOpts<'a> {
     pub left_top_coord: Coordinate,
     pub width: f64,
@@ -84,11 +122,86 @@ pub trait Draw {
     fn draw_rect(&self, opts: DrawRectOpts);
     fn draw_circle(&self, opts: DrawCircleOpts);
     fn draw_text(&self, opts: DrawTextOpts);
+    /// Binders without a native polyline fall back to one line per segment.
+    fn draw_path(&self, opts: DrawPathOpts) {
+        let mut segments: Vec<(&Coordinate, &Coordinate)> =
+            opts.coords.iter().zip(opts.coords.iter().skip(1)).collect();
+        if opts.closed && opts.coords.len() > 2 {
+            segments.push((opts.coords.last().unwrap(), opts.coords.first().unwrap()));
+        }
+        for (from_coord, end_coord) in segments {
+            self.draw_line(DrawLineOpts {
+                from_coord: *from_coord,
+                end_coord: *end_coord,
+                line_size: opts.line_size,
+                line_color: opts.line_color,
+            });
+        }
+    }
+    /// Binders that cannot decode images draw a grey placeholder frame.
+    fn draw_image(&self, opts: DrawImageOpts) {
+        self.draw_rect(DrawRectOpts {
+            left_top_coord: opts.left_top_coord,
+            width: opts.width,
+            height: opts.height,
+            line_size: 1.,
+            line_color: 0x999999,
+            fill_color: Some(0xeeeeee),
+            line_style: LineStyle::Solid,
+        });
+    }
+    /// Applies to everything drawn until the next call, from 0
+    /// (transparent) to 1. Binders that can't blend ignore it.
+    fn set_opacity(&self, _opacity: f64) {}
     fn draw_end(&self) -> Box<dyn std::any::Any> {
         Box::new(())
     }
 }
 
+/// The mouse cursor the app should show over the panel, see
+/// `HookEvent::set_cursor`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Cursor {
+    #[default]
+    Default,
+    Move,
+    ResizeNorth,
+    ResizeNorthEast,
+    ResizeEast,
+    ResizeSouthEast,
+    ResizeSouth,
+    ResizeSouthWest,
+    ResizeWest,
+    ResizeNorthWest,
+    Crosshair,
+    Text,
+    NotAllowed,
+    Grab,
+    Grabbing,
+}
+
+impl Cursor {
+    /// The resize cursor pointing along `offset`, e.g. from the middle of
+    /// an element to the vertex being dragged.
+    pub fn resize_towards(offset: Coordinate) -> Cursor {
+        if offset.x == 0. && offset.y == 0. {
+            return Cursor::Move;
+        }
+        // Binder coordinates grow downwards, so a positive angle is south.
+        let octant = (offset.y.atan2(offset.x) / std::f64::consts::FRAC_PI_4).round() as i64;
+        match octant.rem_euclid(8) {
+            0 => Cursor::ResizeEast,
+            1 => Cursor::ResizeSouthEast,
+            2 => Cursor::ResizeSouth,
+            3 => Cursor::ResizeSouthWest,
+            4 => Cursor::ResizeWest,
+            5 => Cursor::ResizeNorthWest,
+            6 => Cursor::ResizeNorth,
+            _ => Cursor::ResizeNorthEast,
+        }
+    }
+}
+
 pub trait IHookEvent: HookEvent + Debug {}
 
 pub trait HookEvent {
@@ -122,6 +235,46 @@ pub trait HookEvent {
     ) {
     }
     fn flush(&mut self) {}
+    /// When the cursor over the panel should change, e.g. to a resize
+    /// cursor over a handle.
+    fn set_cursor(&mut self, _cursor: Cursor) {}
+
+    // The `before_*` hooks run before the panel changes an element. Returning
+    // false cancels the action and leaves the element as it is.
+
+    /// Before `elem` is added at `mouse_coord`. The panel stays in creating
+    /// mode when refused.
+    fn before_create(&mut self, _elem: &Box<dyn IElem>, _mouse_coord: Coordinate) -> bool {
+        true
+    }
+    /// Once for every element about to be deleted.
+    fn before_delete(&mut self, _elem: &Box<dyn IElem>) -> bool {
+        true
+    }
+    /// On every drag step. When several selected elements move together,
+    /// one refusal keeps all of them in place.
+    fn before_move(
+        &mut self,
+        _elem: &Box<dyn IElem>,
+        _from_coord: Coordinate,
+        _end_coord: Coordinate,
+    ) -> bool {
+        true
+    }
+    /// On every drag step of the vertex at index `vertex`.
+    fn before_resize(
+        &mut self,
+        _elem: &Box<dyn IElem>,
+        _vertex: u8,
+        _from_coord: Coordinate,
+        _end_coord: Coordinate,
+    ) -> bool {
+        true
+    }
+    /// Before the binder's input opens on `elem`.
+    fn before_edit_state(&mut self, _elem: &Box<dyn IElem>) -> bool {
+        true
+    }
 
     fn get_state(&self) -> Box<dyn Any> {
         Box::new(())
@@ -0,0 +1,268 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use geo::Coordinate;
+
+use crate::{elem::ElemId, panel::Panel, session::SessionMode};
+
+/// Something that changed on the panel, see `Drawpanel::subscribe`.
+/// Elements are given in their `type(content)` form, which
+/// `Panel::parse_elem` turns back into an element.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    ElemAdded {
+        id: ElemId,
+        elem: String,
+    },
+    ElemRemoved {
+        id: ElemId,
+        elem: String,
+    },
+    /// The bounding rect moved without changing its size.
+    ElemMoved {
+        id: ElemId,
+        before: geo::Rect,
+        after: geo::Rect,
+    },
+    ElemResized {
+        id: ElemId,
+        before: geo::Rect,
+        after: geo::Rect,
+    },
+    /// Any other change to an element: colors, line style, text content.
+    ElemRestyled {
+        id: ElemId,
+        before: String,
+        after: String,
+    },
+    /// The paint order changed, `after` lists the ids from back to front.
+    OrderChanged {
+        before: Vec<ElemId>,
+        after: Vec<ElemId>,
+    },
+    /// Selected elements, sorted by id.
+    SelectionChanged {
+        before: Vec<ElemId>,
+        after: Vec<ElemId>,
+    },
+    ModeChanged {
+        before: SessionMode,
+        after: SessionMode,
+    },
+    ViewportChanged {
+        before: ViewState,
+        after: ViewState,
+    },
+}
+
+/// Position and zoom of the panel, as in `Viewport::lt_coord` and
+/// `Viewport::scale`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewState {
+    pub lt_coord: Coordinate,
+    pub scale: f64,
+}
+
+pub type ChangeObserver = Box<dyn FnMut(&Change)>;
+
+/// Compares the panel against how it looked at the last check. Only does
+/// any work while someone is subscribed.
+#[derive(Default)]
+pub struct ChangeTracker {
+    observers: Vec<(usize, ChangeObserver)>,
+    next_observer: usize,
+    last: Option<Snapshot>,
+    /// Set while a mouse button is down, so a drag is reported once on
+    /// release instead of on every step.
+    pub(crate) held: bool,
+}
+
+impl Debug for ChangeTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChangeTracker")
+            .field("observers", &self.observers.len())
+            .field("held", &self.held)
+            .finish()
+    }
+}
+
+impl ChangeTracker {
+    pub fn subscribe(&mut self, panel: &Panel, observer: ChangeObserver) -> usize {
+        if self.observers.is_empty() {
+            self.last = Some(Snapshot::new(panel));
+        }
+        let id = self.next_observer;
+        self.next_observer += 1;
+        self.observers.push((id, observer));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: usize) {
+        self.observers.retain(|(i, _)| *i != id);
+        if self.observers.is_empty() {
+            self.last = None;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.observers.is_empty()
+    }
+
+    /// Takes a new snapshot of `panel` and sends what differs from the
+    /// previous one to the observers.
+    pub fn check(&mut self, panel: &Panel) {
+        if !self.is_active() {
+            return;
+        }
+        let snapshot = Snapshot::new(panel);
+        let changes = match &self.last {
+            Some(last) => last.diff(&snapshot),
+            None => vec![],
+        };
+        self.last = Some(snapshot);
+        for change in changes.iter() {
+            for (_, observer) in self.observers.iter_mut() {
+                observer(change);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    elems: Vec<(ElemId, String, Option<geo::Rect>)>,
+    selects: Vec<ElemId>,
+    mode: SessionMode,
+    view: ViewState,
+}
+
+impl Snapshot {
+    fn new(panel: &Panel) -> Self {
+        let elems = panel
+            .elems
+            .iter()
+            .zip(panel.metas.iter())
+            .map(|(elem, meta)| {
+                (
+                    meta.id,
+                    format!("{}({})", elem.elem_type(), elem.export()),
+                    elem.bounding_rect(),
+                )
+            })
+            .collect();
+        let mut selects: Vec<ElemId> = panel
+            .selects
+            .iter()
+            .filter_map(|i| panel.elem_id(*i as usize))
+            .collect();
+        selects.sort();
+        Snapshot {
+            elems,
+            selects,
+            mode: panel.mode(),
+            view: ViewState {
+                lt_coord: panel.viewport.lt_coord,
+                scale: panel.viewport.scale,
+            },
+        }
+    }
+
+    fn diff(&self, after: &Snapshot) -> Vec<Change> {
+        let mut changes = vec![];
+        let before_map: BTreeMap<ElemId, &(ElemId, String, Option<geo::Rect>)> =
+            self.elems.iter().map(|e| (e.0, e)).collect();
+        let after_map: BTreeMap<ElemId, &(ElemId, String, Option<geo::Rect>)> =
+            after.elems.iter().map(|e| (e.0, e)).collect();
+
+        for (id, elem, _) in self.elems.iter() {
+            if !after_map.contains_key(id) {
+                changes.push(Change::ElemRemoved {
+                    id: *id,
+                    elem: elem.clone(),
+                });
+            }
+        }
+        for (id, elem, _) in after.elems.iter() {
+            if !before_map.contains_key(id) {
+                changes.push(Change::ElemAdded {
+                    id: *id,
+                    elem: elem.clone(),
+                });
+            }
+        }
+        for (id, elem, rect) in after.elems.iter() {
+            let (_, before_elem, before_rect) = match before_map.get(id) {
+                Some(before) => before,
+                None => continue,
+            };
+            if before_elem == elem {
+                continue;
+            }
+            let change = match (before_rect, rect) {
+                (Some(before), Some(after)) if before != after => {
+                    let same_size = (before.width() - after.width()).abs() < 1e-9
+                        && (before.height() - after.height()).abs() < 1e-9;
+                    if same_size {
+                        Change::ElemMoved {
+                            id: *id,
+                            before: *before,
+                            after: *after,
+                        }
+                    } else {
+                        Change::ElemResized {
+                            id: *id,
+                            before: *before,
+                            after: *after,
+                        }
+                    }
+                }
+                _ => Change::ElemRestyled {
+                    id: *id,
+                    before: before_elem.clone(),
+                    after: elem.clone(),
+                },
+            };
+            changes.push(change);
+        }
+
+        // Only elements on both sides count, additions and removals are
+        // reported above.
+        let before_order: Vec<ElemId> = self
+            .elems
+            .iter()
+            .map(|e| e.0)
+            .filter(|id| after_map.contains_key(id))
+            .collect();
+        let after_order: Vec<ElemId> = after
+            .elems
+            .iter()
+            .map(|e| e.0)
+            .filter(|id| before_map.contains_key(id))
+            .collect();
+        if before_order != after_order {
+            changes.push(Change::OrderChanged {
+                before: before_order,
+                after: after_order,
+            });
+        }
+
+        if self.selects != after.selects {
+            changes.push(Change::SelectionChanged {
+                before: self.selects.clone(),
+                after: after.selects.clone(),
+            });
+        }
+        if self.mode != after.mode {
+            changes.push(Change::ModeChanged {
+                before: self.mode.clone(),
+                after: after.mode.clone(),
+            });
+        }
+        if self.view != after.view {
+            changes.push(Change::ViewportChanged {
+                before: self.view,
+                after: after.view,
+            });
+        }
+        changes
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// What `Drawpanel::copy` puts on the clipboard. Binders can store
+/// `to_json` on the system clipboard under `MIME_TYPE`, and `svg` as
+/// `image/svg+xml` for other applications.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipboardPayload {
+    /// The copied elements as a document, see `Panel::export`.
+    pub document: String,
+    /// The copied elements rendered as SVG, cropped to their bounds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub svg: Option<String>,
+}
+
+impl ClipboardPayload {
+    pub const MIME_TYPE: &'static str = "application/x-drawpanel";
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Fails for anything that didn't come from `to_json`, e.g. plain text
+    /// on the system clipboard.
+    pub fn from_json(data: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(data)
+    }
+}
+
+#[derive(Debug)]
+pub enum PasteError {
+    Json(serde_json::Error),
+    /// An element that is not in the `type(content)` form.
+    Malformed(String),
+    /// An element whose type is not in `Panel::register_elem_map`.
+    UnknownElem(String),
+    /// An element whose content its type can't read, and why.
+    Invalid(String),
+}
+
+impl Display for PasteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasteError::Json(err) => write!(f, "invalid document: {}", err),
+            PasteError::Malformed(elem) => write!(f, "malformed element: {}", elem),
+            PasteError::UnknownElem(elem_type) => write!(f, "unknown element type: {}", elem_type),
+            PasteError::Invalid(reason) => write!(f, "invalid element: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PasteError {}
+
+impl From<serde_json::Error> for PasteError {
+    fn from(err: serde_json::Error) -> Self {
+        PasteError::Json(err)
+    }
+}
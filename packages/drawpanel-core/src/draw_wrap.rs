@@ -1,24 +1,115 @@
+use geo::{coord, Coordinate};
+
 use crate::{
-    binder::{Draw, DrawCircleOpts, DrawLineOpts, DrawRectOpts, DrawTextOpts, IDraw},
+    binder::{
+        Draw, DrawCircleOpts, DrawImageOpts, DrawLineOpts, DrawPathOpts, DrawRectOpts,
+        DrawTextOpts, IDraw,
+    },
+    elem::rect::LineStyle,
     panel::Panel,
+    theme::{HandleShape, Theme},
+    units::Units,
+    viewport::Affine,
 };
 
 pub struct DrawWrap<'a> {
     draw: &'a Box<dyn IDraw>,
     panel: &'a Panel,
+    view: Affine,
+    scale: f64,
 }
 
 impl<'a> DrawWrap<'a> {
     pub fn new(draw: &'a Box<dyn IDraw>, panel: &'a Panel) -> Self {
-        Self { draw, panel }
+        Self::with_view(draw, panel, panel.viewport.lt_coord, panel.viewport.scale)
+    }
+
+    /// Draws with a view of its own instead of the panel's current one,
+    /// e.g. when rendering off screen.
+    pub fn with_view(
+        draw: &'a Box<dyn IDraw>,
+        panel: &'a Panel,
+        lt_coord: Coordinate,
+        scale: f64,
+    ) -> Self {
+        Self {
+            draw,
+            panel,
+            view: Affine::translate(lt_coord).then_after(&Affine::scale(scale)),
+            scale,
+        }
+    }
+
+    fn absolute_coord(&self, coord: Coordinate) -> Coordinate {
+        self.view.apply(coord)
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.panel.theme
+    }
+
+    /// `line_size` widened for a hovered element, by the same amount on
+    /// screen at any zoom.
+    pub fn hover_size(&self, line_size: f64) -> f64 {
+        line_size + self.panel.theme.hover_outline / self.scale
+    }
+
+    /// An outline in the selection color around a hovered box.
+    pub fn draw_outline(
+        &self,
+        left_top_coord: Coordinate,
+        width: f64,
+        height: f64,
+        line_style: LineStyle,
+    ) {
+        let theme = &self.panel.theme;
+        self.draw.draw_rect(DrawRectOpts {
+            left_top_coord: self.absolute_coord(left_top_coord),
+            width: width * self.scale,
+            height: height * self.scale,
+            line_size: theme.hover_outline,
+            line_color: theme.selection_color,
+            fill_color: None,
+            line_style,
+        });
+    }
+
+    /// A resize handle at `center`, the same size on screen at any zoom.
+    pub fn draw_handle(&self, center: Coordinate) {
+        let theme = &self.panel.theme;
+        let center = self.absolute_coord(center);
+        let size = theme.handle_size;
+        match theme.handle_shape {
+            HandleShape::Circle => self.draw.draw_circle(DrawCircleOpts {
+                center_coord: center,
+                r: size,
+                line_size: 1.,
+                line_color: theme.selection_color,
+                fill_color: theme.handle_fill,
+            }),
+            HandleShape::Square => self.draw.draw_rect(DrawRectOpts {
+                left_top_coord: center - coord! { x: size, y: size },
+                width: size * 2.,
+                height: size * 2.,
+                line_size: 1.,
+                line_color: theme.selection_color,
+                fill_color: Some(theme.handle_fill),
+                line_style: LineStyle::Solid,
+            }),
+        }
+    }
+
+    /// The document's unit, for elements that show lengths.
+    pub fn units(&self) -> Units {
+        self.panel.units
     }
 }
 
 impl Draw for DrawWrap<'_> {
     fn draw_line(&self, opts: DrawLineOpts) {
-        let scale = self.panel.scale;
-        let from_coord = self.panel.absolute_coord(opts.from_coord);
-        let end_coord = self.panel.absolute_coord(opts.end_coord);
+        let scale = self.scale;
+        let from_coord = self.absolute_coord(opts.from_coord);
+        let end_coord = self.absolute_coord(opts.end_coord);
 
         self.draw.draw_line(DrawLineOpts {
             from_coord,
@@ -29,8 +120,8 @@ impl Draw for DrawWrap<'_> {
     }
 
     fn draw_rect(&self, opts: DrawRectOpts) {
-        let scale = self.panel.scale;
-        let left_top_coord = self.panel.absolute_coord(opts.left_top_coord);
+        let scale = self.scale;
+        let left_top_coord = self.absolute_coord(opts.left_top_coord);
 
         self.draw.draw_rect(DrawRectOpts {
             left_top_coord,
@@ -44,8 +135,8 @@ impl Draw for DrawWrap<'_> {
     }
 
     fn draw_circle(&self, opts: DrawCircleOpts) {
-        let scale = self.panel.scale;
-        let center_coord = self.panel.absolute_coord(opts.center_coord);
+        let scale = self.scale;
+        let center_coord = self.absolute_coord(opts.center_coord);
 
         self.draw.draw_circle(DrawCircleOpts {
             line_size: opts.line_size * scale,
@@ -57,8 +148,8 @@ impl Draw for DrawWrap<'_> {
     }
 
     fn draw_text(&self, opts: DrawTextOpts) {
-        let scale = self.panel.scale;
-        let left_top_coord = self.panel.absolute_coord(opts.left_top_coord);
+        let scale = self.scale;
+        let left_top_coord = self.absolute_coord(opts.left_top_coord);
 
         self.draw.draw_text(DrawTextOpts {
             left_top_coord,
@@ -70,4 +161,37 @@ impl Draw for DrawWrap<'_> {
             font_color: opts.font_color,
         })
     }
+
+    fn draw_path(&self, opts: DrawPathOpts) {
+        let scale = self.scale;
+        let coords: Vec<Coordinate> = opts
+            .coords
+            .iter()
+            .map(|coord| self.absolute_coord(*coord))
+            .collect();
+
+        self.draw.draw_path(DrawPathOpts {
+            coords: &coords,
+            line_size: opts.line_size * scale,
+            line_color: opts.line_color,
+            fill_color: opts.fill_color,
+            closed: opts.closed,
+        })
+    }
+
+    fn draw_image(&self, opts: DrawImageOpts) {
+        let scale = self.scale;
+        let left_top_coord = self.absolute_coord(opts.left_top_coord);
+
+        self.draw.draw_image(DrawImageOpts {
+            left_top_coord,
+            width: opts.width * scale,
+            height: opts.height * scale,
+            data: opts.data,
+        })
+    }
+
+    fn set_opacity(&self, opacity: f64) {
+        self.draw.set_opacity(opacity);
+    }
 }
@@ -0,0 +1,213 @@
+use std::{collections::HashMap, fmt::Write};
+
+use geo::Coordinate;
+
+use crate::{
+    elem::{
+        connector::Connector,
+        ellipse::Ellipse,
+        group::flatten,
+        image::Image,
+        line::Line,
+        pen::Pen,
+        rect::{LineStyle, Rect},
+        text::Text,
+        ElemId,
+    },
+    panel::Panel,
+    svg::export::escape,
+};
+
+/// Writes the panel as an uncompressed draw.io file with a single diagram.
+/// Rects, ellipses, texts and images become vertices, lines, pens and
+/// connectors become edges without arrows. Connectors stay connected to the
+/// vertices they are attached to. Other elements are kept as their bounding
+/// box. Groups are written as their children.
+pub fn export_drawio(panel: &Panel) -> String {
+    // The cell id of each element that is not a group.
+    let mut cell_ids: HashMap<ElemId, usize> = HashMap::new();
+    let mut next = 2;
+    for (index, elem) in panel.elems.iter().enumerate() {
+        match panel.elem_id(index) {
+            Some(id) if elem.children().is_none() => {
+                cell_ids.insert(id, next);
+            }
+            _ => {}
+        }
+        next += flatten(std::slice::from_ref(elem)).len();
+    }
+
+    let mut cells = String::new();
+    for (i, (elem, _)) in flatten(&panel.elems).into_iter().enumerate() {
+        // 0 and 1 are the root and the default layer.
+        let id = i + 2;
+        let content = elem.export();
+        match elem.elem_type().as_str() {
+            "rect" => {
+                let rect: Rect = serde_json::from_str(&content).unwrap();
+                write_vertex(&mut cells, id, "", &rect_style("", &rect), &rect);
+            }
+            "ellipse" => {
+                let ellipse: Ellipse = serde_json::from_str(&content).unwrap();
+                let style = rect_style("ellipse;", &ellipse.rect);
+                write_vertex(&mut cells, id, "", &style, &ellipse.rect);
+            }
+            "text" => {
+                let text: Text = serde_json::from_str(&content).unwrap();
+                let lines = text.content.split('\n').count() as f64;
+                let style = format!(
+                    "text;align=center;verticalAlign=middle;whiteSpace=wrap;strokeColor=none;fillColor=none;fontSize={};fontColor={};",
+                    text.height / lines,
+                    color(text.font_color)
+                );
+                let rect = Rect {
+                    lt_coord: text.lt_coord,
+                    width: text.width,
+                    height: text.height,
+                    ..Default::default()
+                };
+                write_vertex(&mut cells, id, &text.content, &style, &rect);
+            }
+            "image" => {
+                let image: Image = serde_json::from_str(&content).unwrap();
+                let style = format!(
+                    "shape=image;imageAspect=0;aspect=fixed;image={};",
+                    image.data.replacen(";base64,", ",", 1)
+                );
+                write_vertex(&mut cells, id, "", &style, &image.rect);
+            }
+            "line" => {
+                let line: Line = serde_json::from_str(&content).unwrap();
+                let style = edge_style(line.line_size, line.line_color);
+                let coords = [line.from_coord, line.end_coord];
+                write_edge(&mut cells, id, &style, "", &coords);
+            }
+            "pen" => {
+                let pen: Pen = serde_json::from_str(&content).unwrap();
+                if pen.coords.len() > 1 {
+                    let style = edge_style(pen.line_size, pen.line_color);
+                    write_edge(&mut cells, id, &style, "", &pen.coords);
+                }
+            }
+            "connector" => {
+                let connector: Connector = serde_json::from_str(&content).unwrap();
+                let mut style = edge_style(connector.line_size, connector.line_color);
+                let mut ends = String::new();
+                let terminals = [
+                    ("source", "exit", connector.source, connector.source_port),
+                    ("target", "entry", connector.target, connector.target_port),
+                ];
+                for (name, port_name, end, port) in terminals {
+                    if let Some(cell_id) = end.and_then(|end| cell_ids.get(&end)) {
+                        write!(ends, r#" {}="{}""#, name, cell_id).unwrap();
+                        if let Some(port) = port {
+                            write!(style, "{0}X={1};{0}Y={2};", port_name, port.x, port.y).unwrap();
+                        }
+                    }
+                }
+                write_edge(&mut cells, id, &style, &ends, &connector.coords);
+            }
+            _ => {
+                if let Some(bounds) = elem.bounding_rect() {
+                    let rect = Rect {
+                        lt_coord: bounds.min(),
+                        width: bounds.width(),
+                        height: bounds.height(),
+                        line_size: 1.,
+                        line_color: 0x999999,
+                        fill_color: None,
+                        line_style: LineStyle::Dotted,
+                    };
+                    write_vertex(&mut cells, id, "", &rect_style("", &rect), &rect);
+                }
+            }
+        }
+    }
+
+    // An infinite canvas turns draw.io's page view off.
+    let (page, width, height) = match panel.page_rect() {
+        Some(rect) => (true, rect.width(), rect.height()),
+        None => (false, panel.width, panel.height),
+    };
+    format!(
+        r#"<mxfile host="drawpanel"><diagram id="drawpanel" name="Page-1"><mxGraphModel grid="1" gridSize="10" page="{}" pageWidth="{}" pageHeight="{}"><root><mxCell id="0"/><mxCell id="1" parent="0"/>{}</root></mxGraphModel></diagram></mxfile>"#,
+        page as u8, width, height, cells
+    )
+}
+
+fn rect_style(shape: &str, rect: &Rect) -> String {
+    let mut style = format!("{}whiteSpace=wrap;", shape);
+    match rect.fill_color {
+        Some(fill_color) => write!(style, "fillColor={};", color(fill_color)).unwrap(),
+        None => style.push_str("fillColor=none;"),
+    }
+    if rect.line_size > 0. {
+        write!(
+            style,
+            "strokeColor={};strokeWidth={};",
+            color(rect.line_color),
+            rect.line_size
+        )
+        .unwrap();
+    } else {
+        style.push_str("strokeColor=none;");
+    }
+    if let LineStyle::Dotted = rect.line_style {
+        style.push_str("dashed=1;");
+    }
+    style
+}
+
+fn edge_style(line_size: f64, line_color: u32) -> String {
+    format!(
+        "endArrow=none;rounded=0;strokeColor={};strokeWidth={};",
+        color(line_color),
+        line_size
+    )
+}
+
+fn write_vertex(out: &mut String, id: usize, value: &str, style: &str, rect: &Rect) {
+    write!(
+        out,
+        r#"<mxCell id="{}" value="{}" style="{}" vertex="1" parent="1"><mxGeometry x="{}" y="{}" width="{}" height="{}" as="geometry"/></mxCell>"#,
+        id,
+        // Attribute values would turn a raw newline into a space.
+        escape(value).replace('\n', "&#xa;"),
+        escape(style),
+        rect.lt_coord.x,
+        rect.lt_coord.y,
+        rect.width,
+        rect.height
+    )
+    .unwrap();
+}
+
+/// `ends` are the `source` and `target` attributes of a connected edge.
+fn write_edge(out: &mut String, id: usize, style: &str, ends: &str, coords: &[Coordinate]) {
+    let from = coords.first().unwrap();
+    let end = coords.last().unwrap();
+    write!(
+        out,
+        r#"<mxCell id="{}" style="{}" edge="1" parent="1"{}><mxGeometry relative="1" as="geometry"><mxPoint x="{}" y="{}" as="sourcePoint"/><mxPoint x="{}" y="{}" as="targetPoint"/>"#,
+        id,
+        escape(style),
+        ends,
+        from.x,
+        from.y,
+        end.x,
+        end.y
+    )
+    .unwrap();
+    if coords.len() > 2 {
+        out.push_str(r#"<Array as="points">"#);
+        for coord in &coords[1..coords.len() - 1] {
+            write!(out, r#"<mxPoint x="{}" y="{}"/>"#, coord.x, coord.y).unwrap();
+        }
+        out.push_str("</Array>");
+    }
+    out.push_str("</mxGeometry></mxCell>");
+}
+
+fn color(color: u32) -> String {
+    format!("#{:06x}", color & 0xffffff)
+}
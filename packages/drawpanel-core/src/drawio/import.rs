@@ -0,0 +1,547 @@
+use std::{collections::HashMap, io::Read};
+
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use geo::{coord, Coordinate};
+use roxmltree::{Document, Node};
+
+use crate::{
+    elem::{
+        connector::{clip, Connector},
+        ellipse::Ellipse,
+        image::Image,
+        rect::{LineStyle, Rect},
+        text::Text,
+        ElemId, IElem,
+    },
+    panel::{ImportMode, Panel},
+};
+
+use super::DrawioError;
+
+/// Converts a draw.io file (`.drawio` / `.dio`) into native elements and
+/// adds them to `panel`. Only the first diagram (page) is read. Vertices map
+/// to `Rect`, `Ellipse`, `Text` and `Image`, their labels to `Text`, and
+/// edges to `Connector`s attached to the vertices they connect.
+///
+/// Returns the number of imported elements.
+pub fn import_drawio(
+    panel: &mut Panel,
+    data: &str,
+    mode: ImportMode,
+) -> Result<usize, DrawioError> {
+    let doc = Document::parse(data)?;
+    let root = doc.root_element();
+
+    let elems = if root.has_tag_name("mxGraphModel") {
+        import_model(root)
+    } else {
+        let diagram = root
+            .descendants()
+            .find(|n| n.has_tag_name("diagram"))
+            .ok_or(DrawioError::NoDiagram)?;
+        match diagram.children().find(|n| n.has_tag_name("mxGraphModel")) {
+            Some(model) => import_model(model),
+            None => {
+                let model = decompress(diagram.text().unwrap_or(""))?;
+                let doc = Document::parse(&model)?;
+                import_model(doc.root_element())
+            }
+        }
+    };
+
+    let count = elems.len();
+    panel.add_imported(elems, mode);
+    Ok(count)
+}
+
+/// draw.io compresses a diagram as base64(deflate(encodeURIComponent(xml))).
+fn decompress(data: &str) -> Result<String, DrawioError> {
+    let data = data.trim();
+    if data.starts_with('<') {
+        return Ok(data.to_string());
+    }
+    let deflated = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|_| DrawioError::Decode)?;
+    let mut encoded = String::new();
+    DeflateDecoder::new(&deflated[..])
+        .read_to_string(&mut encoded)
+        .map_err(|_| DrawioError::Decode)?;
+    percent_encoding::percent_decode_str(&encoded)
+        .decode_utf8()
+        .map(|xml| xml.into_owned())
+        .map_err(|_| DrawioError::Decode)
+}
+
+struct Cell<'a, 'input> {
+    node: Node<'a, 'input>,
+    parent: Option<&'a str>,
+    value: String,
+    style: HashMap<&'a str, &'a str>,
+    vertex: bool,
+    edge: bool,
+    visible: bool,
+}
+
+impl<'a, 'input> Cell<'a, 'input> {
+    fn geometry(&self) -> Option<Node<'a, 'input>> {
+        self.node.children().find(|n| n.has_tag_name("mxGeometry"))
+    }
+
+    /// The bare token of the style, e.g. `ellipse` in
+    /// `ellipse;whiteSpace=wrap;`, or its `shape=` entry.
+    fn shape(&self) -> &'a str {
+        if let Some(shape) = self.style.get("shape") {
+            return shape;
+        }
+        self.node
+            .attribute("style")
+            .unwrap_or("")
+            .split(';')
+            .find(|s| !s.is_empty() && !s.contains('='))
+            .unwrap_or("")
+    }
+
+    fn number(&self, key: &str, default: f64) -> f64 {
+        self.style
+            .get(key)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// `None` when unset or `default`, `Some(None)` for `none`.
+    fn color(&self, key: &str) -> Option<Option<u32>> {
+        match *self.style.get(key)? {
+            "none" => Some(None),
+            value => Some(Some(
+                u32::from_str_radix(value.strip_prefix('#')?, 16).ok()?,
+            )),
+        }
+    }
+
+    fn font_size(&self) -> f64 {
+        self.number("fontSize", 12.)
+    }
+
+    fn font_color(&self) -> u32 {
+        self.color("fontColor").flatten().unwrap_or(0x000000)
+    }
+}
+
+fn import_model(model: Node) -> Vec<Box<dyn IElem>> {
+    let mut cells: Vec<(&str, Cell)> = vec![];
+    for node in model.descendants().filter(|n| n.has_tag_name("mxCell")) {
+        // Cells with custom properties are wrapped in `<object>` (or
+        // `<UserObject>`), which then carries the id and the label.
+        let wrapper = node
+            .parent_element()
+            .filter(|p| p.has_tag_name("object") || p.has_tag_name("UserObject"));
+        let id = match node
+            .attribute("id")
+            .or(wrapper.and_then(|w| w.attribute("id")))
+        {
+            Some(id) => id,
+            None => continue,
+        };
+        let value = node
+            .attribute("value")
+            .or(wrapper.and_then(|w| w.attribute("label")))
+            .unwrap_or("");
+        let style = parse_style(node.attribute("style").unwrap_or(""));
+        let value = if style.get("html") == Some(&"1") {
+            strip_html(value)
+        } else {
+            value.trim().to_string()
+        };
+        cells.push((
+            id,
+            Cell {
+                node,
+                parent: node.attribute("parent"),
+                value,
+                style,
+                vertex: node.attribute("vertex") == Some("1"),
+                edge: node.attribute("edge") == Some("1"),
+                visible: node.attribute("visible") != Some("0"),
+            },
+        ));
+    }
+    let index: HashMap<&str, &Cell> = cells.iter().map(|(id, cell)| (*id, cell)).collect();
+
+    let mut importer = Importer {
+        cells: &index,
+        bounds: HashMap::new(),
+        edges: HashMap::new(),
+        shapes: HashMap::new(),
+        connectors: vec![],
+        elems: vec![],
+    };
+    for (id, cell) in cells.iter() {
+        if cell.vertex {
+            if let Some(bounds) = importer.vertex_bounds(id) {
+                importer.bounds.insert(id, bounds);
+            }
+        }
+    }
+    // Document order is draw.io's z-order.
+    for (id, cell) in cells.iter() {
+        if !importer.is_visible(id) {
+            continue;
+        }
+        if cell.edge {
+            importer.edge(id, cell);
+        } else if cell.vertex {
+            let position = importer.elems.len();
+            importer.vertex(id, cell);
+            if importer.elems.len() > position {
+                importer.shapes.insert(id, position);
+            }
+        }
+    }
+    // Edges may come before the vertices they connect.
+    for (position, source, target) in std::mem::take(&mut importer.connectors) {
+        let end = |id: Option<&str>| importer.shapes.get(id?).map(|p| *p as ElemId);
+        importer.elems[position].set_ends(end(source), end(target));
+    }
+    importer.elems
+}
+
+struct Importer<'a, 'input> {
+    cells: &'a HashMap<&'a str, &'a Cell<'a, 'input>>,
+    /// Absolute bounds of every vertex.
+    bounds: HashMap<&'a str, geo::Rect>,
+    /// Absolute points of every imported edge, for their labels.
+    edges: HashMap<&'a str, Vec<Coordinate>>,
+    /// Position in `elems` of the first element imported for each vertex.
+    shapes: HashMap<&'a str, usize>,
+    /// Position in `elems` of each connector with the ids of the cells it
+    /// connects.
+    connectors: Vec<(usize, Option<&'a str>, Option<&'a str>)>,
+    elems: Vec<Box<dyn IElem>>,
+}
+
+impl<'a, 'input> Importer<'a, 'input> {
+    /// A cell is hidden by itself or by any of its ancestors (e.g. a hidden
+    /// layer).
+    fn is_visible(&self, id: &str) -> bool {
+        let mut id = Some(id);
+        while let Some(cell) = id.and_then(|id| self.cells.get(id)) {
+            if !cell.visible {
+                return false;
+            }
+            id = cell.parent;
+        }
+        true
+    }
+
+    /// Child geometries are relative to their parent vertex (groups,
+    /// containers, swimlanes). Layers don't move anything.
+    fn offset(&self, parent: Option<&str>) -> Coordinate {
+        let mut offset = coord! { x: 0., y: 0. };
+        let mut id = parent;
+        while let Some(cell) = id.and_then(|id| self.cells.get(id)) {
+            if !cell.vertex {
+                break;
+            }
+            if let Some(geometry) = cell.geometry() {
+                offset.x += number(geometry, "x");
+                offset.y += number(geometry, "y");
+            }
+            id = cell.parent;
+        }
+        offset
+    }
+
+    fn vertex_bounds(&self, id: &str) -> Option<geo::Rect> {
+        let cell = self.cells.get(id)?;
+        let geometry = cell.geometry()?;
+        if geometry.attribute("relative") == Some("1") {
+            return None;
+        }
+        let offset = self.offset(cell.parent);
+        let lt = coord! {
+            x: offset.x + number(geometry, "x"),
+            y: offset.y + number(geometry, "y"),
+        };
+        Some(geo::Rect::new(
+            lt,
+            coord! {
+                x: lt.x + number(geometry, "width"),
+                y: lt.y + number(geometry, "height"),
+            },
+        ))
+    }
+
+    fn vertex(&mut self, id: &'a str, cell: &Cell) {
+        let bounds = match self.bounds.get(id) {
+            Some(bounds) => *bounds,
+            None => {
+                // A label placed on an edge.
+                if let Some(points) = cell.parent.and_then(|p| self.edges.get(p)) {
+                    let center = midpoint(points);
+                    self.label(cell, center, None);
+                }
+                return;
+            }
+        };
+        let shape = cell.shape();
+        match shape {
+            "text" | "edgeLabel" => {
+                self.label(cell, bounds.center(), Some(bounds.width()));
+                return;
+            }
+            "group" => return,
+            "image" => {
+                let data = cell.style.get("image").copied().unwrap_or("");
+                self.elems.push(Box::new(Image {
+                    rect: Rect {
+                        lt_coord: bounds.min(),
+                        width: bounds.width(),
+                        height: bounds.height(),
+                        ..Default::default()
+                    },
+                    data: image_data(data),
+                }));
+            }
+            _ => {
+                let rect = Rect {
+                    lt_coord: bounds.min(),
+                    width: bounds.width(),
+                    height: bounds.height(),
+                    line_size: match cell.color("strokeColor") {
+                        Some(None) => 0.,
+                        _ => cell.number("strokeWidth", 1.),
+                    },
+                    line_color: cell.color("strokeColor").flatten().unwrap_or(0x000000),
+                    fill_color: cell.color("fillColor").unwrap_or(Some(0xffffff)),
+                    line_style: if cell.style.get("dashed") == Some(&"1") {
+                        LineStyle::Dotted
+                    } else {
+                        LineStyle::Solid
+                    },
+                };
+                if shape == "ellipse" || cell.style.contains_key("ellipse") {
+                    self.elems.push(Box::new(Ellipse { rect }));
+                } else {
+                    self.elems.push(Box::new(rect));
+                }
+            }
+        }
+        if !cell.value.is_empty() {
+            self.label(cell, bounds.center(), Some(bounds.width()));
+        }
+    }
+
+    fn edge(&mut self, id: &'a str, cell: &Cell<'a, 'input>) {
+        let geometry = cell.geometry();
+        let offset = self.offset(cell.parent);
+        let point = |name: &str| {
+            geometry
+                .and_then(|g| {
+                    g.children()
+                        .find(|n| n.has_tag_name("mxPoint") && n.attribute("as") == Some(name))
+                })
+                .map(|n| coord! { x: offset.x + number(n, "x"), y: offset.y + number(n, "y") })
+        };
+        let waypoints: Vec<Coordinate> = geometry
+            .and_then(|g| {
+                g.children()
+                    .find(|n| n.has_tag_name("Array") && n.attribute("as") == Some("points"))
+            })
+            .map(|array| {
+                array
+                    .children()
+                    .filter(|n| n.has_tag_name("mxPoint"))
+                    .map(|n| coord! { x: offset.x + number(n, "x"), y: offset.y + number(n, "y") })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let source = self.terminal(cell, "source", "exit");
+        let target = self.terminal(cell, "target", "entry");
+        let port = |name: &str| match (
+            cell.style.get(format!("{}X", name).as_str()),
+            cell.style.get(format!("{}Y", name).as_str()),
+        ) {
+            (Some(x), Some(y)) => Some(coord! { x: x.parse().ok()?, y: y.parse().ok()? }),
+            _ => None,
+        };
+        let from = match source {
+            Some((bounds, fixed, ellipse)) => fixed.unwrap_or_else(|| {
+                let toward = waypoints
+                    .first()
+                    .copied()
+                    .or_else(|| target.map(|(b, f, _)| f.unwrap_or(b.center())))
+                    .or_else(|| point("targetPoint"));
+                clip(bounds, toward, ellipse)
+            }),
+            None => match point("sourcePoint") {
+                Some(from) => from,
+                None => return,
+            },
+        };
+        let end = match target {
+            Some((bounds, fixed, ellipse)) => fixed
+                .unwrap_or_else(|| clip(bounds, Some(*waypoints.last().unwrap_or(&from)), ellipse)),
+            None => match point("targetPoint") {
+                Some(end) => end,
+                None => return,
+            },
+        };
+
+        let mut points = vec![from];
+        points.extend(waypoints);
+        points.push(end);
+        let source = source.and(cell.node.attribute("source"));
+        let target = target.and(cell.node.attribute("target"));
+        self.connectors.push((self.elems.len(), source, target));
+        self.elems.push(Box::new(Connector {
+            coords: points.clone(),
+            source: None,
+            target: None,
+            source_port: source.and(port("exit")),
+            target_port: target.and(port("entry")),
+            line_size: cell.number("strokeWidth", 1.),
+            line_color: cell.color("strokeColor").flatten().unwrap_or(0x000000),
+        }));
+        if !cell.value.is_empty() {
+            self.label(cell, midpoint(&points), None);
+        }
+        self.edges.insert(id, points);
+    }
+
+    /// Bounds of the connected vertex, the fixed connection point from
+    /// `exitX`/`exitY` (or `entryX`/`entryY`) and whether it is an ellipse.
+    fn terminal(
+        &self,
+        cell: &Cell,
+        name: &str,
+        port: &str,
+    ) -> Option<(geo::Rect, Option<Coordinate>, bool)> {
+        let id = cell.node.attribute(name)?;
+        let bounds = *self.bounds.get(id)?;
+        let fixed = match (
+            cell.style.get(format!("{}X", port).as_str()),
+            cell.style.get(format!("{}Y", port).as_str()),
+        ) {
+            (Some(x), Some(y)) => match (x.parse::<f64>(), y.parse::<f64>()) {
+                (Ok(x), Ok(y)) => Some(coord! {
+                    x: bounds.min().x + x * bounds.width(),
+                    y: bounds.min().y + y * bounds.height(),
+                }),
+                _ => None,
+            },
+            _ => None,
+        };
+        let ellipse = self.cells.get(id).map_or(false, |c| {
+            c.shape() == "ellipse" || c.style.contains_key("ellipse")
+        });
+        Some((bounds, fixed, ellipse))
+    }
+
+    /// Adds `cell.value` as a `Text` centered on `center`. Without `width`
+    /// the box is sized after the content.
+    fn label(&mut self, cell: &Cell, center: Coordinate, width: Option<f64>) {
+        if cell.value.is_empty() {
+            return;
+        }
+        let font_size = cell.font_size();
+        let lines = cell.value.split('\n').count() as f64;
+        let width = width.unwrap_or_else(|| {
+            cell.value
+                .split('\n')
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0) as f64
+                * font_size
+        });
+        // `Text` derives its font size from the height of the box.
+        let height = font_size * lines;
+        self.elems.push(Box::new(Text {
+            lt_coord: coord! { x: center.x - width / 2., y: center.y - height / 2. },
+            width,
+            height,
+            content: cell.value.clone(),
+            font_color: cell.font_color(),
+        }));
+    }
+}
+
+fn parse_style(style: &str) -> HashMap<&str, &str> {
+    style
+        .split(';')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.split_once('=').unwrap_or((s, "")))
+        .collect()
+}
+
+fn number(node: Node, name: &str) -> f64 {
+    node.attribute(name)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0.)
+}
+
+/// draw.io drops `;base64` from data URIs since `;` separates the style.
+fn image_data(data: &str) -> String {
+    match data.split_once(',') {
+        Some((head, body)) if head.starts_with("data:") && !head.ends_with(";base64") => {
+            format!("{};base64,{}", head, body)
+        }
+        _ => data.to_string(),
+    }
+}
+
+/// The point halfway along a polyline.
+fn midpoint(points: &[Coordinate]) -> Coordinate {
+    let length =
+        |a: &Coordinate, b: &Coordinate| ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt();
+    let total: f64 = points.windows(2).map(|w| length(&w[0], &w[1])).sum();
+    let mut rest = total / 2.;
+    for w in points.windows(2) {
+        let segment = length(&w[0], &w[1]);
+        if segment >= rest && segment > 0. {
+            let t = rest / segment;
+            return coord! {
+                x: w[0].x + (w[1].x - w[0].x) * t,
+                y: w[0].y + (w[1].y - w[0].y) * t,
+            };
+        }
+        rest -= segment;
+    }
+    points.first().copied().unwrap_or_default()
+}
+
+/// Labels with `html=1` may contain markup. Line breaks are kept, every
+/// other tag is dropped.
+fn strip_html(value: &str) -> String {
+    let mut text = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('>')
+            .map_or(rest.len(), |e| start + e + 1);
+        let tag = rest[start + 1..end].trim_end_matches('>');
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "br" => text.push('\n'),
+            "div" | "p" if !text.is_empty() && !text.ends_with('\n') => text.push('\n'),
+            _ => {}
+        }
+        rest = &rest[end..];
+    }
+    text.push_str(rest);
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,31 @@
+use std::fmt::Display;
+
+pub mod export;
+pub mod import;
+
+#[derive(Debug)]
+pub enum DrawioError {
+    Xml(roxmltree::Error),
+    /// A compressed `<diagram>` that is not base64 + deflate + URI encoded.
+    Decode,
+    /// Neither an `<mxfile>` with a diagram nor an `<mxGraphModel>`.
+    NoDiagram,
+}
+
+impl Display for DrawioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawioError::Xml(err) => write!(f, "invalid drawio xml: {}", err),
+            DrawioError::Decode => write!(f, "cannot decode compressed diagram"),
+            DrawioError::NoDiagram => write!(f, "no diagram found"),
+        }
+    }
+}
+
+impl std::error::Error for DrawioError {}
+
+impl From<roxmltree::Error> for DrawioError {
+    fn from(err: roxmltree::Error) -> Self {
+        DrawioError::Xml(err)
+    }
+}
@@ -4,12 +4,32 @@ use std::{
     rc::{Rc, Weak},
 };
 
+use geo::{coord, Coordinate};
+
 use crate::{
     binder::Binder,
-    elem::{self, IElem},
-    panel::Panel,
+    change::Change,
+    clipboard::{ClipboardPayload, PasteError},
+    drawio::{export::export_drawio, import::import_drawio, DrawioError},
+    elem::{self, ElemId, IElem},
+    excalidraw::{export::export_excalidraw, import::import_excalidraw, ExcalidrawReport},
+    layer::{Layer, LayerId},
+    minimap::Minimap,
+    page::{CanvasMode, Page},
+    panel::{union_rect, ImportMode, Panel, PanelSerialize, ZOrder},
+    ruler::Rulers,
+    session::{Session, SessionAction, SessionPlayer, SessionRecording},
+    svg::{
+        export::{export_svg, SvgExportOpts, SvgScope},
+        import::import_svg,
+    },
+    theme::Theme,
+    tool::{CreateTool, DeleteTool, MoveTool, SelectTool, TextEditTool, Tool},
+    units::{Readout, Units},
+    viewport::{PanBindings, Viewport},
 };
 
+/// The built-in tools, see `Drawpanel::set_mode`.
 #[derive(Debug)]
 pub enum Mode {
     EditMoving, // default
@@ -20,8 +40,28 @@ pub enum Mode {
     Select,
 }
 
+impl Mode {
+    pub fn into_tool(self) -> Box<dyn Tool> {
+        match self {
+            Mode::EditMoving => Box::new(MoveTool::default()),
+            Mode::Creating(Some(elem)) => Box::new(CreateTool::new(elem)),
+            Mode::Creating(None) => Box::new(MoveTool::default()),
+            Mode::EditResizing(vertex) => Box::new(MoveTool::resizing(vertex)),
+            Mode::Deleting => Box::new(DeleteTool),
+            Mode::EditState => Box::new(TextEditTool::new(None)),
+            Mode::Select => Box::new(SelectTool),
+        }
+    }
+}
+
+/// How far `paste` and `duplicate` move elements away from the originals.
+pub const PASTE_OFFSET: f64 = 10.;
+
 pub struct Drawpanel {
     panel: Rc<RefCell<Panel>>,
+    clipboard: Option<ClipboardPayload>,
+    /// Pastes since the last copy, each lands a bit further away.
+    pastes: u32,
 }
 
 impl Drawpanel {
@@ -32,15 +72,12 @@ impl Drawpanel {
             region.min().y,
             region.width(),
             region.height(),
-            vec![
-                Box::new(elem::pen::Pen::default()) as Box<dyn IElem>,
-                Box::new(elem::line::Line::default()) as Box<dyn IElem>,
-                Box::new(elem::rect::Rect::default()) as Box<dyn IElem>,
-                Box::new(elem::text::Text::default()) as Box<dyn IElem>,
-            ],
+            elem::builtin_elems(),
         )));
         let drawpanel = Drawpanel {
             panel: panel.clone(),
+            clipboard: None,
+            pastes: 0,
         };
 
         binder.init(Rc::downgrade(&panel));
@@ -70,7 +107,94 @@ impl Drawpanel {
 
     pub fn scale(&self) -> f64 {
         let panel = (*self.panel).borrow();
-        panel.scale
+        panel.viewport.scale
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        let panel = (*self.panel).borrow();
+        panel.viewport
+    }
+
+    /// Replaces the viewport, e.g. to change the zoom limits or step.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.viewport = viewport;
+        panel.flush();
+    }
+
+    /// Zooms around `anchor` in binder coordinates.
+    pub fn zoom_to(&mut self, scale: f64, anchor: Coordinate) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.set_scale(scale, anchor.x, anchor.y);
+    }
+
+    pub fn zoom_to_fit(&mut self) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.zoom_to_fit();
+    }
+
+    pub fn zoom_to_selection(&mut self) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.zoom_to_selection();
+    }
+
+    /// Moves the view by `delta` in binder coordinates.
+    pub fn pan_by(&mut self, delta: Coordinate) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.viewport.pan_by(delta);
+        panel.flush();
+    }
+
+    pub fn set_pan_bindings(&mut self, pan_bindings: PanBindings) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.pan_bindings = pan_bindings;
+    }
+
+    /// Shows, hides, moves or resizes the minimap.
+    pub fn set_minimap(&mut self, update: impl FnOnce(&mut Minimap)) {
+        let mut panel = (*self.panel).borrow_mut();
+        update(&mut panel.minimap);
+        panel.flush();
+    }
+
+    /// Changes the document's unit, see `Panel::units`.
+    pub fn set_units(&mut self, units: Units) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.units = units;
+        panel.flush();
+    }
+
+    pub fn set_rulers(&mut self, update: impl FnOnce(&mut Rulers)) {
+        let mut panel = (*self.panel).borrow_mut();
+        update(&mut panel.rulers);
+        panel.flush();
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.theme = theme;
+        panel.flush();
+    }
+
+    /// See `Panel::readout`.
+    pub fn readout(&self) -> Readout {
+        self.panel.borrow().readout()
+    }
+
+    /// See `Panel::tick`.
+    pub fn tick(&mut self, dt: f64) -> bool {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.tick(dt)
+    }
+
+    pub fn fling(&mut self, velocity: Coordinate) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.fling(velocity);
+    }
+
+    pub fn reset_view(&mut self) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.reset_view();
     }
 
     // pub fn scale_mut(&mut self) -> &mut f64 {
@@ -85,8 +209,280 @@ impl Drawpanel {
     // }
 
     pub fn set_mode(&mut self, mode: Mode) {
+        self.set_tool(mode.into_tool());
+    }
+
+    pub fn set_tool(&mut self, tool: Box<dyn Tool>) {
+        let mut panel = (*self.panel).borrow_mut();
+        if let Some(session) = &mut panel.session {
+            session.record(SessionAction::Mode(tool.mode()));
+        }
+        panel.set_tool(tool);
+        panel.update_cursor();
+        panel.emit_changes();
+    }
+
+    /// Calls `observer` with every change to the document, the selection,
+    /// the mode and the view. Changes made while a mouse button is down are
+    /// reported together when it is released. The panel is borrowed while
+    /// `observer` runs, so queue any work on the `Drawpanel` instead of
+    /// doing it there. Returns an id for `unsubscribe`.
+    pub fn subscribe(&mut self, observer: impl FnMut(&Change) + 'static) -> usize {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.sync_metas();
+        let mut changes = std::mem::take(&mut panel.changes);
+        let id = changes.subscribe(&panel, Box::new(observer));
+        panel.changes = changes;
+        id
+    }
+
+    /// Paints the element at `index` above all others, or the selection
+    /// when `index` is `None`.
+    pub fn bring_to_front(&mut self, index: Option<usize>) {
+        self.reorder(index, ZOrder::Front);
+    }
+
+    pub fn send_to_back(&mut self, index: Option<usize>) {
+        self.reorder(index, ZOrder::Back);
+    }
+
+    pub fn bring_forward(&mut self, index: Option<usize>) {
+        self.reorder(index, ZOrder::Forward);
+    }
+
+    pub fn send_backward(&mut self, index: Option<usize>) {
+        self.reorder(index, ZOrder::Backward);
+    }
+
+    fn reorder(&mut self, index: Option<usize>, z_order: ZOrder) {
         let mut panel = (*self.panel).borrow_mut();
-        panel.mode = mode;
+        let indices: Vec<usize> = match index {
+            Some(index) => vec![index],
+            None => panel.selects.iter().map(|i| *i as usize).collect(),
+        };
+        panel.reorder(&indices, z_order);
+    }
+
+    /// Groups the selected elements, see `Panel::group`.
+    pub fn group(&mut self) -> Option<usize> {
+        let mut panel = (*self.panel).borrow_mut();
+        let indices: Vec<usize> = panel.selects.iter().map(|i| *i as usize).collect();
+        panel.group(&indices)
+    }
+
+    /// Ungroups the group at `index`, or the selected groups when `index`
+    /// is `None`.
+    pub fn ungroup(&mut self, index: Option<usize>) {
+        let mut panel = (*self.panel).borrow_mut();
+        let mut indices: Vec<usize> = match index {
+            Some(index) => vec![index],
+            None => panel.selects.iter().map(|i| *i as usize).collect(),
+        };
+        // From the top, so the lower indices stay valid.
+        indices.sort();
+        for index in indices.iter().rev() {
+            panel.ungroup(*index);
+        }
+    }
+
+    /// Adds an empty layer on top, new elements go there.
+    pub fn add_layer(&mut self, name: &str) -> LayerId {
+        let mut panel = (*self.panel).borrow_mut();
+        let id = panel.add_layer(name);
+        panel.flush();
+        id
+    }
+
+    /// Changes the name, visibility, lock or opacity of a layer.
+    pub fn update_layer(&mut self, id: LayerId, update: impl FnOnce(&mut Layer)) {
+        let mut panel = (*self.panel).borrow_mut();
+        if let Some(layer) = panel.layer_mut(id) {
+            update(layer);
+        }
+        // Whatever is hovered may have become hidden or locked.
+        panel.hover_index = -1;
+        panel.flush();
+    }
+
+    pub fn move_layer(&mut self, id: LayerId, to: usize) -> bool {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.move_layer(id, to)
+    }
+
+    pub fn merge_layer(&mut self, from: LayerId, into: LayerId) -> bool {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.merge_layer(from, into)
+    }
+
+    /// Moves the element at `index`, or the selection when `index` is
+    /// `None`, on top of `layer`.
+    pub fn move_to_layer(&mut self, index: Option<usize>, layer: LayerId) -> bool {
+        let mut panel = (*self.panel).borrow_mut();
+        let indices: Vec<usize> = match index {
+            Some(index) => vec![index],
+            None => panel.selects.iter().map(|i| *i as usize).collect(),
+        };
+        panel.move_to_layer(&indices, layer)
+    }
+
+    /// Switches between one page, an infinite canvas and several pages, see
+    /// `Panel::set_canvas_mode`.
+    pub fn set_canvas_mode(&mut self, canvas: CanvasMode) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.set_canvas_mode(canvas);
+    }
+
+    pub fn pages(&self) -> Vec<Page> {
+        self.panel.borrow().pages.clone()
+    }
+
+    pub fn current_page(&self) -> usize {
+        self.panel.borrow().current_page
+    }
+
+    /// Adds an empty page after the others and returns its index.
+    pub fn add_page(&mut self, name: &str, width: f64, height: f64) -> usize {
+        let mut panel = (*self.panel).borrow_mut();
+        let index = panel.add_page(name, width, height);
+        panel.flush();
+        index
+    }
+
+    pub fn update_page(&mut self, index: usize, update: impl FnOnce(&mut Page)) {
+        let mut panel = (*self.panel).borrow_mut();
+        if let Some(page) = panel.pages.get_mut(index) {
+            update(page);
+        }
+        panel.flush();
+    }
+
+    pub fn remove_page(&mut self, index: usize) -> bool {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.remove_page(index)
+    }
+
+    pub fn go_to_page(&mut self, index: usize) -> bool {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.go_to_page(index)
+    }
+
+    pub fn next_page(&mut self) -> bool {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.next_page()
+    }
+
+    pub fn prev_page(&mut self) -> bool {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.prev_page()
+    }
+
+    /// Copies the selection for `paste` and returns it for the system
+    /// clipboard. `None` without a selection.
+    pub fn copy(&mut self) -> Option<ClipboardPayload> {
+        let panel = self.panel.borrow();
+        let indices: Vec<usize> = panel.selects.iter().map(|i| *i as usize).collect();
+        if indices.is_empty() {
+            return None;
+        }
+        let svg_opts = SvgExportOpts {
+            scope: SvgScope::Selection,
+            embed_document: false,
+        };
+        let payload = ClipboardPayload {
+            document: panel.export_elems(&indices),
+            svg: Some(export_svg(&panel, &svg_opts)),
+        };
+        drop(panel);
+        self.clipboard = Some(payload.clone());
+        self.pastes = 0;
+        Some(payload)
+    }
+
+    /// Copies the selection and deletes it.
+    pub fn cut(&mut self) -> Option<ClipboardPayload> {
+        let payload = self.copy()?;
+        let mut panel = self.panel.borrow_mut();
+        let indices: Vec<usize> = panel.selects.iter().map(|i| *i as usize).collect();
+        panel.delete_elems(&indices);
+        Some(payload)
+    }
+
+    /// Adds what was last copied or cut and selects it. With `at` (panel
+    /// coordinates) the elements' bounds start there, otherwise they are
+    /// offset from the originals, further with each paste. Returns the new
+    /// elements' indices, none when nothing was copied.
+    pub fn paste(&mut self, at: Option<Coordinate>) -> Result<Vec<usize>, PasteError> {
+        match self.clipboard.clone() {
+            Some(payload) => self.paste_payload(&payload, at),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// `paste` for a payload taken from the system clipboard, possibly
+    /// copied in another drawpanel. Fails, leaving the panel as it was, when
+    /// the document or one of its elements can't be read, or has element
+    /// types that aren't registered here.
+    pub fn paste_payload(
+        &mut self,
+        payload: &ClipboardPayload,
+        at: Option<Coordinate>,
+    ) -> Result<Vec<usize>, PasteError> {
+        let mut panel = self.panel.borrow_mut();
+        let (panel_serialize, elems) = panel.try_parse_elems(&payload.document)?;
+        self.pastes += 1;
+        let offset = PASTE_OFFSET * self.pastes as f64;
+        Ok(paste_elems(
+            &mut panel,
+            elems,
+            &panel_serialize.elem_ids,
+            at,
+            offset,
+        ))
+    }
+
+    /// Copies the selection in place, slightly offset, without touching the
+    /// clipboard.
+    pub fn duplicate(&mut self) -> Vec<usize> {
+        let mut panel = self.panel.borrow_mut();
+        let indices: Vec<usize> = panel.selects.iter().map(|i| *i as usize).collect();
+        if indices.is_empty() {
+            return vec![];
+        }
+        panel.sync_metas();
+        let document = panel.export_elems(&indices);
+        let elems = panel.parse_elems(&document);
+        let panel_serialize: PanelSerialize = serde_json::from_str(&document).unwrap();
+        paste_elems(
+            &mut panel,
+            elems,
+            &panel_serialize.elem_ids,
+            None,
+            PASTE_OFFSET,
+        )
+    }
+
+    pub fn unsubscribe(&mut self, id: usize) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.changes.unsubscribe(id);
+    }
+
+    /// Starts logging input events and mode changes. A running recording is
+    /// restarted.
+    pub fn start_recording(&mut self) {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.session = Some(SessionRecording::new(&panel));
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Session> {
+        let mut panel = (*self.panel).borrow_mut();
+        panel.session.take().map(|session| session.finish())
+    }
+
+    /// Rebuilds the document of `session` without waiting between events.
+    pub fn replay(&mut self, session: &Session) {
+        SessionPlayer::new(session.clone())
+            .with_speed(0.)
+            .play(self);
     }
 
     // pub fn mode(&self) -> &Mode {
@@ -101,6 +497,45 @@ impl Drawpanel {
         return self.panel.borrow().export();
     }
 
+    pub fn export_svg(&self, opts: &SvgExportOpts) -> String {
+        export_svg(&self.panel.borrow(), opts)
+    }
+
+    /// The elements of one page as a document, see `Panel::export_page`.
+    pub fn export_page(&self, index: usize) -> Option<String> {
+        self.panel.borrow().export_page(index)
+    }
+
+    /// One page as SVG, whichever page is shown.
+    pub fn export_page_svg(&self, index: usize, opts: &SvgExportOpts) -> Option<String> {
+        let panel = self.panel.borrow();
+        panel.with_page(index, |panel| export_svg(panel, opts))
+    }
+
+    pub fn import_svg(&mut self, data: &str, mode: ImportMode) -> Result<usize, roxmltree::Error> {
+        import_svg(&mut self.panel.borrow_mut(), data, mode)
+    }
+
+    pub fn export_drawio(&self) -> String {
+        export_drawio(&self.panel.borrow())
+    }
+
+    pub fn import_drawio(&mut self, data: &str, mode: ImportMode) -> Result<usize, DrawioError> {
+        import_drawio(&mut self.panel.borrow_mut(), data, mode)
+    }
+
+    pub fn export_excalidraw(&self) -> String {
+        export_excalidraw(&self.panel.borrow())
+    }
+
+    pub fn import_excalidraw(
+        &mut self,
+        data: &str,
+        mode: ImportMode,
+    ) -> Result<ExcalidrawReport, serde_json::Error> {
+        import_excalidraw(&mut self.panel.borrow_mut(), data, mode)
+    }
+
     pub fn import(&mut self, data: &str) {
         let mut panel = (*self.panel).borrow_mut();
         panel.import(data);
@@ -110,3 +545,37 @@ impl Drawpanel {
         Rc::downgrade(&self.panel)
     }
 }
+
+/// Connectors among `elems` stay attached to each other's copies, `old`
+/// being the ids of the originals.
+fn paste_elems(
+    panel: &mut Panel,
+    mut elems: Vec<Box<dyn IElem>>,
+    old: &[ElemId],
+    at: Option<Coordinate>,
+    offset: f64,
+) -> Vec<usize> {
+    let bounds = elems
+        .iter()
+        .filter_map(|elem| elem.bounding_rect())
+        .reduce(union_rect);
+    if let Some(bounds) = bounds {
+        let from = bounds.min();
+        let to = at.unwrap_or(coord! { x: from.x + offset, y: from.y + offset });
+        for elem in elems.iter_mut() {
+            elem.edit_moving(from, to);
+        }
+    }
+    let ids: Vec<ElemId> = elems
+        .into_iter()
+        .map(|elem| panel.push_elem(elem))
+        .collect();
+    if old.len() == ids.len() {
+        panel.reattach(old, &ids);
+    } else {
+        panel.reattach(&[], &ids);
+    }
+    let indices: Vec<usize> = ids.iter().filter_map(|id| panel.elem_index(*id)).collect();
+    panel.select(&indices);
+    indices
+}
@@ -0,0 +1,220 @@
+use crate::{
+    binder::{Draw, DrawPathOpts},
+    clipboard::PasteError,
+    draw_wrap::DrawWrap,
+    serde_helper::{option_coordinate, vec_coordinate},
+};
+
+use super::{fit_coord, from_json, Elem, ElemId, ElemRegistry, IElem, Status};
+
+use geo::{coord, BoundingRect, Coordinate, EuclideanDistance, LineString, Point};
+use serde::{Deserialize, Serialize};
+
+/// A line between two elements that follows them when they move, see
+/// `Panel::route_connectors`. An end that is not attached, or whose element
+/// is gone, stays where it is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Connector {
+    /// The two ends with the waypoints between them.
+    #[serde(with = "vec_coordinate")]
+    pub coords: Vec<Coordinate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<ElemId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<ElemId>,
+    /// Where the connector leaves the source, relative to its bounds (0 to
+    /// 1 across). Without it the end sits where the outline faces the next
+    /// point.
+    #[serde(with = "option_coordinate", skip_serializing_if = "Option::is_none")]
+    pub source_port: Option<Coordinate>,
+    #[serde(with = "option_coordinate", skip_serializing_if = "Option::is_none")]
+    pub target_port: Option<Coordinate>,
+    pub line_size: f64,
+    pub line_color: u32,
+}
+
+impl Default for Connector {
+    fn default() -> Self {
+        Connector {
+            coords: vec![Coordinate::default(); 2],
+            source: None,
+            target: None,
+            source_port: None,
+            target_port: None,
+            line_size: 1.,
+            line_color: 0x000000,
+        }
+    }
+}
+
+/// An element a connector is attached to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Terminal {
+    pub bounds: geo::Rect,
+    pub ellipse: bool,
+}
+
+impl Terminal {
+    /// The end at `port`, or else where the outline faces `toward`.
+    pub fn point(&self, port: Option<Coordinate>, toward: Coordinate) -> Coordinate {
+        match port {
+            Some(port) => self.port(port),
+            None => clip(self.bounds, Some(toward), self.ellipse),
+        }
+    }
+
+    fn port(&self, port: Coordinate) -> Coordinate {
+        coord! {
+            x: self.bounds.min().x + port.x * self.bounds.width(),
+            y: self.bounds.min().y + port.y * self.bounds.height(),
+        }
+    }
+}
+
+/// Where the line from the center of `bounds` toward `toward` leaves it.
+pub(crate) fn clip(bounds: geo::Rect, toward: Option<Coordinate>, ellipse: bool) -> Coordinate {
+    let center = bounds.center();
+    let toward = match toward {
+        Some(toward) => toward,
+        None => return center,
+    };
+    let (dx, dy) = (toward.x - center.x, toward.y - center.y);
+    let (hw, hh) = (bounds.width() / 2., bounds.height() / 2.);
+    if (dx == 0. && dy == 0.) || hw == 0. || hh == 0. {
+        return center;
+    }
+    let t = if ellipse {
+        1. / ((dx / hw).powi(2) + (dy / hh).powi(2)).sqrt()
+    } else {
+        (hw / dx.abs()).min(hh / dy.abs())
+    };
+    if t >= 1. {
+        return center;
+    }
+    coord! { x: center.x + dx * t, y: center.y + dy * t }
+}
+
+impl IElem for Connector {}
+
+impl Elem for Connector {
+    fn draw(&self, draw: &DrawWrap<'_>, status: Status) {
+        let line_size = match status {
+            Status::Hover => draw.hover_size(self.line_size),
+            _ => self.line_size,
+        };
+        draw.draw_path(DrawPathOpts {
+            coords: &self.coords,
+            line_size,
+            line_color: self.line_color,
+            fill_color: None,
+            closed: false,
+        });
+        if let Status::Hover | Status::Resizing(_) = status {
+            for end in self.get_vertex() {
+                draw.draw_handle(end);
+            }
+        }
+    }
+
+    fn get_vertex(&self) -> Vec<Coordinate> {
+        vec![*self.coords.first().unwrap(), *self.coords.last().unwrap()]
+    }
+
+    fn creating(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        self.coords = vec![from_coord, end_coord];
+    }
+
+    /// Moves the waypoints too. Attached ends go back onto their elements.
+    fn edit_moving(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        let offset = end_coord - from_coord;
+        for coord in self.coords.iter_mut() {
+            *coord = *coord + offset;
+        }
+    }
+
+    /// Dragging an end detaches it.
+    fn edit_resizing(&mut self, _from_coord: Coordinate, end_coord: Coordinate, drag_vertex: i32) {
+        match drag_vertex {
+            0 => {
+                self.coords[0] = end_coord;
+                self.source = None;
+                self.source_port = None;
+            }
+            1 => {
+                *self.coords.last_mut().unwrap() = end_coord;
+                self.target = None;
+                self.target_port = None;
+            }
+            _ => (),
+        }
+    }
+
+    fn bounding_rect(&self) -> Option<geo::Rect> {
+        LineString::new(self.coords.clone()).bounding_rect()
+    }
+
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        for coord in self.coords.iter_mut() {
+            *coord = fit_coord(*coord, from, to);
+        }
+    }
+
+    fn hover_condition(&self, mouse_point: Point) -> bool {
+        LineString::new(self.coords.clone()).euclidean_distance(&mouse_point) < 10.
+    }
+
+    fn ends(&self) -> Option<(Option<ElemId>, Option<ElemId>)> {
+        Some((self.source, self.target))
+    }
+
+    fn set_ends(&mut self, source: Option<ElemId>, target: Option<ElemId>) {
+        self.source = source;
+        self.target = target;
+    }
+
+    fn route(&mut self, source: Option<Terminal>, target: Option<Terminal>) {
+        let last = self.coords.len() - 1;
+        if let Some(source) = source {
+            let toward = match target {
+                _ if last > 1 => self.coords[1],
+                Some(target) => match self.target_port {
+                    Some(port) => target.port(port),
+                    None => target.bounds.center(),
+                },
+                None => self.coords[last],
+            };
+            self.coords[0] = source.point(self.source_port, toward);
+        }
+        if let Some(target) = target {
+            self.coords[last] = target.point(self.target_port, self.coords[last - 1]);
+        }
+    }
+
+    fn export(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn import(&self, content: &str) -> Box<dyn IElem> {
+        Box::new(serde_json::from_str::<Connector>(content).unwrap())
+    }
+
+    /// A connector needs both ends.
+    fn try_import(
+        &self,
+        content: &str,
+        _registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        let connector: Connector = from_json(self, content)?;
+        if connector.coords.len() < 2 {
+            return Err(PasteError::Invalid(
+                "connector: fewer than two points".to_string(),
+            ));
+        }
+        Ok(Box::new(connector))
+    }
+
+    fn elem_type(&self) -> String {
+        "connector".to_string()
+    }
+}
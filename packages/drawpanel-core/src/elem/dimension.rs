@@ -0,0 +1,146 @@
+use crate::{
+    binder::{Draw, DrawLineOpts, DrawTextOpts},
+    clipboard::PasteError,
+    draw_wrap::DrawWrap,
+    serde_helper::CoordinateRef,
+};
+
+use super::{fit_coord, from_json, Elem, ElemRegistry, IElem, Status};
+
+use educe::Educe;
+use geo::{coord, Coordinate, EuclideanDistance, Point};
+use serde::{Deserialize, Serialize};
+
+/// The distance between two points, labelled in the document's unit.
+#[derive(Debug, Copy, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(default)]
+pub struct Dimension {
+    #[serde(with = "CoordinateRef")]
+    pub from_coord: Coordinate,
+    #[serde(with = "CoordinateRef")]
+    pub end_coord: Coordinate,
+    #[educe(Default = 1.)]
+    pub line_size: f64,
+    #[educe(Default = 0x333333)]
+    pub line_color: u32,
+    #[educe(Default = 12.)]
+    pub font_size: f64,
+}
+
+impl Dimension {
+    pub fn length(&self) -> f64 {
+        let d = self.end_coord - self.from_coord;
+        d.x.hypot(d.y)
+    }
+
+    /// Draws the line with a tick across each end, and the label above
+    /// its middle.
+    fn draw_measure(&self, draw: &DrawWrap<'_>, line_size: f64) {
+        let line_color = self.line_color;
+        draw.draw_line(DrawLineOpts {
+            from_coord: self.from_coord,
+            end_coord: self.end_coord,
+            line_size,
+            line_color,
+        });
+        let length = self.length();
+        let normal = if length > 0. {
+            let d = (self.end_coord - self.from_coord) / length;
+            coord! { x: -d.y * 6., y: d.x * 6. }
+        } else {
+            coord! { x: 0., y: 6. }
+        };
+        for end in [self.from_coord, self.end_coord] {
+            draw.draw_line(DrawLineOpts {
+                from_coord: end - normal,
+                end_coord: end + normal,
+                line_size,
+                line_color,
+            });
+        }
+
+        let label = draw.units().format(length);
+        let width = label.len() as f64 * self.font_size * 0.6;
+        let height = self.font_size * 1.2;
+        let middle = (self.from_coord + self.end_coord) / 2.;
+        draw.draw_text(DrawTextOpts {
+            left_top_coord: coord! { x: middle.x - width / 2., y: middle.y - height - 2. },
+            width,
+            height,
+            content: &label,
+            font_size: self.font_size,
+            font_space: 0,
+            font_color: line_color,
+        });
+    }
+}
+
+impl IElem for Dimension {}
+
+impl Elem for Dimension {
+    fn draw(&self, draw: &DrawWrap<'_>, status: Status) {
+        let line_size = match status {
+            Status::Hover => draw.hover_size(self.line_size),
+            _ => self.line_size,
+        };
+        self.draw_measure(draw, line_size);
+        if let Status::Hover | Status::Resizing(_) = status {
+            draw.draw_handle(self.from_coord);
+            draw.draw_handle(self.end_coord);
+        }
+    }
+
+    fn get_vertex(&self) -> Vec<Coordinate> {
+        vec![self.from_coord, self.end_coord]
+    }
+
+    fn creating(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        self.from_coord = from_coord;
+        self.end_coord = end_coord;
+    }
+
+    fn edit_moving(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        let offset = end_coord - from_coord;
+        self.from_coord = self.from_coord + offset;
+        self.end_coord = self.end_coord + offset;
+    }
+
+    fn edit_resizing(&mut self, _from_coord: Coordinate, end_coord: Coordinate, drag_vertex: i32) {
+        match drag_vertex {
+            0 => self.from_coord = end_coord,
+            1 => self.end_coord = end_coord,
+            _ => (),
+        }
+    }
+
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        self.from_coord = fit_coord(self.from_coord, from, to);
+        self.end_coord = fit_coord(self.end_coord, from, to);
+    }
+
+    fn hover_condition(&self, mouse_point: Point) -> bool {
+        let t_line = geo::Line::new(self.from_coord, self.end_coord);
+        mouse_point.euclidean_distance(&t_line) < 10.
+    }
+
+    fn export(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn import(&self, content: &str) -> Box<dyn IElem> {
+        Box::new(serde_json::from_str::<Dimension>(content).unwrap())
+    }
+
+    fn try_import(
+        &self,
+        content: &str,
+        _registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        Ok(Box::new(from_json::<Dimension>(self, content)?))
+    }
+
+    fn elem_type(&self) -> String {
+        "dimension".to_string()
+    }
+}
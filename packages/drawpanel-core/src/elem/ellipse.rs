@@ -0,0 +1,113 @@
+use std::f64::consts::PI;
+
+use crate::{
+    binder::{Draw, DrawPathOpts},
+    clipboard::PasteError,
+    draw_wrap::DrawWrap,
+};
+
+use super::{from_json, rect::Rect, Elem, ElemRegistry, IElem, Status};
+use geo::{coord, point, Coordinate, EuclideanDistance, Point};
+use serde::{Deserialize, Serialize};
+
+/// An ellipse inscribed in `rect`, which it also uses for moving and
+/// resizing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ellipse {
+    #[serde(flatten)]
+    pub rect: Rect,
+}
+
+impl Ellipse {
+    fn outline(&self) -> Vec<Coordinate> {
+        let vertex = self.rect.get_vertex();
+        let (tl, br) = (vertex[0], vertex[2]);
+        let center = coord! { x: (tl.x + br.x) / 2., y: (tl.y + br.y) / 2. };
+        let (rx, ry) = ((br.x - tl.x) / 2., (br.y - tl.y) / 2.);
+        (0..64)
+            .map(|i| {
+                let angle = i as f64 / 64. * 2. * PI;
+                coord! { x: center.x + rx * angle.cos(), y: center.y + ry * angle.sin() }
+            })
+            .collect()
+    }
+}
+
+impl IElem for Ellipse {}
+
+impl Elem for Ellipse {
+    fn draw(&self, draw: &DrawWrap, status: Status) {
+        let rect = &self.rect;
+        let outline = self.outline();
+        let line_size = match status {
+            Status::Hover => draw.hover_size(rect.line_size),
+            _ => rect.line_size,
+        };
+        draw.draw_path(DrawPathOpts {
+            coords: &outline,
+            line_size,
+            line_color: rect.line_color,
+            fill_color: rect.fill_color,
+            closed: true,
+        });
+        if let Status::Hover | Status::Resizing(_) = status {
+            for coord in rect.get_vertex() {
+                draw.draw_handle(coord);
+            }
+        }
+    }
+
+    fn get_vertex(&self) -> Vec<Coordinate> {
+        self.rect.get_vertex()
+    }
+
+    fn creating(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        self.rect.creating(from_coord, end_coord)
+    }
+
+    fn edit_moving(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        self.rect.edit_moving(from_coord, end_coord)
+    }
+
+    fn edit_resizing(&mut self, from_coord: Coordinate, end_coord: Coordinate, drag_vertex: i32) {
+        self.rect.edit_resizing(from_coord, end_coord, drag_vertex)
+    }
+
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        self.rect.fit(from, to)
+    }
+
+    fn hover_condition(&self, mouse_point: Point) -> bool {
+        let vertex = self.rect.get_vertex();
+        let (tl, br) = (vertex[0], vertex[2]);
+        let (rx, ry) = ((br.x - tl.x) / 2., (br.y - tl.y) / 2.);
+        let dx = mouse_point.x() - (tl.x + rx);
+        let dy = mouse_point.y() - (tl.y + ry);
+        let inside = rx > 0. && ry > 0. && (dx * dx) / (rx * rx) + (dy * dy) / (ry * ry) <= 1.;
+        inside
+            || vertex
+                .iter()
+                .any(|coord| point! {*coord}.euclidean_distance(&mouse_point) < 10.)
+    }
+
+    fn elem_type(&self) -> String {
+        "ellipse".to_string()
+    }
+
+    fn export(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn import(&self, content: &str) -> Box<dyn IElem> {
+        Box::new(serde_json::from_str::<Ellipse>(content).unwrap())
+    }
+
+    fn try_import(
+        &self,
+        content: &str,
+        _registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        Ok(Box::new(from_json::<Ellipse>(self, content)?))
+    }
+}
@@ -0,0 +1,175 @@
+use geo::{coord, Coordinate, Point};
+
+use crate::{
+    clipboard::PasteError,
+    draw_wrap::DrawWrap,
+    panel::{parse_elem_with, try_parse_elem_with, union_rect, PanelSerialize},
+};
+
+use super::{builtin_elems, from_json, rect::Rect, Elem, ElemRegistry, IElem, Status};
+
+/// Elements that move, resize and delete together. Exported as the
+/// `type(content)` forms of its children, like a document.
+#[derive(Debug, Default)]
+pub struct Group {
+    pub elems: Vec<Box<dyn IElem>>,
+}
+
+impl IElem for Group {}
+
+/// The elements of `elems` with groups replaced by their children, each with
+/// the numbers of the groups it is in, outermost first. Groups are numbered
+/// in the order they are found.
+pub fn flatten(elems: &[Box<dyn IElem>]) -> Vec<(&dyn IElem, Vec<usize>)> {
+    fn walk<'a>(
+        elems: &'a [Box<dyn IElem>],
+        path: &mut Vec<usize>,
+        count: &mut usize,
+        out: &mut Vec<(&'a dyn IElem, Vec<usize>)>,
+    ) {
+        for elem in elems.iter() {
+            match elem.children() {
+                Some(children) => {
+                    path.push(*count);
+                    *count += 1;
+                    walk(children, path, count, out);
+                    path.pop();
+                }
+                None => out.push((elem.as_ref(), path.clone())),
+            }
+        }
+    }
+    let mut out = vec![];
+    walk(elems, &mut vec![], &mut 0, &mut out);
+    out
+}
+
+impl Elem for Group {
+    fn draw(&self, draw: &DrawWrap, status: Status) {
+        for elem in self.elems.iter() {
+            elem.draw(draw, Status::Default);
+        }
+        if let Status::Hover | Status::Resizing(_) = status {
+            let vertex = self.get_vertex();
+            let size = vertex[2] - vertex[0];
+            let line_style = draw.theme().select_box_dash.clone();
+            draw.draw_outline(vertex[0], size.x, size.y, line_style);
+            for coord in vertex {
+                draw.draw_handle(coord);
+            }
+        }
+    }
+
+    fn get_vertex(&self) -> Vec<Coordinate> {
+        let bounds = self
+            .bounding_rect()
+            .unwrap_or(geo::Rect::new(Coordinate::zero(), Coordinate::zero()));
+        let (tl, br) = (bounds.min(), bounds.max());
+        vec![
+            tl,
+            coord! { x: br.x, y: tl.y },
+            br,
+            coord! { x: tl.x, y: br.y },
+        ]
+    }
+
+    fn bounding_rect(&self) -> Option<geo::Rect> {
+        self.elems
+            .iter()
+            .filter_map(|elem| elem.bounding_rect())
+            .reduce(union_rect)
+    }
+
+    fn creating(&mut self, _from_coord: Coordinate, _end_coord: Coordinate) {}
+
+    fn edit_moving(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        for elem in self.elems.iter_mut() {
+            elem.edit_moving(from_coord, end_coord);
+        }
+    }
+
+    fn edit_resizing(&mut self, from_coord: Coordinate, end_coord: Coordinate, drag_vertex: i32) {
+        let before = match self.bounding_rect() {
+            Some(before) => before,
+            None => return,
+        };
+        let mut rect = Rect {
+            lt_coord: before.min(),
+            width: before.width(),
+            height: before.height(),
+            ..Default::default()
+        };
+        rect.edit_resizing(from_coord, end_coord, drag_vertex);
+        let after = geo::Rect::new(
+            rect.lt_coord,
+            coord! { x: rect.lt_coord.x + rect.width, y: rect.lt_coord.y + rect.height },
+        );
+        self.fit(before, after);
+    }
+
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        for elem in self.elems.iter_mut() {
+            elem.fit(from, to);
+        }
+    }
+
+    fn hover_condition(&self, mouse_point: Point) -> bool {
+        self.elems
+            .iter()
+            .any(|elem| elem.hover_condition(mouse_point))
+    }
+
+    fn elem_type(&self) -> String {
+        "group".to_string()
+    }
+
+    fn export(&self) -> String {
+        let panel_serialize = PanelSerialize {
+            elems: self
+                .elems
+                .iter()
+                .map(|elem| format!("{}({})", elem.elem_type(), elem.export()))
+                .collect(),
+            ..Default::default()
+        };
+        serde_json::to_string(&panel_serialize).unwrap()
+    }
+
+    /// Children of types that aren't built in need `import_with`.
+    fn import(&self, content: &str) -> Box<dyn IElem> {
+        let registry: ElemRegistry = builtin_elems()
+            .into_iter()
+            .map(|elem| (elem.elem_type(), elem))
+            .collect();
+        self.import_with(content, &registry)
+    }
+
+    fn children(&self) -> Option<&[Box<dyn IElem>]> {
+        Some(&self.elems)
+    }
+
+    fn import_with(&self, content: &str, registry: &ElemRegistry) -> Box<dyn IElem> {
+        let panel_serialize: PanelSerialize = serde_json::from_str(content).unwrap();
+        Box::new(Group {
+            elems: panel_serialize
+                .elems
+                .iter()
+                .map(|elem_seria| parse_elem_with(registry, elem_seria))
+                .collect(),
+        })
+    }
+
+    fn try_import(
+        &self,
+        content: &str,
+        registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        let panel_serialize: PanelSerialize = from_json(self, content)?;
+        let elems = panel_serialize
+            .elems
+            .iter()
+            .map(|elem_seria| try_parse_elem_with(registry, elem_seria))
+            .collect::<Result<_, _>>()?;
+        Ok(Box::new(Group { elems }))
+    }
+}
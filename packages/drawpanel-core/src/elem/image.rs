@@ -0,0 +1,84 @@
+use crate::{
+    binder::{Draw, DrawImageOpts},
+    clipboard::PasteError,
+    draw_wrap::DrawWrap,
+};
+
+use super::{from_json, rect::Rect, Elem, ElemRegistry, IElem, Status};
+use geo::{Coordinate, Point};
+use serde::{Deserialize, Serialize};
+
+/// A picture stretched over `rect`. `data` is a data URI or a URL.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Image {
+    #[serde(flatten)]
+    pub rect: Rect,
+    pub data: String,
+}
+
+impl IElem for Image {}
+
+impl Elem for Image {
+    fn draw(&self, draw: &DrawWrap, status: Status) {
+        let vertex = self.rect.get_vertex();
+        let wh = vertex[2] - vertex[0];
+        draw.draw_image(DrawImageOpts {
+            left_top_coord: vertex[0],
+            width: wh.x,
+            height: wh.y,
+            data: &self.data,
+        });
+        if let Status::Hover | Status::Resizing(_) | Status::Creating = status {
+            Rect {
+                fill_color: None,
+                ..self.rect.clone()
+            }
+            .draw(draw, status);
+        }
+    }
+
+    fn get_vertex(&self) -> Vec<Coordinate> {
+        self.rect.get_vertex()
+    }
+
+    fn creating(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        self.rect.creating(from_coord, end_coord)
+    }
+
+    fn edit_moving(&mut self, from_coord: Coordinate, end_coord: Coordinate) {
+        self.rect.edit_moving(from_coord, end_coord)
+    }
+
+    fn edit_resizing(&mut self, from_coord: Coordinate, end_coord: Coordinate, drag_vertex: i32) {
+        self.rect.edit_resizing(from_coord, end_coord, drag_vertex)
+    }
+
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        self.rect.fit(from, to)
+    }
+
+    fn hover_condition(&self, mouse_point: Point) -> bool {
+        self.rect.hover_condition(mouse_point)
+    }
+
+    fn elem_type(&self) -> String {
+        "image".to_string()
+    }
+
+    fn export(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn import(&self, content: &str) -> Box<dyn IElem> {
+        Box::new(serde_json::from_str::<Image>(content).unwrap())
+    }
+
+    fn try_import(
+        &self,
+        content: &str,
+        _registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        Ok(Box::new(from_json::<Image>(self, content)?))
+    }
+}
@@ -2,79 +2,48 @@ use std::rc::Rc;
 
 use crate::{
     binder::{Binder, Draw, DrawCircleOpts, DrawLineOpts},
+    clipboard::PasteError,
     draw_wrap::DrawWrap,
     serde_helper::CoordinateRef,
 };
 
-use super::{Elem, IElem, Status};
+use super::{fit_coord, from_json, Elem, ElemRegistry, IElem, Status};
 
+use educe::Educe;
 use geo::{Coordinate, EuclideanDistance, Point};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(default)]
 pub struct Line {
+    #[serde(with = "CoordinateRef")]
     pub from_coord: Coordinate,
+    #[serde(with = "CoordinateRef")]
     pub end_coord: Coordinate,
+    #[educe(Default = 3.)]
+    pub line_size: f64,
+    #[educe(Default = 0xff0000)]
+    pub line_color: u32,
 }
 
 impl IElem for Line {}
 
 impl Elem for Line {
     fn draw(&self, draw: &DrawWrap<'_>, status: Status) {
-        let line_color = 0xff0000;
-        match status {
-            Status::Hover => {
-                draw.draw_line(DrawLineOpts {
-                    from_coord: self.from_coord,
-                    end_coord: self.end_coord,
-                    line_size: 5.,
-                    line_color,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: self.from_coord,
-                    r: 5.,
-                    line_size: 1.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: self.end_coord,
-                    r: 5.,
-                    line_size: 1.,
-                    line_color,
-                    fill_color: 0,
-                });
-            }
-            Status::Resizing(darg_point_index) => {
-                draw.draw_line(DrawLineOpts {
-                    from_coord: self.from_coord,
-                    end_coord: self.end_coord,
-                    line_size: 3.,
-                    line_color,
-                });
-
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: self.from_coord,
-                    r: 5.,
-                    line_size: 1.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: self.end_coord,
-                    r: 5.,
-                    line_size: 1.,
-                    line_color,
-                    fill_color: 0,
-                });
-            }
-            _ => {
-                draw.draw_line(DrawLineOpts {
-                    from_coord: self.from_coord,
-                    end_coord: self.end_coord,
-                    line_size: 3.,
-                    line_color,
-                });
-            }
+        let line_size = match status {
+            Status::Hover => draw.hover_size(self.line_size),
+            _ => self.line_size,
+        };
+        draw.draw_line(DrawLineOpts {
+            from_coord: self.from_coord,
+            end_coord: self.end_coord,
+            line_size,
+            line_color: self.line_color,
+        });
+        if let Status::Hover | Status::Resizing(_) = status {
+            draw.draw_handle(self.from_coord);
+            draw.draw_handle(self.end_coord);
         }
     }
 
@@ -109,19 +78,24 @@ impl Elem for Line {
         }
     }
 
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        self.from_coord = fit_coord(self.from_coord, from, to);
+        self.end_coord = fit_coord(self.end_coord, from, to);
+    }
+
     fn hover_condition(&self, mouse_point: Point) -> bool {
         let t_line = geo::Line::new(self.from_coord, self.end_coord);
         mouse_point.euclidean_distance(&t_line) < 10.
     }
 
     fn export(&self) -> String {
-        format!(
-            "{},{},{},{}",
-            self.from_coord.x, self.from_coord.y, self.end_coord.x, self.end_coord.y
-        )
+        serde_json::to_string(self).unwrap()
     }
 
     fn import(&self, content: &str) -> Box<dyn IElem> {
+        if content.starts_with('{') {
+            return Box::new(serde_json::from_str::<Line>(content).unwrap());
+        }
         let mut content = content.split(',');
         let from_x = content.next().unwrap().parse::<f64>().unwrap();
         let from_y = content.next().unwrap().parse::<f64>().unwrap();
@@ -133,9 +107,18 @@ impl Elem for Line {
                 y: from_y,
             },
             end_coord: Coordinate { x: end_x, y: end_y },
+            ..Default::default()
         })
     }
 
+    fn try_import(
+        &self,
+        content: &str,
+        _registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        Ok(Box::new(from_json::<Line>(self, content)?))
+    }
+
     fn elem_type(&self) -> String {
         "line".to_string()
     }
@@ -1,14 +1,85 @@
+pub mod connector;
+pub mod dimension;
+pub mod ellipse;
+pub mod group;
+pub mod image;
 pub mod line;
 pub mod pen;
 pub mod rect;
 pub mod text;
 
 use erased_serde::serialize_trait_object;
-use geo::{Coordinate, Point};
-use serde::{Deserialize, Serialize, Serializer};
-use std::fmt::Debug;
+use geo::{coord, BoundingRect, Coordinate, MultiPoint, Point};
+use serde::{de::DeserializeOwned, Deserialize, Serialize, Serializer};
+use std::{collections::BTreeMap, fmt::Debug};
 
-use crate::draw_wrap::DrawWrap;
+use crate::{clipboard::PasteError, draw_wrap::DrawWrap, layer::LayerId};
+
+use self::connector::Terminal;
+
+/// Stable identity of an element. Unlike its index in `Panel::elems` it
+/// survives deletions and reordering.
+pub type ElemId = u64;
+
+/// What the panel keeps about each element besides the element itself,
+/// see `Panel::metas`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElemMeta {
+    pub id: ElemId,
+    pub layer: LayerId,
+}
+
+/// Elements by their `elem_type`, used to import them.
+pub type ElemRegistry = BTreeMap<String, Box<dyn IElem>>;
+
+/// One of each element `Drawpanel` knows out of the box.
+pub fn builtin_elems() -> Vec<Box<dyn IElem>> {
+    vec![
+        Box::new(pen::Pen::default()),
+        Box::new(line::Line::default()),
+        Box::new(rect::Rect::default()),
+        Box::new(text::Text::default()),
+        Box::new(ellipse::Ellipse::default()),
+        Box::new(image::Image::default()),
+        Box::new(group::Group::default()),
+        Box::new(dimension::Dimension::default()),
+        Box::new(connector::Connector::default()),
+    ]
+}
+
+/// Reads an element stored as its serde form, for `Elem::try_import`.
+pub(crate) fn from_json<T: DeserializeOwned>(
+    elem: &dyn Elem,
+    content: &str,
+) -> Result<T, PasteError> {
+    serde_json::from_str(content)
+        .map_err(|err| PasteError::Invalid(format!("{}: {}", elem.elem_type(), err)))
+}
+
+/// Maps `coord` from the `from` box onto the `to` box. An empty side only
+/// moves.
+pub(crate) fn fit_coord(coord: Coordinate, from: geo::Rect, to: geo::Rect) -> Coordinate {
+    let (sx, sy) = fit_scale(from, to);
+    coord! {
+        x: to.min().x + (coord.x - from.min().x) * sx,
+        y: to.min().y + (coord.y - from.min().y) * sy,
+    }
+}
+
+pub(crate) fn fit_scale(from: geo::Rect, to: geo::Rect) -> (f64, f64) {
+    (
+        if from.width() > 0. {
+            to.width() / from.width()
+        } else {
+            1.
+        },
+        if from.height() > 0. {
+            to.height() / from.height()
+        } else {
+            1.
+        },
+    )
+}
 
 pub enum Status {
     Default,
@@ -33,12 +104,45 @@ pub trait Elem {
     fn edit_moving(&mut self, from_coord: Coordinate, end_coord: Coordinate);
     fn edit_resizing(&mut self, from_coord: Coordinate, end_coord: Coordinate, drag_vertex: i32);
     fn hover_condition(&self, mouse_point: Point) -> bool;
+    fn bounding_rect(&self) -> Option<geo::Rect> {
+        MultiPoint::from(self.get_vertex()).bounding_rect()
+    }
+    /// Stretches the element from the `from` box onto the `to` box, e.g.
+    /// when the group around it is resized. Only moves it by default.
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        self.edit_moving(from.center(), to.center());
+    }
     fn export(&self) -> String {
         todo!("export")
     }
     fn import(&self, content: &str) -> Box<dyn IElem> {
         todo!("import")
     }
+    /// `import` for elements that contain other elements.
+    fn import_with(&self, content: &str, registry: &ElemRegistry) -> Box<dyn IElem> {
+        self.import(content)
+    }
+    /// `import_with` for content from outside, e.g. the system clipboard,
+    /// that fails instead of panicking. Trusts the content by default.
+    fn try_import(
+        &self,
+        content: &str,
+        registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        Ok(self.import_with(content, registry))
+    }
+    /// The elements inside, for groups.
+    fn children(&self) -> Option<&[Box<dyn IElem>]> {
+        None
+    }
+    /// The ids of the elements at either end, for connectors.
+    fn ends(&self) -> Option<(Option<ElemId>, Option<ElemId>)> {
+        None
+    }
+    fn set_ends(&mut self, _source: Option<ElemId>, _target: Option<ElemId>) {}
+    /// Moves the ends of a connector onto the elements they are attached
+    /// to, see `Panel::route_connectors`.
+    fn route(&mut self, _source: Option<Terminal>, _target: Option<Terminal>) {}
     fn elem_type(&self) -> String {
         todo!("type");
     }
@@ -1,24 +1,39 @@
 use std::{default, rc::Rc};
 
 use crate::{
-    binder::{Binder, Draw, DrawCircleOpts, DrawLineOpts},
+    binder::{Binder, Draw, DrawCircleOpts, DrawLineOpts, DrawPathOpts},
+    clipboard::PasteError,
     draw_wrap::DrawWrap,
     serde_helper::{vec_coordinate, CoordinateRef},
 };
 
-use super::{Elem, IElem, Status};
+use super::{fit_coord, from_json, Elem, ElemRegistry, IElem, Status};
 
-use geo::{coord, Coordinate, EuclideanDistance, Line, LineString, Point, Polygon};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use geo::{
+    coord, BoundingRect, Contains, Coordinate, EuclideanDistance, Line, LineString, Point, Polygon,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Pen {
+    #[serde(with = "vec_coordinate")]
     pub coords: Vec<Coordinate>,
+    pub line_size: f64,
+    pub line_color: u32,
+    /// Fills the outline, closing it from the last point to the first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_color: Option<u32>,
 }
 
 impl Default for Pen {
     fn default() -> Self {
         Pen {
             coords: vec![Coordinate::default()],
+            line_size: 4.,
+            line_color: 0xff0000,
+            fill_color: None,
         }
     }
 }
@@ -27,96 +42,19 @@ impl IElem for Pen {}
 
 impl Elem for Pen {
     fn draw(&self, draw: &DrawWrap<'_>, status: Status) {
-        let line_color = 0xff0000;
-        match status {
-            Status::Hover => {
-                for (i, coord) in self.coords.iter().enumerate() {
-                    if i > 1 {
-                        let prev = self.coords.get(i - 1).unwrap();
-                        draw.draw_line(DrawLineOpts {
-                            from_coord: *prev,
-                            end_coord: *coord,
-                            line_size: 8.,
-                            line_color,
-                        });
-                        draw.draw_circle(DrawCircleOpts {
-                            center_coord: *prev,
-                            r: 4.,
-                            line_size: 0.,
-                            line_color,
-                            fill_color: line_color,
-                        });
-                    }
-                }
-                // draw.draw_circle(DrawCircleOpts {
-                //     center_coord: *self.coords.first().unwrap(),
-                //     r: 5,
-                //     line_size: 1,
-                //     line_color,
-                //     fill_color: 0,
-                // });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *self.coords.last().unwrap(),
-                    r: 8.,
-                    line_size: 1.,
-                    line_color: 0,
-                    fill_color: 0,
-                });
-            }
-            Status::Resizing(darg_point_index) => {
-                for (i, coord) in self.coords.iter().enumerate() {
-                    if i > 1 {
-                        let prev = self.coords.get(i - 1).unwrap();
-                        draw.draw_line(DrawLineOpts {
-                            from_coord: *prev,
-                            end_coord: *coord,
-                            line_size: 4.,
-                            line_color,
-                        });
-                        draw.draw_circle(DrawCircleOpts {
-                            center_coord: *coord,
-                            r: 2.,
-                            line_size: 0.,
-                            line_color,
-                            fill_color: line_color,
-                        });
-                    }
-                }
-                // draw.draw_circle(DrawCircleOpts {
-                //     center_coord: *self.coords.first().unwrap(),
-                //     r: if darg_point_index == 1 { 6 } else { 5 },
-                //     line_size: 1,
-                //     line_color,
-                //     fill_color: 0,
-                // });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *self.coords.last().unwrap(),
-                    r: 8.,
-                    line_size: 1.,
-                    line_color: 0,
-                    fill_color: 0,
-                });
-            }
-            _ => {
-                for (i, coord) in self.coords.iter().enumerate() {
-                    if i > 1 {
-                        let prev = self.coords.get(i - 1).unwrap();
-                        draw.draw_line(DrawLineOpts {
-                            from_coord: *prev,
-                            end_coord: *coord,
-                            line_size: 4.,
-                            line_color,
-                        });
-                        draw.draw_circle(DrawCircleOpts {
-                            center_coord: *prev,
-                            r: 2.,
-                            line_size: 0.,
-                            line_color,
-                            fill_color: line_color,
-                        });
-                    }
-                }
-            }
+        let line_size = match status {
+            Status::Hover => draw.hover_size(self.line_size),
+            _ => self.line_size,
+        };
+        draw.draw_path(DrawPathOpts {
+            coords: &self.coords,
+            line_size,
+            line_color: self.line_color,
+            fill_color: self.fill_color,
+            closed: false,
+        });
+        if let Status::Hover | Status::Resizing(_) = status {
+            draw.draw_handle(*self.coords.last().unwrap());
         }
     }
 
@@ -156,8 +94,22 @@ impl Elem for Pen {
         }
     }
 
+    fn bounding_rect(&self) -> Option<geo::Rect> {
+        LineString::new(self.coords.clone()).bounding_rect()
+    }
+
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        for coord in self.coords.iter_mut() {
+            *coord = fit_coord(*coord, from, to);
+        }
+    }
+
     fn hover_condition(&self, mouse_point: Point) -> bool {
-        LineString::new(self.coords.clone()).euclidean_distance(&mouse_point) < 10.
+        let line = LineString::new(self.coords.clone());
+        if self.fill_color.is_some() && Polygon::new(line.clone(), vec![]).contains(&mouse_point) {
+            return true;
+        }
+        line.euclidean_distance(&mouse_point) < 10.
     }
 
     fn elem_type(&self) -> String {
@@ -165,14 +117,13 @@ impl Elem for Pen {
     }
 
     fn export(&self) -> String {
-        let mut result = String::new();
-        for coord in self.coords.iter() {
-            result.push_str(&format!("{},{};", coord.x, coord.y));
-        }
-        result
+        serde_json::to_string(self).unwrap()
     }
 
     fn import(&self, content: &str) -> Box<dyn IElem> {
+        if content.starts_with('{') {
+            return Box::new(serde_json::from_str::<Pen>(content).unwrap());
+        }
         let mut coords = vec![];
         for coord in content.split(';') {
             if coord.is_empty() {
@@ -184,6 +135,22 @@ impl Elem for Pen {
                 y: coord[1],
             });
         }
-        Box::new(Pen { coords })
+        Box::new(Pen {
+            coords,
+            ..Default::default()
+        })
+    }
+
+    /// A pen needs at least one point.
+    fn try_import(
+        &self,
+        content: &str,
+        _registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        let pen: Pen = from_json(self, content)?;
+        if pen.coords.is_empty() {
+            return Err(PasteError::Invalid("pen: no points".to_string()));
+        }
+        Ok(Box::new(pen))
     }
 }
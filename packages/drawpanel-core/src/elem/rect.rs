@@ -1,15 +1,20 @@
 use crate::{
-    binder::{Draw, DrawCircleOpts, DrawRectOpts},
+    binder::{Draw, DrawRectOpts},
+    clipboard::PasteError,
     draw_wrap::DrawWrap,
+    serde_helper::CoordinateRef,
 };
 
-use super::{Elem, IElem, Status};
+use super::{fit_coord, fit_scale, from_json, Elem, ElemRegistry, IElem, Status};
 use educe::Educe;
 use geo::{coord, point, Coordinate, EuclideanDistance, Intersects, Point};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Educe)]
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
+#[serde(default)]
 pub struct Rect {
+    #[serde(with = "CoordinateRef")]
     pub lt_coord: Coordinate, // left top coord
     pub width: f64,
     pub height: f64,
@@ -25,111 +30,22 @@ impl IElem for Rect {}
 
 impl Elem for Rect {
     fn draw(&self, draw: &DrawWrap<'_>, status: Status) {
-        let line_color = self.line_color;
-        let line_size = self.line_size;
-        let fill_color = self.fill_color;
-        let line_style = self.line_style.clone();
-        let drag_coords = self.get_vertex();
-
-        match status {
-            Status::Hover => {
-                draw.draw_rect(DrawRectOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    line_size: line_size + 2.,
-                    line_color,
-                    fill_color,
-                    line_style,
-                });
-
-                let lt = drag_coords.get(0).unwrap();
-                let tr = drag_coords.get(1).unwrap();
-                let br = drag_coords.get(2).unwrap();
-                let bl = drag_coords.get(3).unwrap();
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *lt,
-                    r: line_size as f64 + 2.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *tr,
-                    r: line_size as f64 + 2.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *br,
-                    r: line_size as f64 + 2.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *bl,
-                    r: line_size as f64 + 2.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-            }
-            Status::Resizing(index) => {
-                draw.draw_rect(DrawRectOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    line_size,
-                    line_color,
-                    fill_color,
-                    line_style,
-                });
-
-                let lt = drag_coords.get(0).unwrap();
-                let tr = drag_coords.get(1).unwrap();
-                let br = drag_coords.get(2).unwrap();
-                let bl = drag_coords.get(3).unwrap();
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *lt,
-                    r: line_size as f64 + 2.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *tr,
-                    r: line_size as f64 + 2.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *br,
-                    r: line_size as f64 + 2.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *bl,
-                    r: line_size as f64 + 2.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-            }
-            _ => {
-                draw.draw_rect(DrawRectOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    line_size,
-                    line_color,
-                    fill_color,
-                    line_style,
-                });
+        let line_size = match status {
+            Status::Hover => draw.hover_size(self.line_size),
+            _ => self.line_size,
+        };
+        draw.draw_rect(DrawRectOpts {
+            left_top_coord: self.lt_coord,
+            width: self.width,
+            height: self.height,
+            line_size,
+            line_color: self.line_color,
+            fill_color: self.fill_color,
+            line_style: self.line_style.clone(),
+        });
+        if let Status::Hover | Status::Resizing(_) = status {
+            for coord in self.get_vertex() {
+                draw.draw_handle(coord);
             }
         }
     }
@@ -223,6 +139,13 @@ impl Elem for Rect {
         }
     }
 
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        let (sx, sy) = fit_scale(from, to);
+        self.lt_coord = fit_coord(self.lt_coord, from, to);
+        self.width *= sx;
+        self.height *= sy;
+    }
+
     fn hover_condition(&self, mouse_point: Point) -> bool {
         let vertex = self.get_vertex();
         geo::Rect::new(vertex[0], vertex[2]).intersects(&mouse_point)
@@ -237,13 +160,13 @@ impl Elem for Rect {
     }
 
     fn export(&self) -> String {
-        format!(
-            "{},{},{},{}",
-            self.lt_coord.x, self.lt_coord.y, self.width, self.height
-        )
+        serde_json::to_string(self).unwrap()
     }
 
     fn import(&self, content: &str) -> Box<dyn IElem> {
+        if content.starts_with('{') {
+            return Box::new(serde_json::from_str::<Rect>(content).unwrap());
+        }
         let mut coords = content.split(',');
         let lt_x = coords.next().unwrap().parse::<f64>().unwrap();
         let lt_y = coords.next().unwrap().parse::<f64>().unwrap();
@@ -257,9 +180,17 @@ impl Elem for Rect {
             ..Default::default()
         })
     }
+
+    fn try_import(
+        &self,
+        content: &str,
+        _registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        Ok(Box::new(from_json::<Rect>(self, content)?))
+    }
 }
 
-#[derive(Debug, Clone, Educe)]
+#[derive(Debug, Clone, PartialEq, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 pub enum LineStyle {
     #[educe(Default)]
@@ -1,171 +1,50 @@
 use crate::{
-    binder::{Draw, DrawCircleOpts, DrawRectOpts, DrawTextOpts},
+    binder::{Draw, DrawTextOpts},
+    clipboard::PasteError,
     draw_wrap::DrawWrap,
     serde_helper::CoordinateRef,
 };
 
-use super::{rect::LineStyle, Elem, IElem, Status};
+use super::{fit_coord, fit_scale, from_json, rect::LineStyle, Elem, ElemRegistry, IElem, Status};
 use geo::{coord, point, Coordinate, EuclideanDistance, Intersects, Point};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Text {
-    lt_coord: Coordinate, // left top coord
-    width: f64,
-    height: f64,
-    content: String,
+    #[serde(with = "CoordinateRef")]
+    pub lt_coord: Coordinate, // left top coord
+    pub width: f64,
+    pub height: f64,
+    pub content: String,
+    pub font_color: u32,
 }
 
 impl IElem for Text {}
 
 impl Elem for Text {
     fn draw(&self, draw: &DrawWrap, status: Status) {
-        let line_color = 0xff0000;
-
-        let drag_coords = self.get_vertex();
-
         let mut font_size = (self.height) / calc_string_lines(&self.content) as f64;
 
         if font_size < 1. {
             (font_size = 1.);
         }
 
-        match status {
-            Status::Default => {
-                draw.draw_text(DrawTextOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    content: &self.content,
-                    font_size,
-                    font_space: 0,
-                    font_color: 0,
-                });
-            }
-            Status::Hover => {
-                draw.draw_rect(DrawRectOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    line_size: 3.,
-                    line_color: line_color,
-                    fill_color: None,
-                    line_style: LineStyle::Solid,
-                });
-                draw.draw_text(DrawTextOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    content: &self.content,
-                    font_size,
-                    font_space: 0,
-                    font_color: 0,
-                });
-
-                let lt = drag_coords.get(0).unwrap();
-                let tr = drag_coords.get(1).unwrap();
-                let br = drag_coords.get(2).unwrap();
-                let bl = drag_coords.get(3).unwrap();
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *lt,
-                    r: 5.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *tr,
-                    r: 5.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *br,
-                    r: 5.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *bl,
-                    r: 5.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-            }
-            Status::Resizing(index) => {
-                draw.draw_rect(DrawRectOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    line_size: 3.,
-                    line_color: line_color,
-                    fill_color: None,
-                    line_style: LineStyle::Solid,
-                });
-                draw.draw_text(DrawTextOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    content: &self.content,
-                    font_size,
-                    font_space: 0,
-                    font_color: 0,
-                });
-
-                let lt = drag_coords.get(0).unwrap();
-                let tr = drag_coords.get(1).unwrap();
-                let br = drag_coords.get(2).unwrap();
-                let bl = drag_coords.get(3).unwrap();
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *lt,
-                    r: 5.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *tr,
-                    r: 5.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *br,
-                    r: 5.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-                draw.draw_circle(DrawCircleOpts {
-                    center_coord: *bl,
-                    r: 5.,
-                    line_size: 0.,
-                    line_color,
-                    fill_color: 0,
-                });
-            }
-            Status::Creating => {
-                draw.draw_rect(DrawRectOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    line_size: 3.,
-                    line_color: line_color,
-                    fill_color: None,
-                    line_style: LineStyle::Solid,
-                });
-                draw.draw_text(DrawTextOpts {
-                    left_top_coord: self.lt_coord,
-                    width: self.width,
-                    height: self.height,
-                    content: &self.content,
-                    font_size,
-                    font_space: 0,
-                    font_color: 0,
-                });
+        if let Status::Hover | Status::Resizing(_) | Status::Creating = status {
+            draw.draw_outline(self.lt_coord, self.width, self.height, LineStyle::Solid);
+        }
+        draw.draw_text(DrawTextOpts {
+            left_top_coord: self.lt_coord,
+            width: self.width,
+            height: self.height,
+            content: &self.content,
+            font_size,
+            font_space: 0,
+            font_color: self.font_color,
+        });
+        if let Status::Hover | Status::Resizing(_) = status {
+            for coord in self.get_vertex() {
+                draw.draw_handle(coord);
             }
         }
     }
@@ -269,6 +148,13 @@ impl Elem for Text {
         }
     }
 
+    fn fit(&mut self, from: geo::Rect, to: geo::Rect) {
+        let (sx, sy) = fit_scale(from, to);
+        self.lt_coord = fit_coord(self.lt_coord, from, to);
+        self.width *= sx;
+        self.height *= sy;
+    }
+
     fn hover_condition(&self, mouse_point: Point) -> bool {
         let vertex = self.get_vertex();
         geo::Rect::new(vertex[0], vertex[2]).intersects(&mouse_point)
@@ -283,14 +169,14 @@ impl Elem for Text {
     }
 
     fn export(&self) -> String {
-        format!(
-            "{},{},{},{},{}",
-            self.lt_coord.x, self.lt_coord.y, self.width, self.height, self.content
-        )
+        serde_json::to_string(self).unwrap()
     }
 
     fn import(&self, content: &str) -> Box<dyn IElem> {
-        let mut t = content.split(",");
+        if content.starts_with('{') {
+            return Box::new(serde_json::from_str::<Text>(content).unwrap());
+        }
+        let mut t = content.splitn(5, ",");
         let x = t.next().unwrap().parse::<f64>().unwrap();
         let y = t.next().unwrap().parse::<f64>().unwrap();
         let w = t.next().unwrap().parse::<f64>().unwrap();
@@ -301,8 +187,17 @@ impl Elem for Text {
             width: w,
             height: h,
             content: String::from(c),
+            ..Default::default()
         })
     }
+
+    fn try_import(
+        &self,
+        content: &str,
+        _registry: &ElemRegistry,
+    ) -> Result<Box<dyn IElem>, PasteError> {
+        Ok(Box::new(from_json::<Text>(self, content)?))
+    }
 }
 
 fn calc_string_lines(text: &str) -> usize {
@@ -0,0 +1,202 @@
+use geo::Coordinate;
+
+use crate::{
+    elem::{
+        connector::Connector,
+        ellipse::Ellipse,
+        group::flatten,
+        image::Image,
+        line::Line,
+        pen::Pen,
+        rect::{LineStyle, Rect},
+        text::Text,
+    },
+    panel::Panel,
+};
+
+use super::{ExcalidrawAppState, ExcalidrawBinary, ExcalidrawElement, ExcalidrawFile};
+
+/// Writes the panel as an `.excalidraw` file. Shapes are exported with
+/// `roughness` 0 so they look the same as in drawpanel. Elements without an
+/// Excalidraw counterpart are kept as their dashed bounding box. Groups
+/// become Excalidraw groups.
+pub fn export_excalidraw(panel: &Panel) -> String {
+    let mut file = ExcalidrawFile {
+        file_type: "excalidraw".to_string(),
+        version: 2,
+        source: "drawpanel".to_string(),
+        app_state: ExcalidrawAppState {
+            view_background_color: Some("#ffffff".to_string()),
+        },
+        ..Default::default()
+    };
+
+    for (i, (elem, path)) in flatten(&panel.elems).into_iter().enumerate() {
+        let base = ExcalidrawElement {
+            id: format!("drawpanel-{}", i),
+            seed: i as u32 + 1,
+            roughness: 0.,
+            // Innermost group first.
+            group_ids: path
+                .iter()
+                .rev()
+                .map(|g| format!("drawpanel-group-{}", g))
+                .collect(),
+            ..Default::default()
+        };
+        let content = elem.export();
+        let element = match elem.elem_type().as_str() {
+            "rect" => {
+                let rect: Rect = serde_json::from_str(&content).unwrap();
+                shape(base, "rectangle", &rect)
+            }
+            "ellipse" => {
+                let ellipse: Ellipse = serde_json::from_str(&content).unwrap();
+                shape(base, "ellipse", &ellipse.rect)
+            }
+            "text" => {
+                let text: Text = serde_json::from_str(&content).unwrap();
+                let lines = text.content.split('\n').count() as f64;
+                ExcalidrawElement {
+                    elem_type: "text".to_string(),
+                    x: text.lt_coord.x,
+                    y: text.lt_coord.y,
+                    width: text.width,
+                    height: text.height,
+                    stroke_color: color(text.font_color),
+                    font_size: Some(text.height / lines),
+                    font_family: Some(1),
+                    text_align: Some("center".to_string()),
+                    vertical_align: Some("middle".to_string()),
+                    line_height: Some(1.),
+                    text: Some(text.content),
+                    ..base
+                }
+            }
+            "image" => {
+                let image: Image = serde_json::from_str(&content).unwrap();
+                let file_id = format!("drawpanel-file-{}", i);
+                let mime_type = image
+                    .data
+                    .strip_prefix("data:")
+                    .and_then(|d| d.split(|c| c == ';' || c == ',').next())
+                    .unwrap_or("image/png")
+                    .to_string();
+                file.files.insert(
+                    file_id.clone(),
+                    ExcalidrawBinary {
+                        id: file_id.clone(),
+                        mime_type,
+                        data_url: image.data,
+                    },
+                );
+                ExcalidrawElement {
+                    elem_type: "image".to_string(),
+                    x: image.rect.lt_coord.x,
+                    y: image.rect.lt_coord.y,
+                    width: image.rect.width,
+                    height: image.rect.height,
+                    file_id: Some(file_id),
+                    status: Some("saved".to_string()),
+                    ..base
+                }
+            }
+            "line" => {
+                let line: Line = serde_json::from_str(&content).unwrap();
+                ExcalidrawElement {
+                    stroke_color: color(line.line_color),
+                    stroke_width: line.line_size,
+                    ..linear(base, "line", &[line.from_coord, line.end_coord])
+                }
+            }
+            "connector" => {
+                let connector: Connector = serde_json::from_str(&content).unwrap();
+                ExcalidrawElement {
+                    stroke_color: color(connector.line_color),
+                    stroke_width: connector.line_size,
+                    ..linear(base, "line", &connector.coords)
+                }
+            }
+            "pen" => {
+                let pen: Pen = serde_json::from_str(&content).unwrap();
+                ExcalidrawElement {
+                    stroke_color: color(pen.line_color),
+                    stroke_width: pen.line_size,
+                    simulate_pressure: Some(true),
+                    ..linear(base, "freedraw", &pen.coords)
+                }
+            }
+            _ => match elem.bounding_rect() {
+                Some(bounds) => {
+                    let rect = Rect {
+                        lt_coord: bounds.min(),
+                        width: bounds.width(),
+                        height: bounds.height(),
+                        line_size: 1.,
+                        line_color: 0x999999,
+                        fill_color: None,
+                        line_style: LineStyle::Dotted,
+                    };
+                    shape(base, "rectangle", &rect)
+                }
+                None => continue,
+            },
+        };
+        file.elements.push(element);
+    }
+
+    serde_json::to_string(&file).unwrap()
+}
+
+fn shape(base: ExcalidrawElement, elem_type: &str, rect: &Rect) -> ExcalidrawElement {
+    ExcalidrawElement {
+        elem_type: elem_type.to_string(),
+        x: rect.lt_coord.x,
+        y: rect.lt_coord.y,
+        width: rect.width,
+        height: rect.height,
+        stroke_color: if rect.line_size > 0. {
+            color(rect.line_color)
+        } else {
+            "transparent".to_string()
+        },
+        background_color: match rect.fill_color {
+            Some(fill_color) => color(fill_color),
+            None => "transparent".to_string(),
+        },
+        stroke_width: rect.line_size,
+        stroke_style: match rect.line_style {
+            LineStyle::Solid => "solid".to_string(),
+            LineStyle::Dotted => "dashed".to_string(),
+        },
+        ..base
+    }
+}
+
+/// Points are stored relative to the first one.
+fn linear(base: ExcalidrawElement, elem_type: &str, coords: &[Coordinate]) -> ExcalidrawElement {
+    let origin = coords.first().copied().unwrap_or_default();
+    let points: Vec<[f64; 2]> = coords
+        .iter()
+        .map(|c| [c.x - origin.x, c.y - origin.y])
+        .collect();
+    let (min_x, max_x) = points
+        .iter()
+        .fold((0f64, 0f64), |(min, max), p| (min.min(p[0]), max.max(p[0])));
+    let (min_y, max_y) = points
+        .iter()
+        .fold((0f64, 0f64), |(min, max), p| (min.min(p[1]), max.max(p[1])));
+    ExcalidrawElement {
+        elem_type: elem_type.to_string(),
+        x: origin.x,
+        y: origin.y,
+        width: max_x - min_x,
+        height: max_y - min_y,
+        points: Some(points),
+        ..base
+    }
+}
+
+fn color(color: u32) -> String {
+    format!("#{:06x}", color & 0xffffff)
+}
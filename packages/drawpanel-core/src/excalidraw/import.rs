@@ -0,0 +1,286 @@
+use std::f64::consts::PI;
+
+use geo::{coord, Coordinate};
+
+use crate::{
+    elem::{
+        ellipse::Ellipse,
+        group::Group,
+        image::Image,
+        line::Line,
+        pen::Pen,
+        rect::{LineStyle, Rect},
+        text::Text,
+        IElem,
+    },
+    panel::{ImportMode, Panel},
+};
+
+use super::{ExcalidrawElement, ExcalidrawFile, ExcalidrawReport};
+
+/// Converts an `.excalidraw` file into native elements and adds them to
+/// `panel`. Rotated shapes become `Pen` outlines, arrowheads become short
+/// `Pen` strokes. Types that can't be translated (frames, embeds, ...) are
+/// left out and listed in the report. Grouped elements end up in nested
+/// `Group`s.
+pub fn import_excalidraw(
+    panel: &mut Panel,
+    data: &str,
+    mode: ImportMode,
+) -> Result<ExcalidrawReport, serde_json::Error> {
+    let file: ExcalidrawFile = serde_json::from_str(data)?;
+    let mut report = ExcalidrawReport::default();
+    let mut elems: Vec<Box<dyn IElem>> = vec![];
+    // Outermost group first, for each element in `elems`.
+    let mut paths: Vec<Vec<String>> = vec![];
+
+    for elem in file.elements.iter().filter(|e| !e.is_deleted) {
+        let before = elems.len();
+        match elem.elem_type.as_str() {
+            "rectangle" => shape(elem, &mut elems, false),
+            "ellipse" => shape(elem, &mut elems, true),
+            "diamond" => {
+                let (w, h) = (elem.width, elem.height);
+                let mut coords = outline(
+                    elem,
+                    &[(w / 2., 0.), (w, h / 2.), (w / 2., h), (0., h / 2.)],
+                    (w / 2., h / 2.),
+                );
+                coords.push(coords[0]);
+                elems.push(Box::new(Pen {
+                    coords,
+                    line_size: elem.stroke_width,
+                    line_color: color(&elem.stroke_color).unwrap_or(0),
+                    fill_color: None,
+                }));
+            }
+            "line" | "arrow" => linear(elem, &mut elems),
+            "freedraw" => {
+                let points = elem.points.clone().unwrap_or_default();
+                let points: Vec<(f64, f64)> = points.iter().map(|p| (p[0], p[1])).collect();
+                if !points.is_empty() {
+                    elems.push(Box::new(Pen {
+                        coords: outline(elem, &points, center(&points)),
+                        line_size: elem.stroke_width,
+                        line_color: color(&elem.stroke_color).unwrap_or(0),
+                        fill_color: None,
+                    }));
+                }
+            }
+            "text" => {
+                let content = elem.text.clone().unwrap_or_default();
+                let font_size = elem.font_size.unwrap_or(20.);
+                let lines = content.split('\n').count() as f64;
+                // `Text` derives its font size from the height of the box,
+                // Excalidraw adds the line height on top.
+                let height = font_size * lines;
+                elems.push(Box::new(Text {
+                    lt_coord: coord! {
+                        x: elem.x,
+                        y: elem.y + (elem.height - height) / 2.,
+                    },
+                    width: elem.width,
+                    height,
+                    content,
+                    font_color: color(&elem.stroke_color).unwrap_or(0),
+                }));
+            }
+            "image" => {
+                let data = elem
+                    .file_id
+                    .as_ref()
+                    .and_then(|id| file.files.get(id))
+                    .map(|f| f.data_url.clone())
+                    .unwrap_or_default();
+                elems.push(Box::new(Image {
+                    rect: Rect {
+                        lt_coord: coord! { x: elem.x, y: elem.y },
+                        width: elem.width,
+                        height: elem.height,
+                        ..Default::default()
+                    },
+                    data,
+                }));
+            }
+            _ => {
+                *report
+                    .unsupported
+                    .entry(elem.elem_type.clone())
+                    .or_insert(0) += 1;
+            }
+        }
+        if elems.len() > before {
+            for id in elem.group_ids.iter() {
+                if !report.groups.contains(id) {
+                    report.groups.push(id.clone());
+                }
+            }
+        }
+        let path: Vec<String> = elem.group_ids.iter().rev().cloned().collect();
+        paths.resize(elems.len(), path);
+    }
+
+    let elems = nest(elems.into_iter().zip(paths).collect(), 0);
+    report.imported = elems.len();
+    panel.add_imported(elems, mode);
+    Ok(report)
+}
+
+/// Puts the elements sharing a group id at `depth` into one `Group`, where
+/// the first of them was.
+fn nest(items: Vec<(Box<dyn IElem>, Vec<String>)>, depth: usize) -> Vec<Box<dyn IElem>> {
+    let mut slots: Vec<(Option<String>, Vec<(Box<dyn IElem>, Vec<String>)>)> = vec![];
+    for (elem, path) in items {
+        let id = path.get(depth).cloned();
+        let slot = match &id {
+            Some(id) => slots.iter().position(|(i, _)| i.as_ref() == Some(id)),
+            None => None,
+        };
+        match slot {
+            Some(slot) => slots[slot].1.push((elem, path)),
+            None => slots.push((id, vec![(elem, path)])),
+        }
+    }
+    slots
+        .into_iter()
+        .flat_map(|(id, members)| match id {
+            Some(_) => vec![Box::new(Group {
+                elems: nest(members, depth + 1),
+            }) as Box<dyn IElem>],
+            None => members.into_iter().map(|(elem, _)| elem).collect(),
+        })
+        .collect()
+}
+
+fn shape(elem: &ExcalidrawElement, elems: &mut Vec<Box<dyn IElem>>, ellipse: bool) {
+    let line_color = color(&elem.stroke_color).unwrap_or(0);
+    if elem.angle != 0. {
+        let (w, h) = (elem.width, elem.height);
+        let points: Vec<(f64, f64)> = if ellipse {
+            (0..=64)
+                .map(|i| {
+                    let t = i as f64 / 64. * 2. * PI;
+                    (w / 2. * (1. + t.cos()), h / 2. * (1. + t.sin()))
+                })
+                .collect()
+        } else {
+            vec![(0., 0.), (w, 0.), (w, h), (0., h), (0., 0.)]
+        };
+        elems.push(Box::new(Pen {
+            coords: outline(elem, &points, (w / 2., h / 2.)),
+            line_size: elem.stroke_width,
+            line_color,
+            fill_color: None,
+        }));
+        return;
+    }
+    let rect = Rect {
+        lt_coord: coord! { x: elem.x, y: elem.y },
+        width: elem.width,
+        height: elem.height,
+        line_size: elem.stroke_width,
+        line_color,
+        fill_color: color(&elem.background_color),
+        line_style: match elem.stroke_style.as_str() {
+            "dashed" | "dotted" => LineStyle::Dotted,
+            _ => LineStyle::Solid,
+        },
+    };
+    if ellipse {
+        elems.push(Box::new(Ellipse { rect }));
+    } else {
+        elems.push(Box::new(rect));
+    }
+}
+
+fn linear(elem: &ExcalidrawElement, elems: &mut Vec<Box<dyn IElem>>) {
+    let points = elem.points.clone().unwrap_or_default();
+    let points: Vec<(f64, f64)> = points.iter().map(|p| (p[0], p[1])).collect();
+    let coords = outline(elem, &points, center(&points));
+    if coords.len() < 2 {
+        return;
+    }
+    let line_size = elem.stroke_width;
+    let line_color = color(&elem.stroke_color).unwrap_or(0);
+    if coords.len() == 2 {
+        elems.push(Box::new(Line {
+            from_coord: coords[0],
+            end_coord: coords[1],
+            line_size,
+            line_color,
+        }));
+    } else {
+        elems.push(Box::new(Pen {
+            coords: coords.clone(),
+            line_size,
+            line_color,
+            fill_color: None,
+        }));
+    }
+
+    let n = coords.len();
+    let heads = [
+        (&elem.start_arrowhead, coords[0], coords[1]),
+        (&elem.end_arrowhead, coords[n - 1], coords[n - 2]),
+    ];
+    for (head, tip, from) in heads {
+        if head.is_none() {
+            continue;
+        }
+        let angle = (from.y - tip.y).atan2(from.x - tip.x);
+        let length = 10. + line_size * 3.;
+        let wing = |a: f64| coord! { x: tip.x + length * a.cos(), y: tip.y + length * a.sin() };
+        elems.push(Box::new(Pen {
+            coords: vec![wing(angle - PI / 6.), tip, wing(angle + PI / 6.)],
+            line_size,
+            line_color,
+            fill_color: None,
+        }));
+    }
+}
+
+/// Moves points given relative to the element to panel coordinates,
+/// rotating them around `(cx, cy)`, also relative to the element.
+fn outline(
+    elem: &ExcalidrawElement,
+    points: &[(f64, f64)],
+    (cx, cy): (f64, f64),
+) -> Vec<Coordinate> {
+    let (sin, cos) = elem.angle.sin_cos();
+    points
+        .iter()
+        .map(|(x, y)| {
+            let (dx, dy) = (x - cx, y - cy);
+            coord! {
+                x: elem.x + cx + dx * cos - dy * sin,
+                y: elem.y + cy + dx * sin + dy * cos,
+            }
+        })
+        .collect()
+}
+
+/// The center of the points' bounds, which lines and freehand strokes turn
+/// around. Their points can go left of or above the element's origin.
+fn center(points: &[(f64, f64)]) -> (f64, f64) {
+    let (min_x, max_x) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), p| {
+        (min.min(p.0), max.max(p.0))
+    });
+    let (min_y, max_y) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), p| {
+        (min.min(p.1), max.max(p.1))
+    });
+    ((min_x + max_x) / 2., (min_y + max_y) / 2.)
+}
+
+/// `#rgb`, `#rrggbb` or `#rrggbbaa`. `None` for `transparent`.
+fn color(value: &str) -> Option<u32> {
+    let hex = value.strip_prefix('#')?;
+    match hex.len() {
+        3 => {
+            let c = u32::from_str_radix(hex, 16).ok()?;
+            let (r, g, b) = ((c >> 8) & 0xf, (c >> 4) & 0xf, c & 0xf);
+            Some((r * 0x11) << 16 | (g * 0x11) << 8 | b * 0x11)
+        }
+        6 | 8 => u32::from_str_radix(&hex[..6], 16).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+pub mod export;
+pub mod import;
+
+/// A `.excalidraw` file. Only the fields drawpanel can use are kept,
+/// Excalidraw fills in the rest when it loads the file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExcalidrawFile {
+    #[serde(rename = "type")]
+    pub file_type: String,
+    pub version: u32,
+    pub source: String,
+    pub elements: Vec<ExcalidrawElement>,
+    pub app_state: ExcalidrawAppState,
+    pub files: HashMap<String, ExcalidrawBinary>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExcalidrawAppState {
+    pub view_background_color: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExcalidrawBinary {
+    pub id: String,
+    pub mime_type: String,
+    #[serde(rename = "dataURL")]
+    pub data_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExcalidrawElement {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub elem_type: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Clockwise rotation around the center, in radians.
+    pub angle: f64,
+    pub stroke_color: String,
+    pub background_color: String,
+    pub fill_style: String,
+    pub stroke_width: f64,
+    pub stroke_style: String,
+    pub roughness: f64,
+    pub opacity: f64,
+    pub group_ids: Vec<String>,
+    pub seed: u32,
+    pub version: u32,
+    pub is_deleted: bool,
+
+    // text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertical_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_height: Option<f64>,
+
+    // line, arrow and freedraw, relative to `x` and `y`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub points: Option<Vec<[f64; 2]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_arrowhead: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_arrowhead: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulate_pressure: Option<bool>,
+
+    // image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+impl Default for ExcalidrawElement {
+    fn default() -> Self {
+        ExcalidrawElement {
+            id: String::new(),
+            elem_type: String::new(),
+            x: 0.,
+            y: 0.,
+            width: 0.,
+            height: 0.,
+            angle: 0.,
+            stroke_color: "#1e1e1e".to_string(),
+            background_color: "transparent".to_string(),
+            fill_style: "solid".to_string(),
+            stroke_width: 2.,
+            stroke_style: "solid".to_string(),
+            roughness: 1.,
+            opacity: 100.,
+            group_ids: vec![],
+            seed: 1,
+            version: 1,
+            is_deleted: false,
+            text: None,
+            font_size: None,
+            font_family: None,
+            text_align: None,
+            vertical_align: None,
+            line_height: None,
+            points: None,
+            start_arrowhead: None,
+            end_arrowhead: None,
+            simulate_pressure: None,
+            file_id: None,
+            status: None,
+        }
+    }
+}
+
+/// What `import_excalidraw` did with the file.
+#[derive(Debug, Clone, Default)]
+pub struct ExcalidrawReport {
+    /// Number of elements added to the panel.
+    pub imported: usize,
+    /// Element types without a drawpanel counterpart, with how many of each
+    /// were left out.
+    pub unsupported: BTreeMap<String, usize>,
+    /// Ids of the groups that were imported as `Group` elements.
+    pub groups: Vec<String>,
+}
@@ -0,0 +1,209 @@
+use std::time::{Duration, Instant};
+
+use geo::Coordinate;
+
+use crate::binder::{EventMouseButton, EventType};
+
+/// Identifies a finger for as long as it touches, see `EventType::TouchStart`.
+pub type TouchId = u64;
+
+/// What the panel should do for the touches seen so far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GestureAction {
+    /// Handled like the mouse event, at the given binder coordinates.
+    Mouse(EventType, Coordinate),
+    /// Zooms by `factor` around `anchor`, in binder coordinates.
+    Zoom {
+        factor: f64,
+        anchor: Coordinate,
+    },
+    Pan(Coordinate),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GestureState {
+    Idle,
+    /// One finger down that hasn't moved far yet. Could still become a
+    /// drag, a pinch or a long press.
+    Pending {
+        start: Coordinate,
+        since: Instant,
+    },
+    /// One finger dragging like the left mouse button.
+    Pointer,
+    /// Two fingers zooming and panning.
+    Pinch,
+    /// Ignores the fingers until all are lifted, after a long press or a
+    /// pinch.
+    Done,
+}
+
+/// Turns touch events into mouse events, pinch zoom and two finger pan, so
+/// every binder gets the same gestures. One finger acts as the left mouse
+/// button, holding it still for `long_press` acts as a right click.
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    /// The fingers down, in the order they touched.
+    touches: Vec<(TouchId, Coordinate)>,
+    state: GestureState,
+    pub long_press: Duration,
+    /// How far a finger can move before it no longer counts as holding
+    /// still, in binder coordinates.
+    pub slop: f64,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        GestureRecognizer {
+            touches: vec![],
+            state: GestureState::Idle,
+            long_press: Duration::from_millis(500),
+            slop: 8.,
+        }
+    }
+}
+
+impl GestureRecognizer {
+    /// Feeds a `TouchStart`, `TouchMove` or `TouchEnd` event. Other events
+    /// are ignored.
+    pub fn handle(
+        &mut self,
+        event_type: EventType,
+        coord: Coordinate,
+        now: Instant,
+    ) -> Vec<GestureAction> {
+        let mut actions = vec![];
+        match event_type {
+            EventType::TouchStart(id) => {
+                self.touches.push((id, coord));
+                match (self.state, self.touches.len()) {
+                    (GestureState::Idle, 1) => {
+                        self.state = GestureState::Pending {
+                            start: coord,
+                            since: now,
+                        };
+                    }
+                    (GestureState::Pending { .. }, 2) => self.state = GestureState::Pinch,
+                    (GestureState::Pointer, 2) => {
+                        // The drag so far stays, the rest is a pinch.
+                        let first = self.touches[0].1;
+                        actions.push(release(first));
+                        self.state = GestureState::Pinch;
+                    }
+                    _ => {}
+                }
+            }
+            EventType::TouchMove(id) => {
+                let (before, index) = match self.touches.iter().position(|(i, _)| *i == id) {
+                    Some(index) => (self.pinch(), index),
+                    None => return actions,
+                };
+                self.touches[index].1 = coord;
+                match self.state {
+                    GestureState::Pending { start, .. } if index == 0 => {
+                        let moved = coord - start;
+                        if moved.x.hypot(moved.y) > self.slop {
+                            actions.push(GestureAction::Mouse(
+                                EventType::Move(EventMouseButton::None),
+                                start,
+                            ));
+                            actions.push(GestureAction::Mouse(
+                                EventType::Push(EventMouseButton::Left),
+                                start,
+                            ));
+                            actions.push(drag(coord));
+                            self.state = GestureState::Pointer;
+                        }
+                    }
+                    GestureState::Pointer if index == 0 => actions.push(drag(coord)),
+                    GestureState::Pinch if index < 2 => {
+                        let ((centroid, distance), (old_centroid, old_distance)) =
+                            (self.pinch(), before);
+                        actions.push(GestureAction::Pan(centroid - old_centroid));
+                        if old_distance > 0. {
+                            actions.push(GestureAction::Zoom {
+                                factor: distance / old_distance,
+                                anchor: centroid,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            EventType::TouchEnd(id) => {
+                let index = match self.touches.iter().position(|(i, _)| *i == id) {
+                    Some(index) => index,
+                    None => return actions,
+                };
+                self.touches.remove(index);
+                match self.state {
+                    GestureState::Pending { start, .. } => {
+                        // A tap is a click.
+                        actions.push(GestureAction::Mouse(
+                            EventType::Move(EventMouseButton::None),
+                            start,
+                        ));
+                        actions.push(GestureAction::Mouse(
+                            EventType::Push(EventMouseButton::Left),
+                            start,
+                        ));
+                        actions.push(release(start));
+                        self.state = GestureState::Done;
+                    }
+                    GestureState::Pointer if index == 0 => {
+                        actions.push(release(coord));
+                        self.state = GestureState::Done;
+                    }
+                    GestureState::Pinch if index < 2 => self.state = GestureState::Done,
+                    _ => {}
+                }
+                if self.touches.is_empty() {
+                    self.state = GestureState::Idle;
+                }
+            }
+            _ => {}
+        }
+        actions
+    }
+
+    /// Reports a long press once a finger has been held still long enough.
+    /// Binders call it regularly while `is_pending`.
+    pub fn poll(&mut self, now: Instant) -> Vec<GestureAction> {
+        match self.state {
+            GestureState::Pending { start, since } if now - since >= self.long_press => {
+                self.state = GestureState::Done;
+                vec![
+                    GestureAction::Mouse(EventType::Move(EventMouseButton::None), start),
+                    GestureAction::Mouse(EventType::Push(EventMouseButton::Right), start),
+                    GestureAction::Mouse(EventType::Released(EventMouseButton::Right), start),
+                ]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Whether a finger is down that may still turn into a long press.
+    pub fn is_pending(&self) -> bool {
+        matches!(self.state, GestureState::Pending { .. })
+    }
+
+    /// Center of and distance between the first two fingers.
+    fn pinch(&self) -> (Coordinate, f64) {
+        match self.touches.as_slice() {
+            [(_, a), (_, b), ..] => {
+                let d = *b - *a;
+                ((*a + *b) / 2., d.x.hypot(d.y))
+            }
+            [(_, a)] => (*a, 0.),
+            [] => (Coordinate::zero(), 0.),
+        }
+    }
+}
+
+fn drag(coord: Coordinate) -> GestureAction {
+    GestureAction::Mouse(EventType::Drag(EventMouseButton::Left), coord)
+}
+
+fn release(coord: Coordinate) -> GestureAction {
+    GestureAction::Mouse(EventType::Released(EventMouseButton::Left), coord)
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// Stable identity of a layer, see `ElemMeta::layer`.
+pub type LayerId = u64;
+
+/// A named slice of the paint order. `Panel::layers` lists them from back
+/// to front and keeps each layer's elements together in `Panel::elems`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    pub id: LayerId,
+    pub name: String,
+    /// Hidden layers are neither drawn nor hit tested.
+    pub visible: bool,
+    /// Locked layers are drawn but can't be hovered or selected.
+    pub locked: bool,
+    /// From 0 (transparent) to 1, applied through `Draw::set_opacity`.
+    pub opacity: f64,
+}
+
+impl Layer {
+    pub fn new(id: LayerId, name: &str) -> Self {
+        Layer {
+            id,
+            name: name.to_string(),
+            visible: true,
+            locked: false,
+            opacity: 1.,
+        }
+    }
+
+    /// Whether its elements react to the mouse.
+    pub fn is_editable(&self) -> bool {
+        self.visible && !self.locked
+    }
+}
@@ -1,72 +1,1376 @@
 pub mod binder;
+pub mod change;
+pub mod clipboard;
 mod draw_wrap;
+pub mod drawio;
 pub mod drawpanel;
 pub mod elem;
+pub mod excalidraw;
+pub mod gesture;
+pub mod layer;
+pub mod minimap;
+pub mod page;
 pub mod panel;
+pub mod recording;
+pub mod ruler;
 pub mod serde_helper;
+pub mod session;
+pub mod svg;
+pub mod theme;
+pub mod tool;
+pub mod units;
+pub mod viewport;
 
-// #[cfg(test)]
-// mod tests {
-//     use std::rc::Rc;
-
-//     use geo::coord;
-//     use mockall::predicate::*;
-//     use mockall::*;
-
-//     use crate::{
-//         drawpanel::{self, Drawpanel},
-//         elem::line::Line,
-//         render::{Draw, EventType, Render},
-//     };
-
-//     static mut is_run: bool = false;
-
-//     #[derive(Default)]
-//     struct TestRender;
-
-//     impl Render for TestRender {
-//         fn bind_draw<F: FnMut()>(&mut self, mut trigger: F) {
-//             trigger();
-//             unsafe {
-//                 is_run = true;
-//             }
-//         }
-
-//         fn bind_event<F: FnMut(EventType, geo::Coordinate)>(&self, mut trigger: F) {
-//             trigger(EventType::Push, coord! {x: 0., y:0.});
-//         }
-
-//         fn draw(&self) -> Box<dyn Draw> {
-//             Box::new(TestDraw::default())
-//         }
-//     }
-
-//     #[derive(Default)]
-//     struct TestDraw;
-
-//     impl Draw for TestDraw {
-//         fn draw_line(&self, opts: crate::render::DrawLineOpts) {
-//             todo!()
-//         }
-
-//         fn draw_rect(&self, opts: crate::render::DrawRectOpts) {
-//             todo!()
-//         }
-
-//         fn draw_circle(&self, opts: crate::render::DrawCircleOpts) {
-//             todo!()
-//         }
-//     }
-
-//     #[test]
-//     fn it_new_drawpanel_append_line() {
-//         let drawpanel = Drawpanel::new(TestRender::default());
-//         assert_eq!(drawpanel.elems().len(), 0);
-//         let mut drawpanel = drawpanel;
-//         drawpanel.append(Line::default());
-//         assert_eq!(drawpanel.elems().len(), 1);
-//         unsafe {
-//             assert_eq!(is_run, true);
-//         }
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        collections::HashSet,
+        rc::Rc,
+        time::{Duration, Instant},
+    };
+
+    use geo::coord;
+    use proptest::prelude::*;
+
+    use crate::{
+        binder::{
+            Cursor, Draw, DrawCircleOpts, EventKey, EventMouseButton, EventType, EventZoom,
+            HookEvent, IHookEvent,
+        },
+        change::Change,
+        clipboard::ClipboardPayload,
+        drawpanel::{Drawpanel, Mode},
+        elem::{connector::Connector, dimension::Dimension, rect::Rect, text::Text, IElem},
+        gesture::{GestureAction, GestureRecognizer},
+        page::CanvasMode,
+        panel::{ImportMode, Panel},
+        recording::{DrawCall, EventScript, Recorder, RecordingBinder},
+        session::{Session, SessionMode, SessionPlayer},
+        svg::export::{SvgExportOpts, SvgScope},
+        theme::{HandleShape, Theme},
+        tool::{MoveTool, TextEditTool, Tool, ToolEvent},
+        units::{Unit, Units},
+        viewport::Viewport,
+    };
+
+    fn setup() -> (Drawpanel, Recorder) {
+        let binder = RecordingBinder::new(400., 300.);
+        let recorder = binder.recorder();
+        (Drawpanel::new(binder), recorder)
+    }
+
+    fn create_rect(drawpanel: &mut Drawpanel, from: (f64, f64), to: (f64, f64)) {
+        drawpanel.set_mode(Mode::Creating(Some(Box::new(Rect::default()))));
+        EventScript::new().drag_path(from, to, 4).run(drawpanel);
+        drawpanel.set_mode(Mode::EditMoving);
+    }
+
+    fn vertex(drawpanel: &Drawpanel, index: usize) -> Vec<(f64, f64)> {
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let panel = panel.borrow();
+        panel.elems[index]
+            .get_vertex()
+            .iter()
+            .map(|c| (c.x, c.y))
+            .collect()
+    }
+
+    #[test]
+    fn it_draws_the_empty_page() {
+        let (drawpanel, recorder) = setup();
+        assert_eq!(
+            recorder.snapshot(&drawpanel),
+            "rect (0.00, 0.00) 400.00x300.00 size 0.00 color #ffffff fill #ffffff Solid"
+        );
+    }
+
+    #[test]
+    fn it_creates_a_rect_by_dragging() {
+        let (mut drawpanel, recorder) = setup();
+        create_rect(&mut drawpanel, (10., 20.), (110., 70.));
+
+        assert_eq!(
+            vertex(&drawpanel, 0),
+            vec![(10., 20.), (110., 20.), (110., 70.), (10., 70.)]
+        );
+        assert_eq!(
+            recorder.snapshot(&drawpanel),
+            "rect (0.00, 0.00) 400.00x300.00 size 0.00 color #ffffff fill #ffffff Solid\n\
+             rect (10.00, 20.00) 100.00x50.00 size 3.00 color #ff0000 fill none Solid"
+        );
+        assert!(recorder.flushes() > 0);
+    }
+
+    #[test]
+    fn it_moves_the_hovered_elem() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 20.), (110., 70.));
+        EventScript::new()
+            .drag_path((50., 45.), (80., 95.), 3)
+            .run(&drawpanel);
+
+        assert_eq!(vertex(&drawpanel, 0)[0], (40., 70.));
+    }
+
+    #[test]
+    fn it_resizes_by_a_vertex() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 20.), (110., 70.));
+        EventScript::new()
+            .drag_path((110., 70.), (150., 100.), 2)
+            .run(&drawpanel);
+
+        assert_eq!(vertex(&drawpanel, 0)[2], (150., 100.));
+        assert_eq!(vertex(&drawpanel, 0)[0], (10., 20.));
+    }
+
+    #[test]
+    fn it_selects_with_a_box() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        create_rect(&mut drawpanel, (60., 10.), (100., 50.));
+        create_rect(&mut drawpanel, (200., 200.), (250., 250.));
+        drawpanel.set_mode(Mode::Select);
+        EventScript::new()
+            .drag_path((5., 5.), (150., 100.), 4)
+            .run(&drawpanel);
+
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let mut selects: Vec<u32> = panel.borrow().selects.iter().copied().collect();
+        selects.sort();
+        assert_eq!(selects, vec![0, 1]);
+    }
+
+    #[test]
+    fn it_zooms_around_the_mouse() {
+        let (mut drawpanel, recorder) = setup();
+        create_rect(&mut drawpanel, (100., 100.), (200., 200.));
+        EventScript::new()
+            .zoom(EventZoom::Grow, 100., 100.)
+            .zoom(EventZoom::Grow, 100., 100.)
+            .run(&drawpanel);
+
+        assert!((drawpanel.scale() - 1.21).abs() < 1e-9);
+        let frame = recorder.capture(&drawpanel);
+        assert_eq!(
+            frame[1].to_string(),
+            "rect (100.00, 100.00) 121.00x121.00 size 3.63 color #ff0000 fill none Solid"
+        );
+    }
+
+    #[test]
+    fn it_replays_a_recorded_session() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        drawpanel.start_recording();
+        create_rect(&mut drawpanel, (100., 100.), (150., 140.));
+        EventScript::new()
+            .drag_path((20., 20.), (60., 30.), 3)
+            .run(&drawpanel);
+        drawpanel.set_mode(Mode::Creating(Some(Box::new(Text::default()))));
+        EventScript::new()
+            .drag_path((200., 200.), (300., 240.), 2)
+            .run(&drawpanel);
+        {
+            // What the binder's input would do.
+            let panel = drawpanel.panel().upgrade().unwrap();
+            let mut panel = panel.borrow_mut();
+            panel.elems.last_mut().unwrap().set_content("hello");
+        }
+        EventScript::new().push(0., 0.).run(&drawpanel);
+        let session = drawpanel.stop_recording().unwrap();
+
+        let json = serde_json::to_string(&session).unwrap();
+        let session: Session = serde_json::from_str(&json).unwrap();
+        let (mut replayed, _) = setup();
+        replayed.replay(&session);
+        assert_eq!(replayed.export(), drawpanel.export());
+    }
+
+    #[test]
+    fn it_steps_and_stops_at_an_index() {
+        let (mut drawpanel, _) = setup();
+        drawpanel.start_recording();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        create_rect(&mut drawpanel, (60., 60.), (90., 90.));
+        let session = drawpanel.stop_recording().unwrap();
+        // set_mode, move, push, 4 drags, release, set_mode
+        let per_rect = 9;
+        assert_eq!(session.entries.len(), per_rect * 2);
+
+        let (mut replayed, _) = setup();
+        let mut player = SessionPlayer::new(session).with_speed(0.).stop_at(per_rect);
+        assert!(player.step(&mut replayed));
+        assert_eq!(player.index(), 1);
+        player.play(&mut replayed);
+        assert!(player.is_finished());
+        assert!(!player.step(&mut replayed));
+        let panel = replayed.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().elems.len(), 1);
+    }
+
+    #[test]
+    fn it_reports_changes_with_ids() {
+        let (mut drawpanel, _) = setup();
+        let changes = Rc::new(RefCell::new(vec![]));
+        let sink = changes.clone();
+        drawpanel.subscribe(move |change| sink.borrow_mut().push(change.clone()));
+
+        create_rect(&mut drawpanel, (10., 20.), (110., 70.));
+        let added: Vec<Change> = changes
+            .borrow()
+            .iter()
+            .filter(|c| matches!(c, Change::ElemAdded { .. }))
+            .cloned()
+            .collect();
+        assert_eq!(added.len(), 1);
+        let id = match &added[0] {
+            Change::ElemAdded { id, .. } => *id,
+            _ => unreachable!(),
+        };
+
+        changes.borrow_mut().clear();
+        EventScript::new()
+            .drag_path((50., 45.), (80., 95.), 3)
+            .run(&drawpanel);
+        assert_eq!(
+            *changes.borrow(),
+            vec![Change::ElemMoved {
+                id,
+                before: geo::Rect::new(coord! { x: 10., y: 20. }, coord! { x: 110., y: 70. }),
+                after: geo::Rect::new(coord! { x: 40., y: 70. }, coord! { x: 140., y: 120. }),
+            }]
+        );
+
+        changes.borrow_mut().clear();
+        drawpanel.set_mode(Mode::Deleting);
+        EventScript::new().click(60., 90.).run(&drawpanel);
+        let removed = changes
+            .borrow()
+            .iter()
+            .any(|c| matches!(c, Change::ElemRemoved { id: removed, .. } if *removed == id));
+        assert!(removed);
+    }
+
+    /// Refuses everything on elements wider than 100.
+    #[derive(Debug)]
+    struct Protect;
+
+    impl Protect {
+        fn allows(elem: &Box<dyn IElem>) -> bool {
+            elem.bounding_rect().map_or(true, |r| r.width() <= 100.)
+        }
+    }
+
+    impl IHookEvent for Protect {}
+
+    impl HookEvent for Protect {
+        fn before_delete(&mut self, elem: &Box<dyn IElem>) -> bool {
+            Protect::allows(elem)
+        }
+
+        fn before_move(
+            &mut self,
+            elem: &Box<dyn IElem>,
+            _from_coord: geo::Coordinate,
+            _end_coord: geo::Coordinate,
+        ) -> bool {
+            Protect::allows(elem)
+        }
+    }
+
+    #[test]
+    fn it_leaves_refused_elems_unchanged() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        create_rect(&mut drawpanel, (100., 100.), (300., 200.));
+        {
+            let panel = drawpanel.panel().upgrade().unwrap();
+            panel.borrow_mut().set_hook_event(Box::new(Protect));
+        }
+        EventScript::new()
+            .drag_path((150., 150.), (160., 170.), 2)
+            .drag_path((30., 30.), (40., 40.), 2)
+            .run(&drawpanel);
+        assert_eq!(vertex(&drawpanel, 1)[0], (100., 100.));
+        assert_eq!(vertex(&drawpanel, 0)[0], (20., 20.));
+
+        drawpanel.set_mode(Mode::Deleting);
+        EventScript::new().click(150., 150.).run(&drawpanel);
+        EventScript::new().click(30., 30.).run(&drawpanel);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().elems.len(), 1);
+        assert_eq!(vertex(&drawpanel, 0)[0], (100., 100.));
+    }
+
+    /// Marks where it was pressed, Escape goes back to moving.
+    #[derive(Debug, Default)]
+    struct MarkTool {
+        marks: Vec<geo::Coordinate>,
+    }
+
+    impl Tool for MarkTool {
+        fn name(&self) -> &str {
+            "mark"
+        }
+
+        fn on_push(&mut self, _panel: &mut Panel, event: &ToolEvent) {
+            self.marks.push(event.coord);
+        }
+
+        fn on_key(&mut self, panel: &mut Panel, key: EventKey, pressed: bool) {
+            if key == EventKey::Escape && pressed {
+                panel.set_tool(Box::new(MoveTool::default()));
+            }
+        }
+
+        fn draw_overlay(&self, _panel: &Panel, draw: &dyn Draw) {
+            for mark in self.marks.iter() {
+                draw.draw_circle(DrawCircleOpts {
+                    center_coord: *mark,
+                    r: 2.,
+                    line_size: 1.,
+                    line_color: 0x0000ff,
+                    fill_color: 0x0000ff,
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn it_runs_a_custom_tool() {
+        let (mut drawpanel, recorder) = setup();
+        drawpanel.set_tool(Box::new(MarkTool::default()));
+        EventScript::new()
+            .click(10., 10.)
+            .zoom(EventZoom::Grow, 0., 0.)
+            .click(30., 40.)
+            .run(&drawpanel);
+        let snapshot = recorder.snapshot(&drawpanel);
+        assert_eq!(
+            snapshot.lines().last().unwrap(),
+            "circle (30.00, 40.00) r 2.20 size 1.10 color #0000ff fill #0000ff"
+        );
+
+        EventScript::new()
+            .event(EventType::KeyDown(EventKey::Escape), 0., 0.)
+            .run(&drawpanel);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().mode(), SessionMode::EditMoving);
+    }
+
+    #[test]
+    fn it_ends_a_text_edit_without_its_element() {
+        let (mut drawpanel, _) = setup();
+        drawpanel.set_tool(Box::new(TextEditTool::new(None)));
+        EventScript::new().click(10., 10.).run(&drawpanel);
+        drawpanel.set_tool(Box::new(TextEditTool::new(Some(3))));
+        EventScript::new().click(10., 10.).run(&drawpanel);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().mode(), SessionMode::EditMoving);
+    }
+
+    #[test]
+    fn it_keeps_creating_until_cancelled() {
+        let (mut drawpanel, _) = setup();
+        drawpanel.set_mode(Mode::Creating(Some(Box::new(Rect::default()))));
+        EventScript::new()
+            .drag_path((10., 10.), (50., 50.), 2)
+            .drag_path((60., 60.), (90., 90.), 2)
+            .move_to(100., 100.)
+            .push(100., 100.)
+            .drag(120., 130.)
+            .event(EventType::KeyDown(EventKey::Escape), 120., 130.)
+            .release(120., 130.)
+            .run(&drawpanel);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().elems.len(), 2);
+        assert!(matches!(
+            panel.borrow().mode(),
+            SessionMode::Creating(Some(_))
+        ));
+        assert_eq!(vertex(&drawpanel, 1)[2], (90., 90.));
+
+        EventScript::new()
+            .event(EventType::Push(EventMouseButton::Left), 100., 100.)
+            .event(EventType::Push(EventMouseButton::Right), 100., 100.)
+            .event(EventType::KeyDown(EventKey::Escape), 0., 0.)
+            .run(&drawpanel);
+        assert_eq!(panel.borrow().elems.len(), 2);
+        assert_eq!(panel.borrow().mode(), SessionMode::EditMoving);
+    }
+
+    #[test]
+    fn it_reorders_the_selection() {
+        let (mut drawpanel, _) = setup();
+        for i in 0..5 {
+            let x = i as f64 * 20.;
+            create_rect(&mut drawpanel, (x, 10.), (x + 10., 20.));
+        }
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let ids = |panel: &Panel| -> Vec<u64> { panel.metas.iter().map(|m| m.id).collect() };
+        let before = ids(&panel.borrow());
+        panel.borrow_mut().selects = [0, 1].into_iter().collect();
+
+        drawpanel.bring_forward(None);
+        assert_eq!(
+            ids(&panel.borrow()),
+            vec![before[2], before[0], before[1], before[3], before[4]]
+        );
+        drawpanel.bring_to_front(None);
+        assert_eq!(
+            ids(&panel.borrow()),
+            vec![before[2], before[3], before[4], before[0], before[1]]
+        );
+        let mut selects: Vec<u32> = panel.borrow().selects.iter().copied().collect();
+        selects.sort();
+        assert_eq!(selects, vec![3, 4]);
+
+        drawpanel.send_to_back(Some(2));
+        assert_eq!(ids(&panel.borrow())[0], before[4]);
+        assert_eq!(vertex(&drawpanel, 0)[0], (80., 10.));
+    }
+
+    #[test]
+    fn it_groups_and_enters_a_group() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        create_rect(&mut drawpanel, (60., 10.), (100., 50.));
+        let panel = drawpanel.panel().upgrade().unwrap();
+        panel.borrow_mut().selects = [0, 1].into_iter().collect();
+        assert_eq!(drawpanel.group(), Some(0));
+        assert_eq!(panel.borrow().elems.len(), 1);
+        let id = panel.borrow().metas[0].id;
+
+        EventScript::new()
+            .drag_path((30., 30.), (40., 40.), 2)
+            .drag_path((110., 60.), (200., 60.), 2)
+            .run(&drawpanel);
+        assert_eq!(vertex(&drawpanel, 0)[0], (20., 20.));
+        assert_eq!(vertex(&drawpanel, 0)[2], (200., 60.));
+
+        let data = drawpanel.export();
+        let (mut other, _) = setup();
+        other.import(&data);
+        assert_eq!(other.export(), data);
+
+        EventScript::new()
+            .move_to(150., 30.)
+            .dblclick(150., 30.)
+            .run(&drawpanel);
+        assert_eq!(panel.borrow().elems.len(), 2);
+        assert_eq!(vertex(&drawpanel, 1)[0], (120., 20.));
+        // Saved as if the group was closed.
+        assert_eq!(drawpanel.export(), data);
+
+        EventScript::new()
+            .move_to(300., 250.)
+            .click(300., 250.)
+            .run(&drawpanel);
+        assert_eq!(panel.borrow().elems.len(), 1);
+        assert_eq!(panel.borrow().metas[0].id, id);
+    }
+
+    #[test]
+    fn it_keeps_elems_in_their_layers() {
+        let (mut drawpanel, recorder) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        let notes = drawpanel.add_layer("notes");
+        create_rect(&mut drawpanel, (30., 30.), (70., 70.));
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let background = panel.borrow().layers[0].id;
+
+        // Both on top of the first layer, still below the notes.
+        drawpanel.update_layer(background, |layer| layer.name = "background".to_string());
+        panel.borrow_mut().active_layer = background;
+        create_rect(&mut drawpanel, (100., 10.), (120., 30.));
+        assert_eq!(vertex(&drawpanel, 1)[0], (100., 10.));
+        assert_eq!(vertex(&drawpanel, 2)[0], (30., 30.));
+
+        drawpanel.update_layer(notes, |layer| layer.locked = true);
+        EventScript::new().move_to(60., 60.).run(&drawpanel);
+        assert_eq!(panel.borrow().hover_index, -1);
+        drawpanel.update_layer(notes, |layer| layer.opacity = 0.5);
+        let frame = recorder.capture(&drawpanel);
+        assert_eq!(frame[3], DrawCall::Opacity { opacity: 0.5 });
+        assert!(frame[4].to_string().starts_with("rect (30.00, 30.00)"));
+        assert_eq!(frame[5], DrawCall::Opacity { opacity: 1. });
+        drawpanel.update_layer(notes, |layer| layer.visible = false);
+        assert_eq!(recorder.snapshot(&drawpanel).lines().count(), 3);
+
+        let data = drawpanel.export();
+        let (mut other, _) = setup();
+        other.import(&data);
+        assert_eq!(other.export(), data);
+
+        assert!(drawpanel.move_layer(notes, 0));
+        assert_eq!(vertex(&drawpanel, 0)[0], (30., 30.));
+        assert!(drawpanel.merge_layer(background, notes));
+        assert_eq!(panel.borrow().layers.len(), 1);
+        assert_eq!(vertex(&drawpanel, 2)[0], (100., 10.));
+    }
+
+    #[test]
+    fn it_pastes_copies_between_panels() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        create_rect(&mut drawpanel, (60., 10.), (100., 50.));
+        let panel = drawpanel.panel().upgrade().unwrap();
+        panel.borrow_mut().select(&[0, 1]);
+
+        let payload = drawpanel.copy().unwrap();
+        assert!(payload.svg.as_ref().unwrap().contains("<rect"));
+        assert_eq!(drawpanel.paste(None).unwrap(), vec![2, 3]);
+        assert_eq!(vertex(&drawpanel, 2)[0], (20., 20.));
+        assert_eq!(drawpanel.paste(None).unwrap(), vec![4, 5]);
+        assert_eq!(vertex(&drawpanel, 4)[0], (30., 30.));
+        assert_eq!(drawpanel.duplicate(), vec![6, 7]);
+        assert_eq!(vertex(&drawpanel, 7)[0], (90., 40.));
+
+        // Dragging moves the pasted elements together.
+        EventScript::new()
+            .drag_path((60., 60.), (70., 70.), 2)
+            .run(&drawpanel);
+        assert_eq!(vertex(&drawpanel, 6)[0], (50., 50.));
+
+        drawpanel.cut().unwrap();
+        assert_eq!(panel.borrow().elems.len(), 6);
+
+        let (mut other, _) = setup();
+        let payload = ClipboardPayload::from_json(&payload.to_json()).unwrap();
+        assert_eq!(
+            other
+                .paste_payload(&payload, Some(coord! { x: 200., y: 100. }))
+                .unwrap(),
+            vec![0, 1]
+        );
+        assert_eq!(vertex(&other, 1)[0], (250., 100.));
+    }
+
+    #[test]
+    fn it_refuses_a_payload_it_cannot_read() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (20., 20.));
+        let payload = |document: &str| ClipboardPayload {
+            document: document.to_string(),
+            svg: None,
+        };
+        for (document, error) in [
+            ("not json", "invalid document"),
+            (r#"{"elems":["rect"]}"#, "malformed element"),
+            (
+                r#"{"elems":["rect({})","cloud({})"]}"#,
+                "unknown element type: cloud",
+            ),
+            (
+                r#"{"elems":["group({\"elems\":[\"cloud({})\"]})"]}"#,
+                "unknown element type: cloud",
+            ),
+            (
+                r#"{"elems":["rect({\"width\":\"x\"})"]}"#,
+                "invalid element: rect",
+            ),
+            (
+                r#"{"elems":["connector({\"coords\":[]})"]}"#,
+                "invalid element: connector",
+            ),
+            (
+                r#"{"elems":["group({\"elems\":[\"pen({\\\"coords\\\":[]})\"]})"]}"#,
+                "invalid element: pen",
+            ),
+        ] {
+            let err = drawpanel
+                .paste_payload(&payload(document), None)
+                .unwrap_err();
+            assert!(err.to_string().starts_with(error), "{}", err);
+        }
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().elems.len(), 1);
+    }
+
+    #[test]
+    fn it_frames_the_content_within_the_zoom_limits() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (100., 100.), (140., 120.));
+        create_rect(&mut drawpanel, (160., 100.), (180., 110.));
+
+        drawpanel.zoom_to_fit();
+        let viewport = drawpanel.viewport();
+        // 400 wide minus the padding for 80 wide content.
+        assert!((viewport.scale - 4.5).abs() < 1e-9);
+        let center = viewport.to_screen(coord! { x: 140., y: 110. });
+        assert!((center.x - 200.).abs() < 1e-9 && (center.y - 150.).abs() < 1e-9);
+
+        let panel = drawpanel.panel().upgrade().unwrap();
+        panel.borrow_mut().select(&[1]);
+        drawpanel.zoom_to_selection();
+        assert_eq!(drawpanel.viewport().scale, 10.);
+
+        for _ in 0..100 {
+            EventScript::new()
+                .zoom(EventZoom::Dwindle, 50., 50.)
+                .run(&drawpanel);
+        }
+        assert!((drawpanel.scale() - 0.1).abs() < 1e-9);
+        let world = panel.borrow().relative_coord(coord! { x: 50., y: 50. });
+        assert!(world.x.is_finite());
+
+        drawpanel.reset_view();
+        assert_eq!(
+            drawpanel.viewport().to_world(coord! { x: 10., y: 20. }),
+            coord! { x: 10., y: 20. }
+        );
+    }
+
+    #[test]
+    fn it_pans_with_the_bound_inputs_in_any_tool() {
+        let (mut drawpanel, _) = setup();
+        drawpanel.set_mode(Mode::Creating(Some(Box::new(Rect::default()))));
+        EventScript::new()
+            .event(EventType::Push(EventMouseButton::Middle), 10., 10.)
+            .event(EventType::Drag(EventMouseButton::Middle), 30., 25.)
+            .event(EventType::Released(EventMouseButton::Middle), 30., 25.)
+            .event(EventType::KeyDown(EventKey::Space), 0., 0.)
+            .drag_path((50., 50.), (60., 50.), 2)
+            .event(EventType::KeyUp(EventKey::Space), 0., 0.)
+            .run(&drawpanel);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert!(panel.borrow().elems.is_empty());
+        assert_eq!(drawpanel.viewport().lt_coord, coord! { x: 30., y: 15. });
+
+        drawpanel.fling(coord! { x: 100., y: 0. });
+        let mut frames = 0;
+        while drawpanel.tick(1. / 60.) {
+            frames += 1;
+        }
+        assert!(frames > 10 && frames < 200);
+        let x = drawpanel.viewport().lt_coord.x;
+        // About 100 / ln(20), the integral of the decaying speed.
+        assert!((x - 30. - 100. / 20f64.ln()).abs() < 2.);
+    }
+
+    #[test]
+    fn it_recognizes_touch_gestures() {
+        let (mut drawpanel, _) = setup();
+        EventScript::new()
+            .event(EventType::TouchStart(1), 100., 100.)
+            .event(EventType::TouchStart(2), 200., 100.)
+            .event(EventType::TouchMove(2), 300., 100.)
+            .event(EventType::TouchEnd(1), 100., 100.)
+            .event(EventType::TouchEnd(2), 300., 100.)
+            .run(&drawpanel);
+        // Twice as far apart, the point between the fingers followed them.
+        let viewport = drawpanel.viewport();
+        assert!((viewport.scale - 2.).abs() < 1e-9);
+        assert!(close(
+            viewport.to_screen(coord! { x: 150., y: 100. }),
+            coord! { x: 200., y: 100. }
+        ));
+
+        drawpanel.set_mode(Mode::Creating(Some(Box::new(Rect::default()))));
+        EventScript::new()
+            .event(EventType::TouchStart(3), 10., 10.)
+            .event(EventType::TouchMove(3), 60., 60.)
+            .event(EventType::TouchEnd(3), 60., 60.)
+            .run(&drawpanel);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().elems.len(), 1);
+
+        let mut gestures = GestureRecognizer::default();
+        let now = Instant::now();
+        gestures.handle(EventType::TouchStart(4), coord! { x: 5., y: 5. }, now);
+        assert!(gestures.poll(now).is_empty());
+        let actions = gestures.poll(now + Duration::from_secs(1));
+        assert_eq!(
+            actions[1],
+            GestureAction::Mouse(
+                EventType::Push(EventMouseButton::Right),
+                coord! { x: 5., y: 5. }
+            )
+        );
+    }
+
+    #[test]
+    fn it_replays_long_presses_by_the_recorded_time() {
+        let long_press = |drawpanel: &Drawpanel| {
+            let panel = drawpanel.panel().upgrade().unwrap();
+            panel.borrow_mut().gestures.long_press = Duration::from_millis(20);
+            panel
+        };
+        let (mut drawpanel, _) = setup();
+        let panel = long_press(&drawpanel);
+        drawpanel.start_recording();
+        drawpanel.set_mode(Mode::Creating(Some(Box::new(Rect::default()))));
+        EventScript::new()
+            .event(EventType::TouchStart(1), 10., 10.)
+            .run(&drawpanel);
+        std::thread::sleep(Duration::from_millis(40));
+        panel.borrow_mut().tick(0.);
+        EventScript::new()
+            .event(EventType::TouchEnd(1), 10., 10.)
+            .run(&drawpanel);
+        let session = drawpanel.stop_recording().unwrap();
+        // The long press was no tap, nothing got created.
+        assert!(matches!(
+            panel.borrow().mode(),
+            SessionMode::Creating(Some(_))
+        ));
+        assert!(panel.borrow().elems.is_empty());
+
+        let (mut replayed, _) = setup();
+        let panel = long_press(&replayed);
+        SessionPlayer::new(session)
+            .with_speed(0.)
+            .play(&mut replayed);
+        assert!(matches!(
+            panel.borrow().mode(),
+            SessionMode::Creating(Some(_))
+        ));
+        assert!(panel.borrow().elems.is_empty());
+    }
+
+    #[test]
+    fn it_exports_another_page_as_it_is() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        drawpanel.add_page("Cover", 200., 100.);
+        assert!(drawpanel.next_page());
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let bottom = panel.borrow().active_layer;
+        drawpanel.add_layer("Notes");
+        panel.borrow_mut().active_layer = bottom;
+        create_rect(&mut drawpanel, (20., 20.), (40., 30.));
+        create_rect(&mut drawpanel, (60., 20.), (80., 30.));
+        create_rect(&mut drawpanel, (20., 60.), (40., 70.));
+        panel.borrow_mut().group(&[0, 1]);
+        assert!(panel.borrow_mut().enter_group(0));
+        panel.borrow_mut().select(&[2]);
+
+        let svg = drawpanel
+            .export_page_svg(0, &SvgExportOpts::default())
+            .unwrap();
+        // The page backdrop and the one rect of page 0.
+        assert_eq!(svg.matches("<rect").count(), 2);
+        let panel = panel.borrow();
+        assert_eq!(panel.current_page, 1);
+        assert_eq!(panel.elems.len(), 3);
+        assert_eq!(panel.selects, HashSet::from([2]));
+        assert_eq!(panel.active_layer, bottom);
+        assert_eq!(panel.entered.len(), 1);
+    }
+
+    #[test]
+    fn it_keeps_each_page_to_itself() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        let id = drawpanel.panel().upgrade().unwrap().borrow().elem_id(0);
+
+        assert_eq!(drawpanel.add_page("Cover", 200., 100.), 1);
+        assert!(drawpanel.next_page());
+        assert!(!drawpanel.next_page());
+        create_rect(&mut drawpanel, (20., 20.), (40., 30.));
+        create_rect(&mut drawpanel, (60., 20.), (80., 30.));
+        let svg = drawpanel
+            .export_page_svg(0, &SvgExportOpts::default())
+            .unwrap();
+        assert!(svg.contains(r#"viewBox="0 0 400 300""#));
+        assert_eq!(drawpanel.current_page(), 1);
+
+        let data = drawpanel.export();
+        let (mut other, _) = setup();
+        other.import(&data);
+        let panel = other.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().elems.len(), 2);
+        assert_eq!(
+            panel.borrow().page_rect().unwrap().max(),
+            coord! { x: 200., y: 100. }
+        );
+        assert!(other.prev_page());
+        assert_eq!(panel.borrow().elems.len(), 1);
+        assert!(other.export_page(1).unwrap().contains("rect("));
+
+        // Pages keep their element ids while hidden.
+        assert!(drawpanel.prev_page());
+        assert_eq!(drawpanel.panel().upgrade().unwrap().borrow().elem_id(0), id);
+
+        // Other canvases keep the pages for later.
+        drawpanel.set_canvas_mode(CanvasMode::Infinite);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().page_rect(), None);
+        assert_eq!(panel.borrow().elems.len(), 1);
+        assert!(!drawpanel.next_page());
+        drawpanel.set_canvas_mode(CanvasMode::Paged);
+        assert_eq!(drawpanel.pages().len(), 2);
+        assert!(drawpanel.next_page());
+        assert_eq!(panel.borrow().elems.len(), 2);
+    }
+
+    fn close(a: geo::Coordinate, b: geo::Coordinate) -> bool {
+        let tolerance = 1e-9 * (1. + a.x.abs().max(a.y.abs()));
+        (a.x - b.x).abs() < tolerance && (a.y - b.y).abs() < tolerance
+    }
+
+    #[test]
+    fn it_pans_from_the_minimap() {
+        let (mut drawpanel, recorder) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (50., 50.));
+        drawpanel.set_minimap(|minimap| minimap.visible = true);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let view = panel.borrow().minimap_view();
+
+        // The outline of the visible part, here the whole page.
+        let frame = recorder.capture(&drawpanel);
+        assert_eq!(
+            frame.last().unwrap().to_string(),
+            format!(
+                "rect ({:.2}, {:.2}) 149.33x112.00 size 1.00 color #1e90ff fill none Solid",
+                view.lt_coord.x, view.lt_coord.y
+            )
+        );
+
+        let target = view.to_screen(coord! { x: 300., y: 200. });
+        EventScript::new()
+            .push(target.x, target.y)
+            .drag(target.x + 10., target.y)
+            .release(target.x + 10., target.y)
+            .run(&drawpanel);
+        let center = drawpanel.viewport().to_world(coord! { x: 200., y: 150. });
+        let expected = view.to_world(coord! { x: target.x + 10., y: target.y });
+        assert!((center.x - expected.x).abs() < 1e-9 && (center.y - expected.y).abs() < 1e-9);
+        // The tool never saw the clicks.
+        assert!(panel.borrow().selects.is_empty());
+        assert_eq!(vertex(&drawpanel, 0)[0], (10., 10.));
+    }
+
+    #[test]
+    fn it_measures_in_the_document_unit() {
+        let (mut drawpanel, recorder) = setup();
+        drawpanel.set_units(Units {
+            unit: Unit::Mm,
+            ..Default::default()
+        });
+        drawpanel.set_mode(Mode::Creating(Some(Box::new(Dimension::default()))));
+        EventScript::new()
+            .drag_path((20., 100.), (116., 100.), 4)
+            .run(&drawpanel);
+        drawpanel.set_mode(Mode::EditMoving);
+        let frame = recorder.capture(&drawpanel);
+        assert!(frame
+            .iter()
+            .any(|call| call.to_string().contains(r#""25.4 mm""#)));
+
+        drawpanel.set_rulers(|rulers| rulers.visible = true);
+        let frame = recorder.capture(&drawpanel);
+        // Labelled every 20 mm, the first step of at least 50px.
+        assert!(frame
+            .iter()
+            .any(|call| call.to_string().contains(r#""20""#)));
+        assert!(!frame
+            .iter()
+            .any(|call| call.to_string().contains(r#""10""#)));
+
+        EventScript::new().move_to(68., 100.).run(&drawpanel);
+        assert_eq!(
+            drawpanel.readout().to_string(),
+            "x 17.99 mm, y 26.46 mm, w 25.4 mm, h 0 mm"
+        );
+
+        let (mut other, _) = setup();
+        other.import(&drawpanel.export());
+        let panel = other.panel().upgrade().unwrap();
+        assert_eq!(panel.borrow().units.unit, Unit::Mm);
+        assert_eq!(panel.borrow().elems[0].elem_type(), "dimension");
+    }
+
+    #[test]
+    fn it_keeps_handles_the_same_size_at_any_zoom() {
+        let (mut drawpanel, recorder) = setup();
+        create_rect(&mut drawpanel, (100., 100.), (150., 150.));
+        drawpanel.set_theme(Theme {
+            canvas_background: 0xfafafa,
+            selection_color: 0x00ff00,
+            handle_shape: HandleShape::Square,
+            handle_size: 4.,
+            ..Default::default()
+        });
+        drawpanel.zoom_to(2., coord! { x: 0., y: 0. });
+        EventScript::new().move_to(200., 250.).run(&drawpanel);
+
+        let frame: Vec<String> = recorder
+            .capture(&drawpanel)
+            .iter()
+            .map(|call| call.to_string())
+            .collect();
+        assert!(frame[0].contains("fill #fafafa"));
+        // Hovered: 3 wide plus 2 on screen, at scale 2.
+        assert_eq!(
+            frame[1],
+            "rect (200.00, 200.00) 100.00x100.00 size 8.00 color #ff0000 fill none Solid"
+        );
+        assert_eq!(
+            frame[2],
+            "rect (196.00, 196.00) 8.00x8.00 size 1.00 color #00ff00 fill #000000 Solid"
+        );
+        assert_eq!(frame.len(), 6);
+    }
+
+    #[test]
+    fn it_reports_the_cursor_for_what_is_under_the_mouse() {
+        let (mut drawpanel, recorder) = setup();
+        create_rect(&mut drawpanel, (100., 100.), (200., 200.));
+        recorder.clear();
+        EventScript::new()
+            .move_to(150., 150.)
+            .move_to(198., 103.)
+            .move_to(300., 250.)
+            .event(EventType::KeyDown(EventKey::Space), 300., 250.)
+            .event(EventType::Push(EventMouseButton::Left), 300., 250.)
+            .event(EventType::Released(EventMouseButton::Left), 300., 250.)
+            .event(EventType::KeyUp(EventKey::Space), 300., 250.)
+            .run(&drawpanel);
+        assert_eq!(
+            recorder.cursors(),
+            vec![
+                Cursor::Move,
+                Cursor::ResizeNorthEast,
+                Cursor::Default,
+                Cursor::Grab,
+                Cursor::Grabbing,
+                Cursor::Grab,
+                Cursor::Default,
+            ]
+        );
+
+        let layer = drawpanel.panel().upgrade().unwrap().borrow().active_layer;
+        drawpanel.update_layer(layer, |layer| layer.locked = true);
+        EventScript::new().move_to(150., 150.).run(&drawpanel);
+        assert_eq!(recorder.cursors().last(), Some(&Cursor::NotAllowed));
+        drawpanel.set_mode(Mode::Creating(Some(Box::new(Text::default()))));
+        assert_eq!(recorder.cursors().last(), Some(&Cursor::Text));
+    }
+
+    fn elems(drawpanel: &Drawpanel) -> Vec<String> {
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let panel = panel.borrow();
+        panel
+            .elems
+            .iter()
+            .map(|elem| format!("{} {}", elem.elem_type(), elem.export()))
+            .collect()
+    }
+
+    const PNG: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg==";
+
+    fn round_trip(svg: &str) -> Vec<String> {
+        let (mut drawpanel, _) = setup();
+        drawpanel.import_svg(svg, ImportMode::Replace).unwrap();
+        let imported = elems(&drawpanel);
+        let exported = drawpanel.export_svg(&SvgExportOpts::default());
+        let (mut again, _) = setup();
+        again.import_svg(&exported, ImportMode::Replace).unwrap();
+        assert_eq!(elems(&again), imported, "{}", exported);
+        imported
+    }
+
+    #[test]
+    fn it_round_trips_svg_shapes() {
+        let svg = format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300">
+                <rect x="10" y="20" width="100" height="50" fill="#00ff00" stroke="#0000ff" stroke-width="2"/>
+                <line x1="10" y1="100" x2="200" y2="120" stroke="#ff0000" stroke-width="3"/>
+                <polyline points="10,150 50,180 90,150" fill="none" stroke="#333333"/>
+                <text x="200" y="40" font-size="20" fill="#112233">Hello</text>
+                <image x="250" y="150" width="40" height="30" href="{PNG}"/>
+            </svg>"##
+        );
+        assert_eq!(
+            round_trip(&svg),
+            vec![
+                r#"rect {"lt_coord":{"x":10.0,"y":20.0},"width":100.0,"height":50.0,"line_size":2.0,"line_color":255,"fill_color":65280,"line_style":"Solid"}"#.to_string(),
+                r#"line {"from_coord":{"x":10.0,"y":100.0},"end_coord":{"x":200.0,"y":120.0},"line_size":3.0,"line_color":16711680}"#.to_string(),
+                r#"pen {"coords":[{"x":10.0,"y":150.0},{"x":50.0,"y":180.0},{"x":90.0,"y":150.0}],"line_size":1.0,"line_color":3355443}"#.to_string(),
+                r#"text {"lt_coord":{"x":200.0,"y":24.0},"width":60.0,"height":20.0,"content":"Hello","font_color":1122867}"#.to_string(),
+                format!(r#"image {{"lt_coord":{{"x":250.0,"y":150.0}},"width":40.0,"height":30.0,"line_size":3.0,"line_color":16711680,"fill_color":null,"line_style":"Solid","data":"{PNG}"}}"#),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_bakes_group_transforms_into_svg_shapes() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300">
+            <g transform="translate(10 20)">
+                <g transform="scale(2)">
+                    <rect x="5" y="5" width="10" height="20" fill="none" stroke="#000000"/>
+                    <polygon points="20,0 30,10 20,10" fill="#ff0000"/>
+                </g>
+            </g>
+        </svg>"##;
+        assert_eq!(
+            round_trip(svg),
+            vec![
+                r#"rect {"lt_coord":{"x":20.0,"y":30.0},"width":20.0,"height":40.0,"line_size":2.0,"line_color":0,"fill_color":null,"line_style":"Solid"}"#,
+                r#"pen {"coords":[{"x":50.0,"y":20.0},{"x":70.0,"y":40.0},{"x":50.0,"y":40.0},{"x":50.0,"y":20.0}],"line_size":0.0,"line_color":0,"fill_color":16711680}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn it_keeps_filtered_svg_groups_as_images() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="400" height="300">
+            <defs><filter id="blur"><feGaussianBlur stdDeviation="2"/></filter></defs>
+            <g filter="url(#blur)" transform="translate(100 50)">
+                <rect x="0" y="0" width="40" height="20"/>
+                <circle cx="60" cy="40" r="10"/>
+            </g>
+            <circle id="dot" cx="10" cy="10" r="5" fill="url(#blur)"/>
+            <use xlink:href="#dot" x="200" y="100"/>
+        </svg>"##;
+        let (mut drawpanel, _) = setup();
+        assert_eq!(drawpanel.import_svg(svg, ImportMode::Replace).unwrap(), 3);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let panel = panel.borrow();
+        let bounds: Vec<_> = panel
+            .elems
+            .iter()
+            .map(|elem| {
+                assert_eq!(elem.elem_type(), "image");
+                let rect = elem.bounding_rect().unwrap();
+                (rect.min().x, rect.min().y, rect.width(), rect.height())
+            })
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                (100., 50., 70., 50.),
+                (5., 5., 10., 10.),
+                (205., 105., 10., 10.)
+            ]
+        );
+        drop(panel);
+        assert_eq!(round_trip(svg).len(), 3);
+    }
+
+    /// The ends of the connector at `index`, as element indices.
+    fn connector_ends(drawpanel: &Drawpanel, index: usize) -> (Option<usize>, Option<usize>) {
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let panel = panel.borrow();
+        let (source, target) = panel.elems[index].ends().unwrap();
+        (
+            source.and_then(|id| panel.elem_index(id)),
+            target.and_then(|id| panel.elem_index(id)),
+        )
+    }
+
+    #[test]
+    fn it_embeds_only_the_exported_elements() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (30., 30.));
+        create_rect(&mut drawpanel, (50., 10.), (70., 30.));
+        let panel = drawpanel.panel().upgrade().unwrap();
+        panel.borrow_mut().push_elem(Box::new(Rect {
+            lt_coord: coord! { x: 1000., y: 1000. },
+            width: 10.,
+            height: 10.,
+            ..Default::default()
+        }));
+        panel.borrow_mut().select(&[1]);
+        let export = |scope| {
+            let svg = drawpanel.export_svg(&SvgExportOpts {
+                scope,
+                embed_document: true,
+            });
+            let (mut other, _) = setup();
+            other.import_svg(&svg, ImportMode::Replace).unwrap();
+            elems(&other)
+        };
+
+        let all = elems(&drawpanel);
+        assert_eq!(export(SvgScope::Selection), all[1..2]);
+        assert_eq!(export(SvgScope::Viewport), all[..2]);
+        assert_eq!(export(SvgScope::Document), all);
+    }
+
+    #[test]
+    fn it_reattaches_embedded_connectors_by_id() {
+        let (mut drawpanel, _) = setup();
+        create_rect(&mut drawpanel, (10., 10.), (30., 30.));
+        create_rect(&mut drawpanel, (50., 10.), (70., 30.));
+        create_rect(&mut drawpanel, (150., 10.), (170., 30.));
+        let panel = drawpanel.panel().upgrade().unwrap();
+        panel.borrow_mut().delete_elems(&[0]);
+        let (source, target) = (panel.borrow().elem_id(0), panel.borrow().elem_id(1));
+        panel.borrow_mut().push_elem(Box::new(Connector {
+            source,
+            target,
+            ..Default::default()
+        }));
+        panel.borrow_mut().flush();
+        let svg = drawpanel.export_svg(&SvgExportOpts {
+            embed_document: true,
+            ..Default::default()
+        });
+
+        let (mut other, _) = setup();
+        assert_eq!(other.import_svg(&svg, ImportMode::Replace).unwrap(), 3);
+        assert_eq!(connector_ends(&other, 2), (Some(0), Some(1)));
+
+        let (mut other, _) = setup();
+        create_rect(&mut other, (10., 200.), (30., 220.));
+        assert_eq!(other.import_svg(&svg, ImportMode::Append).unwrap(), 3);
+        assert_eq!(connector_ends(&other, 3), (Some(1), Some(2)));
+
+        // A document this panel can't read falls back to the drawing.
+        let (mut other, _) = setup();
+        let foreign = svg.replace("rect(", "cloud(");
+        assert!(other.import_svg(&foreign, ImportMode::Replace).unwrap() > 0);
+        assert_eq!(elems(&other)[0].split(' ').next(), Some("rect"));
+    }
+
+    #[test]
+    fn it_keeps_drawio_connectors_attached() {
+        let model = r#"<mxGraphModel><root><mxCell id="0"/><mxCell id="1" parent="0"/>
+            <mxCell id="e" style="endArrow=none;strokeColor=#ff0000;" edge="1" parent="1" source="a" target="b"><mxGeometry relative="1" as="geometry"/></mxCell>
+            <mxCell id="a" value="" style="whiteSpace=wrap;" vertex="1" parent="1"><mxGeometry x="0" y="0" width="100" height="50" as="geometry"/></mxCell>
+            <mxCell id="b" value="" style="ellipse;" vertex="1" parent="1"><mxGeometry x="200" y="0" width="100" height="50" as="geometry"/></mxCell>
+        </root></mxGraphModel>"#;
+        // How draw.io saves a compressed diagram.
+        let encoded =
+            percent_encoding::utf8_percent_encode(model, percent_encoding::NON_ALPHANUMERIC);
+        let mut deflater =
+            flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+        std::io::Write::write_all(&mut deflater, encoded.to_string().as_bytes()).unwrap();
+        let compressed = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            deflater.finish().unwrap(),
+        );
+        let file = format!(
+            r#"<mxfile><diagram id="d" name="Page-1">{}</diagram></mxfile>"#,
+            compressed
+        );
+
+        let (mut drawpanel, _) = setup();
+        assert_eq!(
+            drawpanel.import_drawio(&file, ImportMode::Replace).unwrap(),
+            3
+        );
+        assert_eq!(connector_ends(&drawpanel, 0), (Some(1), Some(2)));
+        assert_eq!(vertex(&drawpanel, 0), vec![(100., 25.), (200., 25.)]);
+
+        // Moving the ellipse drags the connector's end along.
+        EventScript::new()
+            .drag_path((250., 25.), (250., 125.), 4)
+            .run(&drawpanel);
+        let ends = vertex(&drawpanel, 0);
+        assert_eq!(ends[0], (100., 50.));
+        assert!((ends[1].0 - 214.645).abs() < 1e-3 && (ends[1].1 - 107.322).abs() < 1e-3);
+
+        let exported = drawpanel.export_drawio();
+        assert!(exported.contains(r#"edge="1" parent="1" source="3" target="4""#));
+        let (mut again, _) = setup();
+        again.import_drawio(&exported, ImportMode::Replace).unwrap();
+        assert_eq!(connector_ends(&again, 0), (Some(1), Some(2)));
+        assert_eq!(vertex(&again, 0), ends);
+
+        // Saving keeps the element ids the connector refers to.
+        let (mut reloaded, _) = setup();
+        reloaded.import(&drawpanel.export());
+        assert_eq!(connector_ends(&reloaded, 0), (Some(1), Some(2)));
+    }
+
+    fn excalidraw(elements: &str) -> String {
+        format!(
+            r#"{{"type":"excalidraw","version":2,"elements":[{}]}}"#,
+            elements
+        )
+    }
+
+    fn child_types(group: &dyn IElem) -> Vec<String> {
+        group
+            .children()
+            .unwrap()
+            .iter()
+            .map(|elem| elem.elem_type())
+            .collect()
+    }
+
+    #[test]
+    fn it_round_trips_excalidraw_shapes() {
+        let data = excalidraw(
+            r##"{"id":"r","type":"rectangle","x":10,"y":20,"width":100,"height":50,"strokeColor":"#0000ff","backgroundColor":"#00ff00"},
+            {"id":"e","type":"ellipse","x":150,"y":20,"width":60,"height":40,"strokeColor":"#ff0000","strokeStyle":"dashed"},
+            {"id":"d","type":"diamond","x":10,"y":100,"width":40,"height":20},
+            {"id":"a","type":"arrow","x":100,"y":100,"width":100,"height":50,"points":[[0,0],[50,50],[100,0]],"endArrowhead":"arrow"},
+            {"id":"f","type":"freedraw","x":10,"y":200,"width":20,"height":10,"points":[[0,0],[10,10],[20,0]]},
+            {"id":"t","type":"text","x":250,"y":100,"width":60,"height":25,"text":"Hello","fontSize":20,"strokeColor":"#112233"}"##,
+        );
+        let (mut drawpanel, _) = setup();
+        let report = drawpanel
+            .import_excalidraw(&data, ImportMode::Replace)
+            .unwrap();
+        assert_eq!(report.imported, 7);
+        let imported = elems(&drawpanel);
+        let types: Vec<&str> = imported
+            .iter()
+            .map(|elem| elem.split(' ').next().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            vec!["rect", "ellipse", "pen", "pen", "pen", "pen", "text"]
+        );
+        assert_eq!(
+            imported[0],
+            r#"rect {"lt_coord":{"x":10.0,"y":20.0},"width":100.0,"height":50.0,"line_size":2.0,"line_color":255,"fill_color":65280,"line_style":"Solid"}"#
+        );
+        assert!(imported[1].contains(r#""line_style":"Dotted""#));
+        assert!(imported[6].contains(r#""lt_coord":{"x":250.0,"y":102.5}"#));
+
+        let exported = drawpanel.export_excalidraw();
+        let (mut again, _) = setup();
+        again
+            .import_excalidraw(&exported, ImportMode::Replace)
+            .unwrap();
+        let mut round_tripped = elems(&again);
+        // The arrowhead is at an angle, so only close to where it was.
+        for (a, b) in vertex(&again, 4).into_iter().zip(vertex(&drawpanel, 4)) {
+            assert!(close(coord! { x: a.0, y: a.1 }, coord! { x: b.0, y: b.1 }));
+        }
+        round_tripped[4] = imported[4].clone();
+        assert_eq!(round_tripped, imported, "{}", exported);
+    }
+
+    #[test]
+    fn it_nests_excalidraw_groups() {
+        let data = excalidraw(
+            r#"{"id":"a","type":"rectangle","x":0,"y":0,"width":10,"height":10,"groupIds":["inner","outer"]},
+            {"id":"b","type":"ellipse","x":20,"y":0,"width":10,"height":10,"groupIds":["inner","outer"]},
+            {"id":"c","type":"rectangle","x":40,"y":0,"width":10,"height":10,"groupIds":["outer"]},
+            {"id":"d","type":"rectangle","x":60,"y":0,"width":10,"height":10}"#,
+        );
+        let (mut drawpanel, _) = setup();
+        let report = drawpanel
+            .import_excalidraw(&data, ImportMode::Replace)
+            .unwrap();
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.groups, vec!["inner", "outer"]);
+        let panel = drawpanel.panel().upgrade().unwrap();
+        assert!(elems(&drawpanel)[1].starts_with("rect "));
+        let outer = &panel.borrow().elems[0];
+        assert_eq!(child_types(outer.as_ref()), vec!["group", "rect"]);
+        assert_eq!(
+            child_types(outer.children().unwrap()[0].as_ref()),
+            vec!["rect", "ellipse"]
+        );
+    }
+
+    #[test]
+    fn it_reports_unsupported_excalidraw_elements() {
+        let data = excalidraw(
+            r#"{"id":"f","type":"frame","x":0,"y":0,"width":100,"height":100},
+            {"id":"e1","type":"embeddable","x":0,"y":0,"width":10,"height":10},
+            {"id":"e2","type":"embeddable","x":20,"y":0,"width":10,"height":10},
+            {"id":"r","type":"rectangle","x":0,"y":0,"width":10,"height":10},
+            {"id":"x","type":"rectangle","x":0,"y":0,"width":10,"height":10,"isDeleted":true}"#,
+        );
+        let (mut drawpanel, _) = setup();
+        let report = drawpanel
+            .import_excalidraw(&data, ImportMode::Replace)
+            .unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(
+            report.unsupported.into_iter().collect::<Vec<_>>(),
+            vec![("embeddable".to_string(), 2), ("frame".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn it_reads_excalidraw_colors() {
+        let data = excalidraw(
+            r##"{"id":"a","type":"rectangle","x":0,"y":0,"width":10,"height":10,"strokeColor":"#f80","backgroundColor":"transparent"},
+            {"id":"b","type":"rectangle","x":0,"y":0,"width":10,"height":10,"strokeColor":"transparent","backgroundColor":"#11223380"}"##,
+        );
+        let (mut drawpanel, _) = setup();
+        drawpanel
+            .import_excalidraw(&data, ImportMode::Replace)
+            .unwrap();
+        let imported = elems(&drawpanel);
+        assert!(imported[0].contains(r#""line_color":16746496,"fill_color":null"#));
+        assert!(imported[1].contains(r#""line_color":0,"fill_color":1122867"#));
+    }
+
+    #[test]
+    fn it_turns_excalidraw_lines_around_their_points() {
+        // The points run left of `x`, so they turn around (50, 0).
+        let data = excalidraw(
+            r#"{"id":"a","type":"line","x":100,"y":0,"width":100,"height":0,"angle":3.141592653589793,"points":[[0,0],[-100,0]]}"#,
+        );
+        let (mut drawpanel, _) = setup();
+        drawpanel
+            .import_excalidraw(&data, ImportMode::Replace)
+            .unwrap();
+        let ends = vertex(&drawpanel, 0);
+        assert!((ends[0].0 - 0.).abs() < 1e-9 && ends[0].1.abs() < 1e-9);
+        assert!((ends[1].0 - 100.).abs() < 1e-9 && ends[1].1.abs() < 1e-9);
+    }
+
+    proptest! {
+        #[test]
+        fn it_converts_coords_both_ways(
+            x in -1e4..1e4f64, y in -1e4..1e4f64,
+            lt_x in -1e4..1e4f64, lt_y in -1e4..1e4f64,
+            scale in 0.1..10f64,
+        ) {
+            let viewport = Viewport {
+                lt_coord: coord! { x: lt_x, y: lt_y },
+                scale,
+                ..Default::default()
+            };
+            let coord = coord! { x: x, y: y };
+            prop_assert!(close(viewport.to_screen(viewport.to_world(coord)), coord));
+            prop_assert!(close(viewport.to_world(viewport.to_screen(coord)), coord));
+        }
+
+        #[test]
+        fn it_nests_svg_transforms_like_the_viewport(
+            x in -1e3..1e3f64, y in -1e3..1e3f64,
+            lt_x in -1e3..1e3f64, lt_y in -1e3..1e3f64,
+            scale in 0.1..10f64,
+        ) {
+            let svg = format!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300">
+                    <g transform="translate({lt_x} {lt_y})">
+                        <g transform="scale({scale})">
+                            <line x1="{x}" y1="{y}" x2="0" y2="0" stroke="#000000"/>
+                        </g>
+                    </g>
+                </svg>"##
+            );
+            let (mut drawpanel, _) = setup();
+            drawpanel.import_svg(&svg, ImportMode::Replace).unwrap();
+            let viewport = Viewport {
+                lt_coord: coord! { x: lt_x, y: lt_y },
+                scale,
+                ..Default::default()
+            };
+            let expected = viewport.to_screen(coord! { x: x, y: y });
+            let (from_x, from_y) = vertex(&drawpanel, 0)[0];
+            let from = coord! { x: from_x, y: from_y };
+            prop_assert!(close(from, expected));
+        }
+
+        #[test]
+        fn it_keeps_the_anchor_under_the_cursor(
+            x in 0. ..400f64, y in 0. ..300f64,
+            steps in proptest::collection::vec(any::<bool>(), 1..50),
+        ) {
+            let (drawpanel, _) = setup();
+            let anchor = coord! { x: x, y: y };
+            let world = drawpanel.viewport().to_world(anchor);
+            for grow in steps {
+                let zoom = if grow { EventZoom::Grow } else { EventZoom::Dwindle };
+                EventScript::new().zoom(zoom, x, y).run(&drawpanel);
+            }
+            prop_assert!(close(drawpanel.viewport().to_screen(world), anchor));
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use geo::{coord, Coordinate};
+
+use crate::viewport::Viewport;
+
+/// Corner of the binder's region the minimap sits in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MinimapCorner {
+    LeftTop,
+    RightTop,
+    LeftBottom,
+    RightBottom,
+}
+
+/// A scaled down view of the whole board with the visible part outlined,
+/// drawn over a corner of the panel. Dragging in it moves the view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimap {
+    pub visible: bool,
+    pub corner: MinimapCorner,
+    /// Size in binder coordinates.
+    pub width: f64,
+    pub height: f64,
+    /// Space between the minimap and the edges of the region.
+    pub margin: f64,
+    pub background: u32,
+    /// Color of the outline around the visible part.
+    pub viewport_color: u32,
+    /// The minimap's view while dragging in it. It stays put until the
+    /// button is released, whatever the drag does to the panel's view.
+    pub(crate) dragging: Option<Viewport>,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Minimap {
+            visible: false,
+            corner: MinimapCorner::RightBottom,
+            width: 160.,
+            height: 120.,
+            margin: 10.,
+            background: 0xf4f4f4,
+            viewport_color: 0x1e90ff,
+            dragging: None,
+        }
+    }
+}
+
+impl Minimap {
+    /// Where it is drawn within `region`, both in binder coordinates.
+    pub fn rect(&self, region: geo::Rect) -> geo::Rect {
+        let x = match self.corner {
+            MinimapCorner::LeftTop | MinimapCorner::LeftBottom => region.min().x + self.margin,
+            MinimapCorner::RightTop | MinimapCorner::RightBottom => {
+                region.max().x - self.margin - self.width
+            }
+        };
+        let y = match self.corner {
+            MinimapCorner::LeftTop | MinimapCorner::RightTop => region.min().y + self.margin,
+            MinimapCorner::LeftBottom | MinimapCorner::RightBottom => {
+                region.max().y - self.margin - self.height
+            }
+        };
+        geo::Rect::new(
+            coord! { x: x, y: y },
+            coord! { x: x + self.width, y: y + self.height },
+        )
+    }
+
+    /// The view showing `world` (panel coordinates) within `rect`. Never
+    /// enlarges.
+    pub fn view(&self, world: geo::Rect, rect: geo::Rect) -> Viewport {
+        let mut view = Viewport {
+            min_scale: 1e-6,
+            max_scale: 1.,
+            ..Default::default()
+        };
+        view.fit(world, rect, 4.);
+        view
+    }
+
+    pub fn contains(&self, region: geo::Rect, coord: Coordinate) -> bool {
+        let rect = self.rect(region);
+        self.visible
+            && coord.x >= rect.min().x
+            && coord.x <= rect.max().x
+            && coord.y >= rect.min().y
+            && coord.y <= rect.max().y
+    }
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::elem::ElemMeta;
+
+/// How far the drawing surface goes, see `Panel::canvas`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanvasMode {
+    /// One page the size of the binder's region.
+    #[default]
+    Region,
+    /// No page, elements can go anywhere.
+    Infinite,
+    /// The pages of `Panel::pages`, one at a time.
+    Paged,
+}
+
+impl CanvasMode {
+    pub fn is_region(&self) -> bool {
+        *self == CanvasMode::Region
+    }
+}
+
+/// A named page of a paged document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page {
+    pub name: String,
+    pub width: f64,
+    pub height: f64,
+    /// The page's elements as a document, see `Panel::export`. Empty for
+    /// the current page, whose elements are `Panel::elems`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub document: String,
+    /// Ids of the elements in `document`, so they keep them when the page
+    /// is shown again.
+    #[serde(skip)]
+    pub(crate) metas: Vec<ElemMeta>,
+}
+
+impl Page {
+    pub fn new(name: &str, width: f64, height: f64) -> Self {
+        Page {
+            name: name.to_string(),
+            width,
+            height,
+            document: String::new(),
+            metas: vec![],
+        }
+    }
+}
@@ -5,38 +5,82 @@ use std::{
 
 use crate::{
     binder::{
-        Draw, DrawCircleOpts, DrawLineOpts, DrawRectOpts, DrawTextOpts, EventRect, EventType,
-        EventZoom, HookEvent, IDraw, IHookEvent,
+        Cursor, Draw, DrawCircleOpts, DrawLineOpts, DrawRectOpts, DrawTextOpts, EventKey,
+        EventMouseButton, EventRect, EventType, EventZoom, IDraw, IHookEvent,
     },
+    change::ChangeTracker,
+    clipboard::PasteError,
     draw_wrap::DrawWrap,
-    drawpanel::Mode,
     elem::{
+        connector::Terminal,
+        group::Group,
         rect::{LineStyle, Rect},
-        Elem, IElem, Status,
+        Elem, ElemId, ElemMeta, ElemRegistry, IElem, Status,
     },
+    gesture::{GestureAction, GestureRecognizer},
+    layer::{Layer, LayerId},
+    minimap::Minimap,
+    page::{CanvasMode, Page},
+    ruler::{draw_rulers, Rulers},
     serde_helper::{option_coordinate, CoordinateRef},
+    session::{SessionAction, SessionMode, SessionRecording},
+    theme::Theme,
+    tool::{default_status, MoveTool, TextEditTool, Tool, ToolEvent},
+    units::{Readout, Units},
+    viewport::{PanBindings, Viewport, FIT_PADDING},
 };
 
 use erased_serde::Deserializer;
-use geo::{coord, point, Coordinate, EuclideanDistance, Intersects, Point};
+use geo::{coord, point, Coordinate};
 
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap as Map;
 
 #[derive(Debug)]
 pub struct Panel {
     // #[serde(with = "CoordinateRef")]
     pub raw_lt_coord: Coordinate,
-    // #[serde(with = "CoordinateRef")]
-    pub lt_coord: Coordinate,
+    pub viewport: Viewport,
+    pub pan_bindings: PanBindings,
+    /// When the running pan last moved, `None` when not panning.
+    panning: Option<Instant>,
+    space_down: bool,
+    /// Gliding speed after a pan, in binder coordinates per second.
+    pub pan_velocity: Coordinate,
+    pub gestures: GestureRecognizer,
+    /// Stands in for the wall clock while a session is replayed, so
+    /// gestures are timed by the recorded timestamps.
+    pub(crate) clock: Option<Instant>,
+    pub minimap: Minimap,
+    pub rulers: Rulers,
+    pub units: Units,
+    pub theme: Theme,
+    /// Last position of the mouse, in binder coordinates.
+    pub mouse_coord: Coordinate,
+    /// Last sent through `HookEvent::set_cursor`.
+    cursor: Cursor,
     pub width: f64,
     pub height: f64,
-    pub scale: f64,
+    pub canvas: CanvasMode,
+    /// Only used in `CanvasMode::Paged`, never empty there. The other modes
+    /// keep them for when the panel is paged again.
+    pub pages: Vec<Page>,
+    /// Index in `pages` of the page whose elements are `elems`.
+    pub current_page: usize,
     pub elems: Vec<Box<dyn IElem>>,
+    /// One entry per element of `elems`, in the same order. Elements pushed
+    /// to `elems` directly get theirs on the next event or flush.
+    pub metas: Vec<ElemMeta>,
+    pub next_elem_id: ElemId,
+    /// From back to front. `elems` holds the elements of each layer
+    /// together, in this order.
+    pub layers: Vec<Layer>,
+    /// Where new elements go.
+    pub active_layer: LayerId,
+    pub next_layer_id: LayerId,
     pub hover_index: isize,
     pub drag_vertex: isize,
-    // #[serde(skip)]
-    pub mode: Mode,
+    /// Only `None` while one of its methods runs.
+    pub tool: Option<Box<dyn Tool>>,
     // #[serde(with = "CoordinateRef")]
     pub prev_coord: Coordinate,
     // #[serde(with = "CoordinateRef")]
@@ -49,17 +93,72 @@ pub struct Panel {
     pub selects: HashSet<u32>,
     pub event_flag: i32,
 
-    pub register_elem_map: Map<String, Box<dyn IElem>>,
+    pub register_elem_map: ElemRegistry,
+    /// Groups opened with `enter_group`, innermost last.
+    pub entered: Vec<EnteredGroup>,
+    /// Set while `Drawpanel::start_recording` is active.
+    pub session: Option<SessionRecording>,
+    pub changes: ChangeTracker,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Keeps the current elements and adds the imported ones on top.
+    Append,
+    /// Drops the current elements first.
+    Replace,
+}
+
+/// Where `Panel::reorder` puts elements in the paint order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZOrder {
+    Front,
+    Back,
+    /// One step up, above the next element that isn't moved.
+    Forward,
+    Backward,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A group whose children are edited as ordinary elements, see
+/// `Panel::enter_group`.
+#[derive(Debug, Clone)]
+pub struct EnteredGroup {
+    /// The group's id, it gets it back on `leave_group`.
+    pub id: ElemId,
+    pub members: Vec<ElemId>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct PanelSerialize {
     pub elems: Vec<String>,
+    /// Documents without layers put everything on one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<Layer>,
+    /// The layer of each element in `elems`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub elem_layers: Vec<LayerId>,
+    /// The id of each element in `elems`, which connectors refer to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub elem_ids: Vec<ElemId>,
+    #[serde(default, skip_serializing_if = "CanvasMode::is_region")]
+    pub canvas: CanvasMode,
+    /// The pages, kept by other canvases too. The current page's elements are
+    /// `elems`, the others' are in their `Page::document`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pages: Vec<Page>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub current_page: usize,
+    #[serde(default, skip_serializing_if = "Units::is_default")]
+    pub units: Units,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
 }
 
 impl Panel {
     pub fn new(x: f64, y: f64, w: f64, h: f64, register_elem: Vec<Box<dyn IElem>>) -> Panel {
-        let mut register_elem_map: Map<String, Box<dyn IElem>> = Map::new();
+        let mut register_elem_map = ElemRegistry::new();
 
         for elem in register_elem {
             register_elem_map.insert(elem.elem_type().to_string(), elem);
@@ -67,16 +166,35 @@ impl Panel {
 
         Panel {
             raw_lt_coord: coord! { x: x, y: y },
-            lt_coord: coord! { x: x, y: y },
+            viewport: Viewport::new(coord! { x: x, y: y }),
+            pan_bindings: PanBindings::default(),
+            panning: None,
+            space_down: false,
+            pan_velocity: Coordinate::zero(),
+            gestures: GestureRecognizer::default(),
+            clock: None,
+            minimap: Minimap::default(),
+            rulers: Rulers::default(),
+            units: Units::default(),
+            theme: Theme::default(),
+            mouse_coord: coord! { x: x, y: y },
+            cursor: Cursor::Default,
             width: w,
             height: h,
-            scale: 1.,
+            canvas: CanvasMode::Region,
+            pages: vec![],
+            current_page: 0,
             hover_index: -1,
             drag_vertex: -1,
-            mode: Mode::EditMoving,
+            tool: Some(Box::new(MoveTool::default())),
             prev_coord: coord! { x: 0., y:0. },
             raw_prev_coord: coord! { x: 0., y:0. },
             elems: vec![],
+            metas: vec![],
+            next_elem_id: 1,
+            layers: vec![Layer::new(1, "Layer 1")],
+            active_layer: 1,
+            next_layer_id: 2,
             draw: None,
             hook_event: None,
 
@@ -85,6 +203,9 @@ impl Panel {
             event_flag: 0,
 
             register_elem_map,
+            entered: vec![],
+            session: None,
+            changes: ChangeTracker::default(),
         }
     }
 
@@ -103,335 +224,832 @@ impl Panel {
     pub fn trigger_draw2(&self, ctx: Box<dyn std::any::Any>) -> Box<dyn std::any::Any> {
         let draw = &self.draw.as_ref().unwrap();
         draw.draw_begin(ctx);
+        let backdrop = match self.canvas {
+            CanvasMode::Region => None,
+            CanvasMode::Infinite => Some(self.theme.canvas_background),
+            CanvasMode::Paged => Some(self.theme.backdrop),
+        };
+        if let Some(color) = backdrop {
+            draw.draw_rect(DrawRectOpts {
+                left_top_coord: self.raw_lt_coord,
+                width: self.width,
+                height: self.height,
+                line_size: 0.,
+                line_color: color,
+                fill_color: Some(color),
+                line_style: LineStyle::Solid,
+            });
+        }
+        if let Some(page) = self.page_rect() {
+            let (lt_coord, scale) = (self.viewport.lt_coord, self.viewport.scale);
+            draw_page(draw, page, lt_coord, scale, self.theme.canvas_background);
+        }
+        let draw2 = DrawWrap::new(&draw, self);
+        self.draw_layers(&draw2, |i| match &self.tool {
+            Some(tool) => Some(tool.elem_status(self, i)),
+            None => Some(default_status(self, i)),
+        });
+        if let Some(select_box) = &self.select_box {
+            Rect {
+                line_color: self.theme.selection_color,
+                line_size: 1. / self.viewport.scale,
+                line_style: self.theme.select_box_dash.clone(),
+                ..select_box.clone()
+            }
+            .draw(&draw2, Status::Creating)
+        }
+        if let Some(tool) = &self.tool {
+            tool.draw_overlay(self, &draw2);
+        }
+        if self.rulers.visible {
+            draw_rulers(self, draw);
+        }
+        if self.minimap.visible {
+            self.draw_minimap(draw);
+        }
+        return draw.draw_end();
+    }
+
+    /// Draws the page and every element through `draw`, ignoring hover and
+    /// selection state. The panel's origin is placed at `lt_coord`.
+    /// Used to render the document somewhere other than the bound binder.
+    pub fn render(
+        &self,
+        draw: &Box<dyn IDraw>,
+        ctx: Box<dyn std::any::Any>,
+        lt_coord: Coordinate,
+        scale: f64,
+    ) -> Box<dyn std::any::Any> {
+        draw.draw_begin(ctx);
+        if let Some(page) = self.page_rect() {
+            draw_page(draw, page, lt_coord, scale, self.theme.canvas_background);
+        }
+        let draw2 = DrawWrap::with_view(draw, self, lt_coord, scale);
+        self.draw_layers(&draw2, |_| Some(Status::Default));
+        return draw.draw_end();
+    }
+
+    /// Like `render`, but only draws the elements at `indices` and leaves out
+    /// the page.
+    pub fn render_elems(
+        &self,
+        draw: &Box<dyn IDraw>,
+        ctx: Box<dyn std::any::Any>,
+        lt_coord: Coordinate,
+        scale: f64,
+        indices: &[usize],
+    ) -> Box<dyn std::any::Any> {
+        draw.draw_begin(ctx);
+        let draw2 = DrawWrap::with_view(draw, self, lt_coord, scale);
+        self.draw_layers(&draw2, |i| indices.contains(&i).then_some(Status::Default));
+        return draw.draw_end();
+    }
+
+    /// Draws each element `status` returns a status for, with its layer's
+    /// opacity. Hidden layers are left out.
+    fn draw_layers(&self, draw: &DrawWrap, status: impl Fn(usize) -> Option<Status>) {
+        let mut opacity = 1.;
+        for (i, elem) in self.elems.iter().enumerate() {
+            let layer = self.elem_layer(i);
+            if !layer.map_or(true, |layer| layer.visible) {
+                continue;
+            }
+            let status = match status(i) {
+                Some(status) => status,
+                None => continue,
+            };
+            let layer_opacity = layer.map_or(1., |layer| layer.opacity);
+            if layer_opacity != opacity {
+                opacity = layer_opacity;
+                draw.set_opacity(opacity);
+            }
+            elem.draw(draw, status);
+        }
+        if opacity != 1. {
+            draw.set_opacity(1.);
+        }
+    }
+
+    /// The smallest rect containing the elements at `indices`, in panel
+    /// coordinates.
+    pub fn bounds(&self, indices: &[usize]) -> Option<geo::Rect> {
+        indices
+            .iter()
+            .filter_map(|i| self.elems.get(*i))
+            .filter_map(|elem| elem.bounding_rect())
+            .reduce(union_rect)
+    }
+
+    /// The binder's region, in binder coordinates.
+    pub fn region(&self) -> geo::Rect {
+        geo::Rect::new(
+            self.raw_lt_coord,
+            coord! {
+                x: self.raw_lt_coord.x + self.width,
+                y: self.raw_lt_coord.y + self.height,
+            },
+        )
+    }
+
+    /// The part of the panel currently visible in the binder's region.
+    pub fn visible_rect(&self) -> geo::Rect {
+        let region = self.region();
+        geo::Rect::new(
+            self.relative_coord(region.min()),
+            self.relative_coord(region.max()),
+        )
+    }
+
+    /// The minimap's view, showing the elements, the page and the visible
+    /// part together.
+    pub fn minimap_view(&self) -> Viewport {
+        if let Some(view) = self.minimap.dragging {
+            return view;
+        }
+        let all: Vec<usize> = (0..self.elems.len()).collect();
+        let world = [self.bounds(&all), self.page_rect()]
+            .into_iter()
+            .flatten()
+            .fold(self.visible_rect(), union_rect);
+        self.minimap.view(world, self.minimap.rect(self.region()))
+    }
+
+    fn draw_minimap(&self, draw: &Box<dyn IDraw>) {
+        let rect = self.minimap.rect(self.region());
+        let view = self.minimap_view();
         draw.draw_rect(DrawRectOpts {
-            left_top_coord: self.lt_coord,
-            width: self.width * self.scale,
-            height: self.height * self.scale,
-            line_size: 0.,
-            line_color: 0xffffff,
-            fill_color: Some(0xffffff),
+            left_top_coord: rect.min(),
+            width: rect.width(),
+            height: rect.height(),
+            line_size: 1.,
+            line_color: 0x999999,
+            fill_color: Some(self.minimap.background),
             line_style: LineStyle::Solid,
         });
-        let draw2 = DrawWrap::new(&draw, self);
-        for (i, elem) in self.elems.iter().enumerate() {
-            elem.draw(
-                &draw2,
-                if i == (self.hover_index as usize) {
-                    if let Mode::EditResizing(darg_point_index) = self.mode {
-                        Status::Resizing(darg_point_index)
-                    } else {
-                        Status::Hover
-                    }
-                } else if let Mode::Creating(_) = self.mode {
-                    Status::Creating
-                } else if self.selects.contains(&(i as u32)) {
-                    Status::Hover
-                } else {
-                    Status::Default
-                },
+        if let Some(page) = self.page_rect() {
+            draw_page(
+                draw,
+                page,
+                view.lt_coord,
+                view.scale,
+                self.theme.canvas_background,
             );
         }
-        if let Some(select_box) = &self.select_box {
-            select_box.draw(&draw2, Status::Creating)
-        }
-        return draw.draw_end();
+        let draw2 = DrawWrap::with_view(draw, self, view.lt_coord, view.scale);
+        self.draw_layers(&draw2, |_| Some(Status::Default));
+        let visible = self.visible_rect();
+        draw.draw_rect(DrawRectOpts {
+            left_top_coord: view.to_screen(visible.min()),
+            width: visible.width() * view.scale,
+            height: visible.height() * view.scale,
+            line_size: 1.,
+            line_color: self.minimap.viewport_color,
+            fill_color: None,
+            line_style: LineStyle::Solid,
+        });
     }
 
     pub fn trigger_event(&mut self, event_type: EventType, inp_mouse_coord: Coordinate) {
+        if let Some(session) = &mut self.session {
+            session.record(SessionAction::Event {
+                event_type,
+                coord: inp_mouse_coord,
+            });
+        }
+        if let EventType::TouchStart(_) | EventType::TouchMove(_) | EventType::TouchEnd(_) =
+            event_type
+        {
+            let now = self.now();
+            let actions = self.gestures.handle(event_type, inp_mouse_coord, now);
+            self.apply_gestures(actions);
+            // The mouse events made up from touches flushed already.
+            self.flush_view();
+            return;
+        }
+        self.dispatch_event(event_type, inp_mouse_coord);
+    }
+
+    /// `trigger_event` without recording, also used for the mouse events
+    /// made up from touches.
+    fn dispatch_event(&mut self, event_type: EventType, inp_mouse_coord: Coordinate) {
+        match event_type {
+            EventType::Push(_) => self.changes.held = true,
+            EventType::Released(_) => self.changes.held = false,
+            _ => {}
+        }
+        self.sync_metas();
+        if self.handle_minimap(event_type, inp_mouse_coord)
+            || self.handle_pan(event_type, inp_mouse_coord)
+        {
+            self.flush_view();
+            return;
+        }
         let relative_coord = self.relative_coord(inp_mouse_coord);
-        let mouse_point = point!(relative_coord);
-        let hover_index = &mut self.hover_index;
-        let drag_vertex = &mut self.drag_vertex;
+        if let EventType::Move(_)
+        | EventType::Push(_)
+        | EventType::Released(_)
+        | EventType::Drag(_) = event_type
+        {
+            self.mouse_coord = inp_mouse_coord;
+        }
+        let button = match event_type {
+            EventType::Move(button)
+            | EventType::Push(button)
+            | EventType::Released(button)
+            | EventType::Drag(button) => button,
+            _ => EventMouseButton::None,
+        };
+        let event = ToolEvent {
+            button,
+            coord: relative_coord,
+            raw_coord: inp_mouse_coord,
+        };
         // println!(
         //     "[DEBUG] mode: {:?}, event_type: {:?}, mouse_point: {:?}",
-        //     self.mode,
+        //     self.mode(),
         //     event_type,
-        //     (mouse_point.0.x, mouse_point.0.y)
+        //     (relative_coord.x, relative_coord.y)
         // );
+        if let EventType::Zoom(zoom) = event_type {
+            match zoom {
+                EventZoom::None => {}
+                EventZoom::Grow => self.viewport.zoom_in(inp_mouse_coord),
+                EventZoom::Dwindle => self.viewport.zoom_out(inp_mouse_coord),
+                EventZoom::Factor(factor) => self
+                    .viewport
+                    .zoom_to(self.viewport.scale * factor, inp_mouse_coord),
+            }
+            self.flush_view();
+            return;
+        }
+        if let EventType::Push(_) = event_type {
+            self.prev_coord = relative_coord;
+            self.raw_prev_coord = inp_mouse_coord;
+        }
+
+        let mut tool = self.tool.take().unwrap();
         match event_type {
-            EventType::Move(_) => {
-                if let Mode::EditState = self.mode {
-                } else {
-                    *hover_index.borrow_mut() = -1;
-                    let len = self.elems.len();
-                    for (i, elem) in self.elems.iter().rev().enumerate() {
-                        if elem.hover_condition(mouse_point) {
-                            *hover_index = (len - i - 1) as isize;
-                            break;
-                        }
-                    }
-                }
+            EventType::Move(_) => tool.on_move(self, &event),
+            EventType::Push(_) => tool.on_push(self, &event),
+            EventType::Released(_) => tool.on_release(self, &event),
+            EventType::Drag(_) => tool.on_drag(self, &event),
+            EventType::Dblclick => tool.on_dblclick(self, &event),
+            EventType::KeyDown(key) => tool.on_key(self, key, true),
+            EventType::KeyUp(key) => tool.on_key(self, key, false),
+            EventType::Zoom(_)
+            | EventType::TouchStart(_)
+            | EventType::TouchMove(_)
+            | EventType::TouchEnd(_) => {}
+        }
+        // Unless the tool switched to another one.
+        if self.tool.is_none() {
+            self.tool = Some(tool);
+        }
+        // Moving the mouse only changes what is hovered.
+        if let EventType::Move(_) = event_type {
+            self.flush_view();
+        } else {
+            self.flush();
+        }
+    }
+
+    /// Centers the view on what is under the cursor while dragging with the
+    /// left button in the minimap. Returns true when the event was used up.
+    fn handle_minimap(&mut self, event_type: EventType, coord: Coordinate) -> bool {
+        match event_type {
+            EventType::Push(EventMouseButton::Left)
+                if self.minimap.contains(self.region(), coord) =>
+            {
+                self.pan_velocity = Coordinate::zero();
+                self.minimap.dragging = Some(self.minimap_view());
             }
-            EventType::Push(_) => {
-                self.prev_coord = relative_coord;
-                self.raw_prev_coord = inp_mouse_coord;
-                let idx = *hover_index;
-
-                match &mut self.mode {
-                    Mode::EditMoving => {
-                        let elem = self.elems.get_mut(idx as usize);
-                        if let Some(elem) = elem {
-                            let vertex = elem.get_vertex();
-                            for (i, coord) in vertex.iter().enumerate() {
-                                let point = Point::new(coord.x, coord.y);
-                                if mouse_point.euclidean_distance(&point) < 10. {
-                                    self.mode = Mode::EditResizing(i as u8);
-                                    *drag_vertex = i as isize;
-                                }
-                            }
-                        }
-                        if let Some(select_box) = &self.select_box {
-                            let select_box_ver = select_box.get_vertex();
-                            let tl = select_box_ver.get(0).unwrap();
-                            let br = select_box_ver.get(2).unwrap();
-                            let box_rect = geo::Rect::new(*tl, *br);
-                            if box_rect
-                                .to_polygon()
-                                .euclidean_distance(&geo::Point::from(relative_coord))
-                                > 0.
-                            {
-                                self.selects.clear();
-                                self.select_box = None;
-                            }
-                        }
-                    }
-                    Mode::Creating(elem) => {
-                        if let Some(elem) = elem.take() {
-                            self.hook_event
-                                .as_mut()
-                                .unwrap()
-                                .begin_create(&elem, relative_coord);
-                            self.elems.push(elem);
-                        } else {
-                            let elem = self.elems.last_mut();
-                            self.hook_event
-                                .as_mut()
-                                .unwrap()
-                                .end_create(elem.unwrap(), relative_coord);
-                            self.mode = Mode::EditMoving;
-                        }
-                    }
-                    Mode::EditResizing(_) => {}
-                    Mode::Deleting => {
-                        if idx > -1 {
-                            self.elems.remove(idx as usize);
-                            self.selects.clear();
-                            self.select_box = None;
-                        } else if let Some(select_box) = self.select_box.borrow_mut() {
-                            if select_box.hover_condition(mouse_point) {
-                                let mut ver = Vec::from_iter(self.selects.iter());
-                                ver.sort();
-                                for select in ver.iter().rev() {
-                                    self.elems.remove(**select as usize);
-                                }
-
-                                self.selects.clear();
-                                self.select_box = None;
-                                self.flush();
-                            }
-                        }
-                    }
-                    Mode::EditState => {
-                        let elem = self.elems.last_mut();
-                        self.mode = Mode::EditMoving;
-                        self.hook_event
-                            .as_mut()
-                            .unwrap()
-                            .end_edit_state(elem.unwrap(), relative_coord);
-                    }
-                    Mode::Select => {
-                        self.selects.clear();
-                        self.select_box = Some(Rect {
-                            line_color: 0x000000,
-                            line_size: 1.,
-                            line_style: LineStyle::Dotted,
-                            ..Rect::default()
-                        });
-                    }
-                }
+            EventType::Drag(_) if self.minimap.dragging.is_some() => {}
+            EventType::Released(_) if self.minimap.dragging.is_some() => {
+                self.minimap.dragging = None;
+                return true;
             }
-            EventType::Released(_) => match self.mode {
-                Mode::EditMoving => {}
-                Mode::Creating(_) => {
-                    let elem = self.elems.last().unwrap();
-                    if elem.need_input() {
-                        let vec = elem.get_vertex();
-                        let event_rect = self.calc_event_rect(vec);
-                        let elem = self.elems.last_mut().unwrap();
-                        self.hook_event
-                            .as_mut()
-                            .unwrap()
-                            .end_create(elem, relative_coord);
-                        self.hook_event
-                            .as_mut()
-                            .unwrap()
-                            .begin_edit_state(elem, event_rect);
-                        self.mode = Mode::EditState;
-                    }
-                }
-                Mode::EditResizing(_) => {
-                    self.mode = Mode::EditMoving;
+            _ => return false,
+        }
+        let world = self.minimap_view().to_world(coord);
+        let center = self.region().center();
+        self.viewport
+            .pan_by(center - self.viewport.to_screen(world));
+        true
+    }
+
+    /// Pans for the input in `pan_bindings`. Returns true when the event
+    /// was used up and the tool shouldn't see it.
+    fn handle_pan(&mut self, event_type: EventType, coord: Coordinate) -> bool {
+        match event_type {
+            EventType::KeyDown(EventKey::Space) => self.space_down = true,
+            EventType::KeyUp(EventKey::Space) => self.space_down = false,
+            EventType::Push(button) => {
+                // Any press stops gliding.
+                self.pan_velocity = Coordinate::zero();
+                let space_drag = self.pan_bindings.space_drag
+                    && self.space_down
+                    && button == EventMouseButton::Left;
+                if space_drag || self.pan_bindings.buttons.contains(&button) {
+                    self.panning = Some(Instant::now());
+                    self.raw_prev_coord = coord;
+                    return true;
                 }
-                Mode::Deleting => {}
-                Mode::EditState => {}
-                Mode::Select => {
-                    let select_box = self.select_box.borrow_mut().as_ref().unwrap();
-                    let select_box_ver = select_box.get_vertex();
-                    let tl: Coordinate<f64> = *select_box_ver.get(0).unwrap();
-                    let br: Coordinate<f64> = *select_box_ver.get(2).unwrap();
-                    let box_rect = geo::Rect::new(tl, br);
-                    for (i, elem) in self.elems.iter().enumerate() {
-                        let ver = elem.get_vertex();
-                        let mut is_select = true;
-                        for coord in ver {
-                            if !box_rect.intersects(&geo::Point::from(coord)) {
-                                is_select = false;
-                            }
-                        }
-                        if is_select {
-                            self.selects.insert(i as u32);
-                        }
+            }
+            EventType::Drag(_) => {
+                if let Some(last) = self.panning {
+                    let delta = coord - self.raw_prev_coord;
+                    self.viewport.pan_by(delta);
+                    let now = Instant::now();
+                    let dt = (now - last).as_secs_f64();
+                    if dt > 0. {
+                        // Averaged, a single jerky step shouldn't decide.
+                        self.pan_velocity = (self.pan_velocity + delta / dt) / 2.;
                     }
-                    self.mode = Mode::EditMoving;
+                    self.panning = Some(now);
+                    self.raw_prev_coord = coord;
+                    return true;
                 }
-            },
-            EventType::Drag(mouse_button) => match self.mode {
-                Mode::Creating(_) => {
-                    let top = self.elems.last_mut();
-                    if let Some(elem) = top {
-                        elem.creating(self.prev_coord, relative_coord);
-                        self.hook_event
-                            .as_mut()
-                            .unwrap()
-                            .doing_create(elem, relative_coord);
+            }
+            EventType::Released(_) => {
+                if let Some(last) = self.panning.take() {
+                    // Holding still before letting go doesn't fling.
+                    if !self.pan_bindings.inertia || last.elapsed().as_secs_f64() > 0.1 {
+                        self.pan_velocity = Coordinate::zero();
                     }
+                    return true;
                 }
-                Mode::EditMoving => {
-                    let mut is_move_elem = false;
-                    if self.selects.is_empty() {
-                        let idx = *hover_index;
-                        let elem = self.elems.get_mut(idx as usize);
-                        if let Some(elem) = elem {
-                            elem.edit_moving(self.prev_coord, relative_coord);
-                            is_move_elem = true;
-                        }
-                    } else {
-                        for idx in self.selects.iter() {
-                            let elem = self.elems.get_mut((*idx) as usize);
-                            if let Some(elem) = elem {
-                                elem.edit_moving(self.prev_coord, relative_coord);
-                                is_move_elem = true;
-                            }
-                        }
-                        let select_box = self.select_box.as_mut().unwrap();
-                        select_box.edit_moving(self.prev_coord, relative_coord);
-                    }
+            }
+            _ => {}
+        }
+        false
+    }
 
-                    if !is_move_elem {
-                        self.move_pos(self.raw_prev_coord, inp_mouse_coord);
-                    }
+    /// Lets the view glide on after a pan. Binders call it every frame with
+    /// the seconds since the last one. Returns true while it still moves.
+    /// Also reports long presses, so it should keep being called while a
+    /// finger is down.
+    pub fn tick(&mut self, dt: f64) -> bool {
+        // A replay polls at the recorded ticks instead.
+        if self.clock.is_none() {
+            self.poll_gestures();
+        }
+        let pending = self.gestures.is_pending();
+        let speed = self.pan_velocity.x.hypot(self.pan_velocity.y);
+        if self.panning.is_some() || speed < 1. {
+            self.pan_velocity = Coordinate::zero();
+            return pending;
+        }
+        self.viewport.pan_by(self.pan_velocity * dt);
+        self.pan_velocity = self.pan_velocity * self.pan_bindings.friction.powf(dt);
+        self.flush_view();
+        true
+    }
 
-                    self.raw_prev_coord = inp_mouse_coord;
-                    self.prev_coord = relative_coord;
-                }
-                Mode::EditResizing(_) => {
-                    let idx = *hover_index;
-                    let elem = self.elems.get_mut(idx as usize);
-                    if let Some(elem) = elem {
-                        elem.edit_resizing(self.prev_coord, relative_coord, *drag_vertex as i32);
-                    }
-                }
-                Mode::Deleting => {}
-                Mode::EditState => {}
-                Mode::Select => {
-                    if let Some(select_box) = &mut self.select_box {
-                        select_box.creating(self.prev_coord, relative_coord);
-                    }
-                }
-            },
-            EventType::Dblclick => {
-                let idx = *hover_index;
-                if idx > -1 {
-                    let vec = self.elems.get(idx as usize).unwrap().get_vertex();
-                    let event_rect = self.calc_event_rect(vec);
-
-                    let elem = self.elems.get_mut(idx as usize).unwrap();
-                    self.mode = Mode::EditState;
-                    self.hook_event
-                        .as_mut()
-                        .unwrap()
-                        .begin_edit_state(elem, event_rect);
+    /// Applies what the gesture recognizer reports by itself, e.g. a long
+    /// press.
+    pub(crate) fn poll_gestures(&mut self) {
+        let actions = self.gestures.poll(self.now());
+        if actions.is_empty() {
+            return;
+        }
+        if let Some(session) = &mut self.session {
+            session.record(SessionAction::Tick);
+        }
+        self.apply_gestures(actions);
+        self.flush_view();
+    }
+
+    fn now(&self) -> Instant {
+        self.clock.unwrap_or_else(Instant::now)
+    }
+
+    fn apply_gestures(&mut self, actions: Vec<GestureAction>) {
+        for action in actions {
+            match action {
+                GestureAction::Mouse(event_type, coord) => self.dispatch_event(event_type, coord),
+                GestureAction::Zoom { factor, anchor } => {
+                    self.viewport.zoom_to(self.viewport.scale * factor, anchor);
                 }
+                GestureAction::Pan(delta) => self.viewport.pan_by(delta),
             }
-            EventType::Zoom(zoom) => match zoom {
-                EventZoom::None => {}
-                EventZoom::Grow => {
-                    self.set_scale(self.scale + 0.01, inp_mouse_coord.x, inp_mouse_coord.y);
-                }
-                EventZoom::Dwindle => {
-                    self.set_scale(self.scale - 0.01, inp_mouse_coord.x, inp_mouse_coord.y);
+        }
+    }
+
+    /// Starts gliding at `velocity`, in binder coordinates per second, e.g.
+    /// for a touchpad fling.
+    pub fn fling(&mut self, velocity: Coordinate) {
+        self.pan_velocity = velocity;
+    }
+
+    /// What the cursor should look like, from the pan state and the tool.
+    /// `None` while a tool method runs.
+    fn current_cursor(&self) -> Option<Cursor> {
+        if self.panning.is_some() || self.minimap.dragging.is_some() {
+            return Some(Cursor::Grabbing);
+        }
+        if self.space_down && self.pan_bindings.space_drag {
+            return Some(Cursor::Grab);
+        }
+        Some(self.tool.as_ref()?.cursor(self))
+    }
+
+    /// The cursor last sent to the binder.
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    /// Whether the topmost visible element at `coord` is on a locked layer.
+    pub fn is_locked_at(&self, coord: Coordinate) -> bool {
+        let mouse_point = point!(coord);
+        self.elems
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(i, _)| self.elem_layer(*i).map_or(true, |layer| layer.visible))
+            .find(|(_, elem)| elem.hover_condition(mouse_point))
+            .map_or(false, |(i, _)| !self.is_editable(i))
+    }
+
+    /// Makes the topmost element under `coord` the hovered one.
+    pub fn update_hover(&mut self, coord: Coordinate) {
+        let mouse_point = point!(coord);
+        self.hover_index = -1;
+        let len = self.elems.len();
+        let entered = self.entered.last();
+        for (i, elem) in self.elems.iter().rev().enumerate() {
+            let index = len - i - 1;
+            if !self.is_editable(index) {
+                continue;
+            }
+            // Only the children of an entered group can be picked.
+            if let Some(entered) = entered {
+                if !entered.members.contains(&self.metas[index].id) {
+                    continue;
                 }
-            },
-        };
-        self.flush();
+            }
+            if elem.hover_condition(mouse_point) {
+                self.hover_index = index as isize;
+                break;
+            }
+        }
+    }
+
+    /// Opens the binder's input on the element at `index` and switches to
+    /// `TextEditTool`. Returns false when `before_edit_state` refused.
+    pub fn begin_edit_state(&mut self, index: usize) -> bool {
+        let vec = self.elems[index].get_vertex();
+        let event_rect = self.calc_event_rect(vec);
+        let elem = self.elems.get_mut(index).unwrap();
+        let hook_event = self.hook_event.as_mut().unwrap();
+        if !hook_event.before_edit_state(elem) {
+            return false;
+        }
+        hook_event.begin_edit_state(elem, event_rect);
+        self.set_tool(Box::new(TextEditTool::new(Some(index))));
+        true
     }
 
-    fn calc_event_rect(&self, ver: Vec<Coordinate>) -> EventRect {
+    pub fn set_tool(&mut self, tool: Box<dyn Tool>) {
+        self.tool = Some(tool);
+    }
+
+    /// The current tool as sessions and change events describe it.
+    pub fn mode(&self) -> SessionMode {
+        self.tool
+            .as_ref()
+            .map_or(SessionMode::EditMoving, |tool| tool.mode())
+    }
+
+    pub(crate) fn calc_event_rect(&self, ver: Vec<Coordinate>) -> EventRect {
         let left_top = ver.get(0).unwrap();
         let right_bottom = ver.get(2).unwrap();
         let w_h = *right_bottom - *left_top;
         EventRect {
             coord: self.absolute_coord(*left_top),
-            width: w_h.x * self.scale,
-            height: w_h.y * self.scale,
+            width: w_h.x * self.viewport.scale,
+            height: w_h.y * self.viewport.scale,
         }
     }
 
+    /// Zooms around `x`, `y` in binder coordinates. The scale is kept
+    /// within the viewport's limits.
     pub fn set_scale(&mut self, val: f64, x: f64, y: f64) {
-        let zoom = self.scale - val;
-        let scale_coord = self.relative_coord(coord! {x:x, y:y });
+        self.viewport.zoom_to(val, coord! { x: x, y: y });
+        self.flush_view();
+    }
 
-        self.lt_coord.x += (scale_coord.x) * zoom;
-        self.lt_coord.y += (scale_coord.y) * zoom;
+    pub fn scale(&self) -> f64 {
+        self.viewport.scale
+    }
 
-        self.scale = val;
+    pub fn move_pos(&mut self, prev: Coordinate, target: Coordinate) {
+        self.viewport.pan_by(target - prev);
+    }
 
-        self.flush();
+    /// Frames every element, or shows the page when there are none.
+    pub fn zoom_to_fit(&mut self) {
+        let all: Vec<usize> = (0..self.elems.len()).collect();
+        match self.bounds(&all).or(self.page_rect()) {
+            Some(rect) => self.zoom_to_rect(rect),
+            None => self.reset_view(),
+        }
     }
 
-    pub fn scale(&self) -> f64 {
-        self.scale
+    /// Frames the selected elements. Does nothing without a selection.
+    pub fn zoom_to_selection(&mut self) {
+        let selects: Vec<usize> = self.selects.iter().map(|i| *i as usize).collect();
+        if let Some(bounds) = self.bounds(&selects) {
+            self.zoom_to_rect(bounds);
+        }
     }
 
-    pub fn move_pos(&mut self, prev: Coordinate, target: Coordinate) {
-        let diff = target - prev;
-        self.lt_coord.x += diff.x;
-        self.lt_coord.y += diff.y;
+    /// Shows `rect` (panel coordinates) centered in the binder's region.
+    pub fn zoom_to_rect(&mut self, rect: geo::Rect) {
+        self.viewport.fit(rect, self.region(), FIT_PADDING);
+        self.flush_view();
     }
 
+    /// Back to the initial view.
+    pub fn reset_view(&mut self) {
+        self.viewport.reset(self.raw_lt_coord);
+        self.flush_view();
+    }
+
+    /// From binder to panel coordinates.
     pub fn relative_coord(&self, coord: Coordinate) -> Coordinate {
-        let relative_coord = coord - self.lt_coord;
-        let relative_coord = relative_coord / self.scale;
-        relative_coord
+        self.viewport.to_world(coord)
     }
 
+    /// From panel to binder coordinates.
     pub fn absolute_coord(&self, coord: Coordinate) -> Coordinate {
-        coord! {
-            x: (self.lt_coord.x + coord.x) * self.scale + self.lt_coord.x * (1. - self.scale),
-            y: (self.lt_coord.y + coord.y) * self.scale + self.lt_coord.y * (1. - self.scale),
-        }
+        self.viewport.to_screen(coord)
     }
 
     pub fn export(&self) -> String {
-        let mut panel_serialize = PanelSerialize { elems: vec![] };
-        for elem in self.elems.iter() {
+        let mut panel_serialize = self.serialize();
+        panel_serialize.pages = self.pages.clone();
+        panel_serialize.current_page = self.current_page;
+        panel_serialize.canvas = self.canvas;
+        panel_serialize.units = self.units;
+        return serde_json::to_string(&panel_serialize).unwrap();
+    }
+
+    /// The elements and layers, without the canvas.
+    fn serialize(&self) -> PanelSerialize {
+        let mut list: Vec<(ElemId, LayerId, String)> = vec![];
+        for (elem, meta) in self.elems.iter().zip(self.metas.iter()) {
             let elem_seria = format!(r#"{}({})"#, elem.elem_type(), elem.export());
-            panel_serialize.elems.push(elem_seria);
+            list.push((meta.id, meta.layer, elem_seria));
+        }
+        // Entered groups are saved closed.
+        for entered in self.entered.iter().rev() {
+            let positions: Vec<usize> = (0..list.len())
+                .filter(|i| entered.members.contains(&list[*i].0))
+                .collect();
+            if positions.is_empty() {
+                continue;
+            }
+            let children = PanelSerialize {
+                elems: positions.iter().map(|i| list[*i].2.clone()).collect(),
+                ..Default::default()
+            };
+            let layer = list[positions[0]].1;
+            for i in positions.iter().rev() {
+                list.remove(*i);
+            }
+            let group = format!("group({})", serde_json::to_string(&children).unwrap());
+            list.insert(positions[0], (entered.id, layer, group));
+        }
+        PanelSerialize {
+            elem_ids: list.iter().map(|(id, _, _)| *id).collect(),
+            elem_layers: list.iter().map(|(_, layer, _)| *layer).collect(),
+            elems: list
+                .into_iter()
+                .map(|(_, _, elem_seria)| elem_seria)
+                .collect(),
+            layers: self.layers.clone(),
+            ..Default::default()
         }
-        return serde_json::to_string(&panel_serialize).unwrap();
     }
 
     pub fn import(&mut self, data: &str) {
+        let panel_serialize: PanelSerialize = serde_json::from_str(data).unwrap();
+        self.canvas = panel_serialize.canvas;
+        self.pages = panel_serialize.pages;
+        self.current_page = panel_serialize.current_page;
+        self.units = panel_serialize.units;
+        if self.canvas == CanvasMode::Paged && self.pages.is_empty() {
+            self.pages
+                .push(Page::new("Page 1", self.width, self.height));
+        }
+        self.current_page = self.current_page.min(self.pages.len().saturating_sub(1));
+        self.load(data);
+        self.flush();
+    }
+
+    /// Replaces the elements and layers with those of `data`.
+    fn load(&mut self, data: &str) {
+        let elems = self.parse_elems(data);
+        let panel_serialize: PanelSerialize = serde_json::from_str(data).unwrap();
+        self.set_layers(panel_serialize.layers);
+        self.set_elems(elems);
+        for (meta, layer) in self.metas.iter_mut().zip(panel_serialize.elem_layers) {
+            meta.layer = layer;
+        }
+        if panel_serialize.elem_ids.len() == self.metas.len() {
+            for (meta, id) in self.metas.iter_mut().zip(panel_serialize.elem_ids) {
+                meta.id = id;
+                self.next_elem_id = self.next_elem_id.max(id + 1);
+            }
+        }
+        self.sort_by_layer(&[]);
+    }
+
+    /// Where the cursor is, with the size of the selection or else of the
+    /// hovered element, in the document's unit.
+    pub fn readout(&self) -> Readout {
+        let coord = self.relative_coord(self.mouse_coord);
+        let mut indices: Vec<usize> = self.selects.iter().map(|i| *i as usize).collect();
+        if indices.is_empty() && self.hover_index >= 0 {
+            indices.push(self.hover_index as usize);
+        }
+        Readout {
+            unit: self.units.unit,
+            x: self.units.to_unit(coord.x),
+            y: self.units.to_unit(coord.y),
+            size: self.bounds(&indices).map(|bounds| {
+                (
+                    self.units.to_unit(bounds.width()),
+                    self.units.to_unit(bounds.height()),
+                )
+            }),
+        }
+    }
+
+    /// The current page in panel coordinates, `None` on an infinite canvas.
+    pub fn page_rect(&self) -> Option<geo::Rect> {
+        let (width, height) = match self.canvas {
+            CanvasMode::Region => (self.width, self.height),
+            CanvasMode::Infinite => return None,
+            CanvasMode::Paged => {
+                let page = &self.pages[self.current_page];
+                (page.width, page.height)
+            }
+        };
+        Some(geo::Rect::new(
+            coord! { x: 0., y: 0. },
+            coord! { x: width, y: height },
+        ))
+    }
+
+    /// Switches the canvas. Leaving `Paged` shows the current page's
+    /// elements and keeps the other pages, hidden and unchanged, until it is
+    /// paged again. Going to `Paged` without pages makes the current
+    /// elements the first page.
+    pub fn set_canvas_mode(&mut self, canvas: CanvasMode) {
+        if canvas == self.canvas {
+            return;
+        }
+        if canvas == CanvasMode::Paged && self.pages.is_empty() {
+            self.current_page = 0;
+            self.pages
+                .push(Page::new("Page 1", self.width, self.height));
+        }
+        self.canvas = canvas;
+        self.flush();
+    }
+
+    /// Adds an empty page after the others and returns its index. Switches
+    /// to `CanvasMode::Paged` first if needed.
+    pub fn add_page(&mut self, name: &str, width: f64, height: f64) -> usize {
+        self.set_canvas_mode(CanvasMode::Paged);
+        self.pages.push(Page::new(name, width, height));
+        self.pages.len() - 1
+    }
+
+    /// Removes a page and its elements. The last page can't be removed.
+    pub fn remove_page(&mut self, index: usize) -> bool {
+        if self.canvas != CanvasMode::Paged || index >= self.pages.len() || self.pages.len() == 1 {
+            return false;
+        }
+        if index == self.current_page {
+            let next = if index == 0 { 1 } else { index - 1 };
+            self.go_to_page(next);
+        }
+        self.pages.remove(index);
+        if self.current_page > index {
+            self.current_page -= 1;
+        }
+        self.flush();
+        true
+    }
+
+    /// Shows the page at `index` and clears the selection.
+    pub fn go_to_page(&mut self, index: usize) -> bool {
+        if self.canvas != CanvasMode::Paged || index >= self.pages.len() {
+            return false;
+        }
+        if index != self.current_page {
+            self.switch_page(index);
+            self.selects.clear();
+            self.select_box = None;
+            self.hover_index = -1;
+            self.drag_vertex = -1;
+        }
+        self.flush();
+        true
+    }
+
+    pub fn next_page(&mut self) -> bool {
+        self.go_to_page(self.current_page + 1)
+    }
+
+    pub fn prev_page(&mut self) -> bool {
+        self.current_page > 0 && self.go_to_page(self.current_page - 1)
+    }
+
+    /// Stores the current page's elements in its `Page::document` and loads
+    /// those of the page at `index`. Elements keep their ids, entered
+    /// groups are left.
+    fn switch_page(&mut self, index: usize) {
+        while self.leave_group() {}
+        let document = serde_json::to_string(&self.serialize()).unwrap();
+        let current = &mut self.pages[self.current_page];
+        current.document = document;
+        current.metas = std::mem::take(&mut self.metas);
+        self.current_page = index;
+        let page = &mut self.pages[index];
+        let document = std::mem::take(&mut page.document);
+        let metas = std::mem::take(&mut page.metas);
+        if document.is_empty() {
+            self.set_layers(vec![]);
+            self.set_elems(vec![]);
+        } else {
+            self.load(&document);
+        }
+        if metas.len() == self.metas.len() {
+            self.metas = metas;
+        }
+    }
+
+    /// The elements of the page at `index` as a document of their own.
+    pub fn export_page(&self, index: usize) -> Option<String> {
+        if self.canvas != CanvasMode::Paged {
+            return (index == 0).then(|| serde_json::to_string(&self.serialize()).unwrap());
+        }
+        let page = self.pages.get(index)?;
+        if index == self.current_page {
+            Some(serde_json::to_string(&self.serialize()).unwrap())
+        } else if page.document.is_empty() {
+            Some(serde_json::to_string(&PanelSerialize::default()).unwrap())
+        } else {
+            Some(page.document.clone())
+        }
+    }
+
+    /// Runs `f` on a panel showing the page at `index`. Other pages are
+    /// loaded into a panel of their own, with the same elements registered
+    /// and the same theme, so the current page isn't touched.
+    pub fn with_page<T>(&self, index: usize, f: impl FnOnce(&Panel) -> T) -> Option<T> {
+        if self.canvas != CanvasMode::Paged {
+            return (index == 0).then(|| f(self));
+        }
+        let page = self.pages.get(index)?;
+        if index == self.current_page {
+            return Some(f(self));
+        }
+        let registry = self
+            .register_elem_map
+            .values()
+            .map(|elem| self.parse_elem(&format!("{}({})", elem.elem_type(), elem.export())))
+            .collect();
+        let (x, y) = (self.raw_lt_coord.x, self.raw_lt_coord.y);
+        let mut panel = Panel::new(x, y, self.width, self.height, registry);
+        panel.viewport = self.viewport;
+        panel.units = self.units;
+        panel.theme = self.theme.clone();
+        panel.canvas = CanvasMode::Paged;
+        panel.pages = vec![Page::new(&page.name, page.width, page.height)];
+        if !page.document.is_empty() {
+            panel.load(&page.document);
+        }
+        Some(f(&panel))
+    }
+
+    /// Replaces the layers, an empty list gets one default layer. The top
+    /// layer becomes the active one.
+    fn set_layers(&mut self, mut layers: Vec<Layer>) {
+        if layers.is_empty() {
+            layers.push(Layer::new(1, "Layer 1"));
+        }
+        self.next_layer_id = layers.iter().map(|layer| layer.id).max().unwrap() + 1;
+        self.active_layer = layers.last().unwrap().id;
+        self.layers = layers;
+    }
+
+    /// Builds the elements of an `export`ed document without adding them to
+    /// the panel.
+    pub fn parse_elems(&self, data: &str) -> Vec<Box<dyn IElem>> {
         let json = &mut serde_json::Deserializer::from_slice(data.as_bytes());
         let mut json: Box<dyn Deserializer> = Box::new(<dyn Deserializer>::erase(json));
         let panel_serialize: PanelSerialize = erased_serde::deserialize(&mut json).unwrap();
@@ -439,31 +1057,548 @@ impl Panel {
         let mut elems: Vec<Box<dyn IElem>> = Vec::new();
 
         for elem_seria in panel_serialize.elems.iter() {
-            // 解析出类型和元素
-            let elem_type = elem_seria.split("(").next().unwrap();
-            let elem_seria = elem_seria.split("(").nth(1).unwrap();
-            let elem_seria = elem_seria.split(")").next().unwrap();
+            elems.push(self.parse_elem(elem_seria));
+        }
+
+        elems
+    }
+
+    /// `parse_elems` for documents from outside, e.g. the system clipboard.
+    /// Fails instead of panicking when an element can't be built.
+    pub fn try_parse_elems(
+        &self,
+        data: &str,
+    ) -> Result<(PanelSerialize, Vec<Box<dyn IElem>>), PasteError> {
+        let panel_serialize: PanelSerialize = serde_json::from_str(data)?;
+        let elems = panel_serialize
+            .elems
+            .iter()
+            .map(|elem_seria| try_parse_elem_with(&self.register_elem_map, elem_seria))
+            .collect::<Result<_, _>>()?;
+        Ok((panel_serialize, elems))
+    }
 
-            let elem = self.borrow_mut().register_elem_map.get(elem_type).unwrap();
-            let elem = elem.import(elem_seria);
-            elems.push(elem);
+    /// Builds one element from its `type(content)` form.
+    pub fn parse_elem(&self, elem_seria: &str) -> Box<dyn IElem> {
+        parse_elem_with(&self.register_elem_map, elem_seria)
+    }
+
+    /// The elements at `indices` as a document of their own, in paint
+    /// order.
+    pub fn export_elems(&self, indices: &[usize]) -> String {
+        let mut indices = indices.to_vec();
+        indices.sort();
+        indices.dedup();
+        indices.retain(|i| *i < self.elems.len());
+        let panel_serialize = PanelSerialize {
+            elems: indices
+                .iter()
+                .map(|i| &self.elems[*i])
+                .map(|elem| format!(r#"{}({})"#, elem.elem_type(), elem.export()))
+                .collect(),
+            elem_ids: indices.iter().filter_map(|i| self.elem_id(*i)).collect(),
+            ..Default::default()
+        };
+        serde_json::to_string(&panel_serialize).unwrap()
+    }
+
+    /// A copy of the element at `index`, made by exporting and importing
+    /// it.
+    pub fn clone_elem(&self, index: usize) -> Option<Box<dyn IElem>> {
+        let elem = self.elems.get(index)?;
+        Some(self.parse_elem(&format!("{}({})", elem.elem_type(), elem.export())))
+    }
+
+    /// Selects the elements at `indices`, with a selection box around them
+    /// so they can be dragged together.
+    pub fn select(&mut self, indices: &[usize]) {
+        self.selects = indices
+            .iter()
+            .filter(|i| **i < self.elems.len())
+            .map(|i| *i as u32)
+            .collect();
+        let indices: Vec<usize> = self.selects.iter().map(|i| *i as usize).collect();
+        self.select_box = self.bounds(&indices).map(|bounds| Rect {
+            lt_coord: bounds.min(),
+            width: bounds.width(),
+            height: bounds.height(),
+            line_color: 0x000000,
+            line_size: 1.,
+            line_style: LineStyle::Dotted,
+            ..Rect::default()
+        });
+        self.flush();
+    }
+
+    /// Removes the elements at `indices` that `before_delete` allows and
+    /// clears the selection. Returns how many were removed.
+    pub fn delete_elems(&mut self, indices: &[usize]) -> usize {
+        let mut indices = indices.to_vec();
+        indices.sort();
+        indices.dedup();
+        let mut count = 0;
+        for index in indices.iter().rev() {
+            let elem = match self.elems.get(*index) {
+                Some(elem) => elem,
+                None => continue,
+            };
+            if !self.hook_event.as_mut().unwrap().before_delete(elem) {
+                continue;
+            }
+            self.remove_elem(*index);
+            count += 1;
         }
+        self.selects.clear();
+        self.select_box = None;
+        self.hover_index = -1;
+        self.flush();
+        count
+    }
+
+    /// Adds elements converted from another format. Connectors among them
+    /// refer to the others by their position in `elems`.
+    pub fn add_imported(&mut self, elems: Vec<Box<dyn IElem>>, mode: ImportMode) {
+        let positions: Vec<ElemId> = (0..elems.len() as ElemId).collect();
+        self.add_exported(elems, &positions, mode);
+    }
 
-        self.elems = elems;
+    /// `add_imported` for the elements of an `export`ed document, whose
+    /// connectors refer to the others by `PanelSerialize::elem_ids`.
+    pub fn add_exported(&mut self, elems: Vec<Box<dyn IElem>>, old: &[ElemId], mode: ImportMode) {
+        if let ImportMode::Replace = mode {
+            self.set_elems(vec![]);
+            self.selects.clear();
+            self.select_box = None;
+            self.hover_index = -1;
+        }
+        let ids: Vec<ElemId> = elems.into_iter().map(|elem| self.push_elem(elem)).collect();
+        if old.len() == ids.len() {
+            self.reattach(old, &ids);
+        } else {
+            self.reattach(&[], &ids);
+        }
         self.flush();
     }
 
+    /// Points the connectors among the elements `ids` at the copies of
+    /// what they were attached to, `ids[i]` being the copy of `old[i]`.
+    /// Ends attached to anything else come loose.
+    pub(crate) fn reattach(&mut self, old: &[ElemId], ids: &[ElemId]) {
+        let copy = |end: Option<ElemId>| {
+            let position = old.iter().position(|id| Some(*id) == end)?;
+            ids.get(position).copied()
+        };
+        for id in ids {
+            let index = match self.elem_index(*id) {
+                Some(index) => index,
+                None => continue,
+            };
+            if let Some((source, target)) = self.elems[index].ends() {
+                self.elems[index].set_ends(copy(source), copy(target));
+            }
+        }
+    }
+
+    /// Moves the ends of every connector onto the elements they are
+    /// attached to.
+    pub fn route_connectors(&mut self) {
+        self.sync_metas();
+        for index in 0..self.elems.len() {
+            let (source, target) = match self.elems[index].ends() {
+                Some(ends) => ends,
+                None => continue,
+            };
+            let terminal = |id: Option<ElemId>| {
+                let elem = &self.elems[self.elem_index(id?).filter(|i| *i != index)?];
+                Some(Terminal {
+                    bounds: elem.bounding_rect()?,
+                    ellipse: elem.elem_type() == "ellipse",
+                })
+            };
+            let (source, target) = (terminal(source), terminal(target));
+            if source.is_some() || target.is_some() {
+                self.elems[index].route(source, target);
+            }
+        }
+    }
+
+    /// Adds an element on top of the active layer and gives it a new id.
+    pub fn push_elem(&mut self, elem: Box<dyn IElem>) -> ElemId {
+        self.sync_metas();
+        let layer = self.active_layer;
+        let pos = self.layer_index(layer);
+        let index = self
+            .metas
+            .iter()
+            .filter(|meta| self.layer_index(meta.layer) <= pos)
+            .count();
+        self.insert_elem_in(index, elem, layer)
+    }
+
+    fn insert_elem_in(&mut self, index: usize, elem: Box<dyn IElem>, layer: LayerId) -> ElemId {
+        let id = self.next_elem_id;
+        self.next_elem_id += 1;
+        self.elems.insert(index, elem);
+        self.metas.insert(index, ElemMeta { id, layer });
+        id
+    }
+
+    pub fn remove_elem(&mut self, index: usize) -> Box<dyn IElem> {
+        self.sync_metas();
+        self.metas.remove(index);
+        self.elems.remove(index)
+    }
+
+    /// Adds an element at `index` in the paint order and gives it a new id.
+    /// It joins the layer of the element below it.
+    pub fn insert_elem(&mut self, index: usize, elem: Box<dyn IElem>) -> ElemId {
+        self.sync_metas();
+        let below = if index > 0 {
+            self.metas.get(index - 1)
+        } else {
+            None
+        };
+        let layer = below
+            .or(self.metas.get(index))
+            .map_or(self.active_layer, |meta| meta.layer);
+        self.insert_elem_in(index, elem, layer)
+    }
+
+    /// Replaces all elements, each gets a new id.
+    pub fn set_elems(&mut self, elems: Vec<Box<dyn IElem>>) {
+        self.elems.clear();
+        self.metas.clear();
+        self.entered.clear();
+        for elem in elems {
+            self.push_elem(elem);
+        }
+    }
+
+    /// Moves the elements at `indices` in the paint order, which is also
+    /// the hover order and the export order. Several elements keep their
+    /// order among each other. Selection and hover follow the elements.
+    pub fn reorder(&mut self, indices: &[usize], z_order: ZOrder) {
+        self.sync_metas();
+        let len = self.elems.len();
+        let mut moved = vec![false; len];
+        for index in indices.iter().filter(|i| **i < len) {
+            moved[*index] = true;
+        }
+        // Old indices in their new order. Elements stay in their layers.
+        let mut order: Vec<usize> = (0..len).collect();
+        match z_order {
+            ZOrder::Front => order.sort_by_key(|i| moved[*i]),
+            ZOrder::Back => order.sort_by_key(|i| !moved[*i]),
+            ZOrder::Forward => {
+                for i in (0..len.saturating_sub(1)).rev() {
+                    if moved[order[i]] && !moved[order[i + 1]] {
+                        order.swap(i, i + 1);
+                    }
+                }
+            }
+            ZOrder::Backward => {
+                for i in 1..len {
+                    if moved[order[i]] && !moved[order[i - 1]] {
+                        order.swap(i - 1, i);
+                    }
+                }
+            }
+        }
+        order.sort_by_key(|i| self.layer_index(self.metas[*i].layer));
+        self.apply_order(&order);
+        self.flush();
+    }
+
+    /// Rearranges the elements so `order[i]` becomes index `i`.
+    fn apply_order(&mut self, order: &[usize]) {
+        let len = self.elems.len();
+        let mut new_index = vec![0; len];
+        for (new, old) in order.iter().enumerate() {
+            new_index[*old] = new;
+        }
+        let mut elems: Vec<Option<Box<dyn IElem>>> = self.elems.drain(..).map(Some).collect();
+        self.elems = order.iter().map(|i| elems[*i].take().unwrap()).collect();
+        self.metas = order.iter().map(|i| self.metas[*i].clone()).collect();
+        self.selects = self
+            .selects
+            .iter()
+            .filter(|i| (**i as usize) < len)
+            .map(|i| new_index[*i as usize] as u32)
+            .collect();
+        if self.hover_index > -1 && (self.hover_index as usize) < len {
+            self.hover_index = new_index[self.hover_index as usize] as isize;
+        }
+    }
+
+    /// Brings the elements back into the order of their layers. Those at
+    /// `on_top` go above the rest of their layer.
+    fn sort_by_layer(&mut self, on_top: &[usize]) {
+        self.sync_metas();
+        let mut order: Vec<usize> = (0..self.elems.len()).collect();
+        order.sort_by_key(|i| (self.layer_index(self.metas[*i].layer), on_top.contains(i)));
+        self.apply_order(&order);
+    }
+
+    /// Position of the layer in `layers`.
+    pub fn layer_index(&self, id: LayerId) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.id == id)
+    }
+
+    pub fn layer(&self, id: LayerId) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.id == id)
+    }
+
+    pub fn layer_mut(&mut self, id: LayerId) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.id == id)
+    }
+
+    /// The layer of the element at `index`.
+    pub fn elem_layer(&self, index: usize) -> Option<&Layer> {
+        self.metas
+            .get(index)
+            .and_then(|meta| self.layer(meta.layer))
+    }
+
+    /// False for elements on hidden or locked layers.
+    pub fn is_editable(&self, index: usize) -> bool {
+        self.elem_layer(index)
+            .map_or(true, |layer| layer.is_editable())
+    }
+
+    /// Adds an empty layer on top and makes it the active one.
+    pub fn add_layer(&mut self, name: &str) -> LayerId {
+        let id = self.next_layer_id;
+        self.next_layer_id += 1;
+        self.layers.push(Layer::new(id, name));
+        self.active_layer = id;
+        id
+    }
+
+    /// Moves a layer to `to` in `layers`, its elements follow.
+    pub fn move_layer(&mut self, id: LayerId, to: usize) -> bool {
+        let from = match self.layer_index(id) {
+            Some(from) => from,
+            None => return false,
+        };
+        let layer = self.layers.remove(from);
+        self.layers.insert(to.min(self.layers.len()), layer);
+        self.sort_by_layer(&[]);
+        self.flush();
+        true
+    }
+
+    /// Puts the elements of `from` on top of those of `into` and removes
+    /// `from`.
+    pub fn merge_layer(&mut self, from: LayerId, into: LayerId) -> bool {
+        if from == into || self.layer(from).is_none() || self.layer(into).is_none() {
+            return false;
+        }
+        self.sync_metas();
+        let moved: Vec<usize> = (0..self.metas.len())
+            .filter(|i| self.metas[*i].layer == from)
+            .collect();
+        self.move_to_layer(&moved, into);
+        self.layers.retain(|layer| layer.id != from);
+        if self.active_layer == from {
+            self.active_layer = into;
+        }
+        true
+    }
+
+    /// Puts the elements at `indices` on top of `layer`.
+    pub fn move_to_layer(&mut self, indices: &[usize], layer: LayerId) -> bool {
+        if self.layer(layer).is_none() {
+            return false;
+        }
+        self.sync_metas();
+        let indices: Vec<usize> = indices
+            .iter()
+            .copied()
+            .filter(|i| *i < self.metas.len())
+            .collect();
+        for index in indices.iter() {
+            self.metas[*index].layer = layer;
+        }
+        self.sort_by_layer(&indices);
+        self.flush();
+        true
+    }
+
+    /// Puts the elements at `indices` into a new `Group`, where the topmost
+    /// of them was. Returns the group's index, `None` for less than two
+    /// elements.
+    pub fn group(&mut self, indices: &[usize]) -> Option<usize> {
+        self.sync_metas();
+        let mut indices: Vec<usize> = indices
+            .iter()
+            .copied()
+            .filter(|i| *i < self.elems.len())
+            .collect();
+        indices.sort();
+        indices.dedup();
+        if indices.len() < 2 {
+            return None;
+        }
+        let mut elems = vec![];
+        for index in indices.iter().rev() {
+            elems.push(self.remove_elem(*index));
+        }
+        elems.reverse();
+        let index = indices[indices.len() - 1] + 1 - indices.len();
+        self.insert_elem(index, Box::new(Group { elems }));
+        self.selects.clear();
+        self.select_box = None;
+        self.hover_index = -1;
+        self.flush();
+        Some(index)
+    }
+
+    /// Replaces the group at `index` with its children. Returns false if it
+    /// isn't a group.
+    pub fn ungroup(&mut self, index: usize) -> bool {
+        let elems = match self.group_children(index) {
+            Some(elems) => elems,
+            None => return false,
+        };
+        self.remove_elem(index);
+        for (i, elem) in elems.into_iter().enumerate() {
+            self.insert_elem(index + i, elem);
+        }
+        self.selects.clear();
+        self.select_box = None;
+        self.hover_index = -1;
+        self.flush();
+        true
+    }
+
+    fn group_children(&self, index: usize) -> Option<Vec<Box<dyn IElem>>> {
+        let elem = self.elems.get(index)?;
+        if elem.elem_type() != "group" {
+            return None;
+        }
+        Some(self.parse_elems(&elem.export()))
+    }
+
+    /// Opens the group at `index` so its children can be edited one by
+    /// one. Until `leave_group`, only they can be hovered.
+    pub fn enter_group(&mut self, index: usize) -> bool {
+        let elems = match self.group_children(index) {
+            Some(elems) => elems,
+            None => return false,
+        };
+        let id = self.elem_id(index).unwrap();
+        self.remove_elem(index);
+        let members = elems
+            .into_iter()
+            .enumerate()
+            .map(|(i, elem)| self.insert_elem(index + i, elem))
+            .collect();
+        self.entered.push(EnteredGroup { id, members });
+        self.selects.clear();
+        self.select_box = None;
+        self.hover_index = -1;
+        self.flush();
+        true
+    }
+
+    /// Closes the innermost entered group again.
+    pub fn leave_group(&mut self) -> bool {
+        let entered = match self.entered.pop() {
+            Some(entered) => entered,
+            None => return false,
+        };
+        let positions: Vec<usize> = (0..self.elems.len())
+            .filter(|i| entered.members.contains(&self.metas[*i].id))
+            .collect();
+        if !positions.is_empty() {
+            let layer = self.metas[positions[0]].layer;
+            let mut elems = vec![];
+            for index in positions.iter().rev() {
+                elems.push(self.remove_elem(*index));
+            }
+            elems.reverse();
+            self.elems.insert(positions[0], Box::new(Group { elems }));
+            self.metas.insert(
+                positions[0],
+                ElemMeta {
+                    id: entered.id,
+                    layer,
+                },
+            );
+        }
+        self.selects.clear();
+        self.select_box = None;
+        self.hover_index = -1;
+        self.flush();
+        true
+    }
+
+    pub fn elem_id(&self, index: usize) -> Option<ElemId> {
+        self.metas.get(index).map(|meta| meta.id)
+    }
+
+    pub fn elem_index(&self, id: ElemId) -> Option<usize> {
+        self.metas.iter().position(|meta| meta.id == id)
+    }
+
+    /// Catches `metas` up with elements added to or removed from `elems`
+    /// without going through the methods above. Elements pushed to `elems`
+    /// belong to the top layer.
+    pub fn sync_metas(&mut self) {
+        self.metas.truncate(self.elems.len());
+        while self.metas.len() < self.elems.len() {
+            self.metas.push(ElemMeta {
+                id: self.next_elem_id,
+                layer: self.layers.last().unwrap().id,
+            });
+            self.next_elem_id += 1;
+        }
+    }
+
+    /// Reports what changed since the last call to the subscribers of
+    /// `Drawpanel::subscribe`.
+    pub fn emit_changes(&mut self) {
+        if !self.changes.is_active() || self.tool.is_none() {
+            return;
+        }
+        self.sync_metas();
+        let mut changes = std::mem::take(&mut self.changes);
+        changes.check(self);
+        self.changes = changes;
+    }
+
     pub fn set_region(&mut self, region: geo::Rect) {
         self.raw_lt_coord = region.min();
-        self.lt_coord = region.min();
+        self.viewport.lt_coord = region.min();
         self.width = region.width();
         self.height = region.height();
-        self.flush();
+        self.flush_view();
+    }
+
+    /// Tells the binder when the cursor has changed.
+    pub(crate) fn update_cursor(&mut self) {
+        let cursor = self.current_cursor();
+        if let (Some(cursor), Some(hook_event)) = (cursor, self.hook_event.as_mut()) {
+            if cursor != self.cursor {
+                self.cursor = cursor;
+                hook_event.set_cursor(cursor);
+            }
+        }
     }
 
     pub fn flush(&mut self) {
-        let hook_event = self.hook_event.as_mut().unwrap();
-        hook_event.flush();
+        self.route_connectors();
+        self.flush_view();
+    }
+
+    /// `flush` for changes that leave the elements where they are, e.g.
+    /// panning, zooming or hovering. The connectors aren't routed again.
+    pub fn flush_view(&mut self) {
+        self.update_cursor();
+        self.hook_event.as_mut().unwrap().flush();
+        if !self.changes.held {
+            self.emit_changes();
+        }
     }
 
     // pub fn update(&mut self, ctx: Box<dyn Any>) {
@@ -471,3 +1606,55 @@ impl Panel {
     //     draw.update(ctx);
     // }
 }
+
+/// Fills `page` (panel coordinates) with `color`, seen through `scale` and
+/// `lt_coord`.
+fn draw_page(draw: &Box<dyn IDraw>, page: geo::Rect, lt_coord: Coordinate, scale: f64, color: u32) {
+    draw.draw_rect(DrawRectOpts {
+        left_top_coord: lt_coord + page.min() * scale,
+        width: page.width() * scale,
+        height: page.height() * scale,
+        line_size: 0.,
+        line_color: color,
+        fill_color: Some(color),
+        line_style: LineStyle::Solid,
+    });
+}
+
+pub(crate) fn union_rect(a: geo::Rect, b: geo::Rect) -> geo::Rect {
+    geo::Rect::new(
+        coord! { x: a.min().x.min(b.min().x), y: a.min().y.min(b.min().y) },
+        coord! { x: a.max().x.max(b.max().x), y: a.max().y.max(b.max().y) },
+    )
+}
+
+/// Builds one element from its `type(content)` form with the elements
+/// registered in `registry`.
+pub fn parse_elem_with(registry: &ElemRegistry, elem_seria: &str) -> Box<dyn IElem> {
+    let (elem_type, elem_seria) = split_elem(elem_seria).unwrap();
+    let elem = registry.get(elem_type).unwrap();
+    elem.import_with(elem_seria, registry)
+}
+
+/// `parse_elem_with` that fails instead of panicking, see `Elem::try_import`.
+pub fn try_parse_elem_with(
+    registry: &ElemRegistry,
+    elem_seria: &str,
+) -> Result<Box<dyn IElem>, PasteError> {
+    let (elem_type, elem_seria) = split_elem(elem_seria)?;
+    let elem = registry
+        .get(elem_type)
+        .ok_or_else(|| PasteError::UnknownElem(elem_type.to_string()))?;
+    elem.try_import(elem_seria, registry)
+}
+
+/// The type and the content of an element in its `type(content)` form.
+fn split_elem(elem_seria: &str) -> Result<(&str, &str), PasteError> {
+    // 解析出类型和元素
+    match (elem_seria.find('('), elem_seria.rfind(')')) {
+        (Some(start), Some(end)) if start < end => {
+            Ok((&elem_seria[..start], &elem_seria[start + 1..end]))
+        }
+        _ => Err(PasteError::Malformed(elem_seria.to_string())),
+    }
+}
@@ -0,0 +1,463 @@
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    rc::{Rc, Weak},
+};
+
+use geo::{coord, Coordinate};
+
+use crate::{
+    binder::{
+        Binder, Cursor, Draw, DrawCircleOpts, DrawImageOpts, DrawLineOpts, DrawPathOpts,
+        DrawRectOpts, DrawTextOpts, EventMouseButton, EventType, EventZoom, HookEvent, IDraw,
+        IHookEvent,
+    },
+    drawpanel::Drawpanel,
+    elem::rect::LineStyle,
+    panel::Panel,
+};
+
+/// One call made on a `Draw`, with everything it was given.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCall {
+    Line {
+        from_coord: Coordinate,
+        end_coord: Coordinate,
+        line_size: f64,
+        line_color: u32,
+    },
+    Rect {
+        left_top_coord: Coordinate,
+        width: f64,
+        height: f64,
+        line_size: f64,
+        line_color: u32,
+        fill_color: Option<u32>,
+        line_style: LineStyle,
+    },
+    Circle {
+        center_coord: Coordinate,
+        r: f64,
+        line_size: f64,
+        line_color: u32,
+        fill_color: u32,
+    },
+    Text {
+        left_top_coord: Coordinate,
+        width: f64,
+        height: f64,
+        content: String,
+        font_size: f64,
+        font_space: u32,
+        font_color: u32,
+    },
+    Path {
+        coords: Vec<Coordinate>,
+        line_size: f64,
+        line_color: u32,
+        fill_color: Option<u32>,
+        closed: bool,
+    },
+    Image {
+        left_top_coord: Coordinate,
+        width: f64,
+        height: f64,
+        data: String,
+    },
+    Opacity {
+        opacity: f64,
+    },
+}
+
+/// One line per call, with numbers rounded to two decimals so snapshots
+/// don't break on float noise.
+impl Display for DrawCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawCall::Line {
+                from_coord,
+                end_coord,
+                line_size,
+                line_color,
+            } => write!(
+                f,
+                "line {} -> {} size {} color {}",
+                point(from_coord),
+                point(end_coord),
+                num(*line_size),
+                color(*line_color)
+            ),
+            DrawCall::Rect {
+                left_top_coord,
+                width,
+                height,
+                line_size,
+                line_color,
+                fill_color,
+                line_style,
+            } => write!(
+                f,
+                "rect {} {}x{} size {} color {} fill {} {:?}",
+                point(left_top_coord),
+                num(*width),
+                num(*height),
+                num(*line_size),
+                color(*line_color),
+                fill_color.map_or("none".to_string(), color),
+                line_style
+            ),
+            DrawCall::Circle {
+                center_coord,
+                r,
+                line_size,
+                line_color,
+                fill_color,
+            } => write!(
+                f,
+                "circle {} r {} size {} color {} fill {}",
+                point(center_coord),
+                num(*r),
+                num(*line_size),
+                color(*line_color),
+                color(*fill_color)
+            ),
+            DrawCall::Text {
+                left_top_coord,
+                width,
+                height,
+                content,
+                font_size,
+                font_space,
+                font_color,
+            } => write!(
+                f,
+                "text {} {}x{} {:?} font {} space {} color {}",
+                point(left_top_coord),
+                num(*width),
+                num(*height),
+                content,
+                num(*font_size),
+                font_space,
+                color(*font_color)
+            ),
+            DrawCall::Path {
+                coords,
+                line_size,
+                line_color,
+                fill_color,
+                closed,
+            } => write!(
+                f,
+                "path [{}]{} size {} color {} fill {}",
+                coords.iter().map(point).collect::<Vec<_>>().join(" "),
+                if *closed { " closed" } else { "" },
+                num(*line_size),
+                color(*line_color),
+                fill_color.map_or("none".to_string(), color)
+            ),
+            DrawCall::Image {
+                left_top_coord,
+                width,
+                height,
+                data,
+            } => write!(
+                f,
+                "image {} {}x{} {} bytes",
+                point(left_top_coord),
+                num(*width),
+                num(*height),
+                data.len()
+            ),
+            DrawCall::Opacity { opacity } => write!(f, "opacity {}", num(*opacity)),
+        }
+    }
+}
+
+fn num(value: f64) -> String {
+    let value = format!("{:.2}", value);
+    match value.as_str() {
+        "-0.00" => "0.00".to_string(),
+        _ => value,
+    }
+}
+
+fn point(coord: &Coordinate) -> String {
+    format!("({}, {})", num(coord.x), num(coord.y))
+}
+
+fn color(color: u32) -> String {
+    format!("#{:06x}", color)
+}
+
+/// Everything drawn through a `RecordingBinder`, one frame per
+/// `draw_begin` .. `draw_end`. Clones share the same frames, so keep one
+/// before the binder is moved into `Drawpanel::new`.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    frames: Rc<RefCell<Vec<Vec<DrawCall>>>>,
+    flushes: Rc<RefCell<usize>>,
+    cursors: Rc<RefCell<Vec<Cursor>>>,
+}
+
+impl Recorder {
+    pub fn frames(&self) -> Vec<Vec<DrawCall>> {
+        self.frames.borrow().clone()
+    }
+
+    pub fn last_frame(&self) -> Vec<DrawCall> {
+        self.frames.borrow().last().cloned().unwrap_or_default()
+    }
+
+    /// How often the panel asked the binder to redraw.
+    pub fn flushes(&self) -> usize {
+        *self.flushes.borrow()
+    }
+
+    /// Every cursor the panel asked for, in order.
+    pub fn cursors(&self) -> Vec<Cursor> {
+        self.cursors.borrow().clone()
+    }
+
+    pub fn clear(&self) {
+        self.frames.borrow_mut().clear();
+        *self.flushes.borrow_mut() = 0;
+        self.cursors.borrow_mut().clear();
+    }
+
+    /// Draws `drawpanel` once and returns what was drawn.
+    pub fn capture(&self, drawpanel: &Drawpanel) -> Vec<DrawCall> {
+        let panel = drawpanel.panel().upgrade().unwrap();
+        panel.borrow().trigger_draw();
+        self.last_frame()
+    }
+
+    /// `capture` as text, one call per line.
+    pub fn snapshot(&self, drawpanel: &Drawpanel) -> String {
+        self.capture(drawpanel)
+            .iter()
+            .map(|call| call.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// A binder without any output that records every draw call. Meant for
+/// tests, together with `EventScript`.
+#[derive(Debug, Clone)]
+pub struct RecordingBinder {
+    width: f64,
+    height: f64,
+    recorder: Recorder,
+}
+
+impl RecordingBinder {
+    pub fn new(width: f64, height: f64) -> Self {
+        RecordingBinder {
+            width,
+            height,
+            recorder: Recorder::default(),
+        }
+    }
+
+    pub fn recorder(&self) -> Recorder {
+        self.recorder.clone()
+    }
+}
+
+impl Binder for RecordingBinder {
+    fn init(&mut self, _panel: Weak<RefCell<Panel>>) {}
+
+    fn draw(&self, _panel: Weak<RefCell<Panel>>) -> Box<dyn IDraw> {
+        Box::new(RecordingDraw {
+            recorder: self.recorder.clone(),
+        })
+    }
+
+    fn hook_event(&self) -> Box<dyn IHookEvent> {
+        Box::new(RecordingHookEvent {
+            recorder: self.recorder.clone(),
+        })
+    }
+
+    fn region(&self) -> geo::Rect<f64> {
+        geo::Rect::new(
+            coord!(x: 0.0, y: 0.0),
+            coord!(x: self.width, y: self.height),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct RecordingDraw {
+    recorder: Recorder,
+}
+
+impl RecordingDraw {
+    fn record(&self, call: DrawCall) {
+        let mut frames = self.recorder.frames.borrow_mut();
+        if frames.is_empty() {
+            frames.push(vec![]);
+        }
+        frames.last_mut().unwrap().push(call);
+    }
+}
+
+impl IDraw for RecordingDraw {}
+
+impl Draw for RecordingDraw {
+    fn draw_begin(&self, _ctx: Box<dyn std::any::Any>) {
+        self.recorder.frames.borrow_mut().push(vec![]);
+    }
+
+    fn draw_line(&self, opts: DrawLineOpts) {
+        self.record(DrawCall::Line {
+            from_coord: opts.from_coord,
+            end_coord: opts.end_coord,
+            line_size: opts.line_size,
+            line_color: opts.line_color,
+        });
+    }
+
+    fn draw_rect(&self, opts: DrawRectOpts) {
+        self.record(DrawCall::Rect {
+            left_top_coord: opts.left_top_coord,
+            width: opts.width,
+            height: opts.height,
+            line_size: opts.line_size,
+            line_color: opts.line_color,
+            fill_color: opts.fill_color,
+            line_style: opts.line_style,
+        });
+    }
+
+    fn draw_circle(&self, opts: DrawCircleOpts) {
+        self.record(DrawCall::Circle {
+            center_coord: opts.center_coord,
+            r: opts.r,
+            line_size: opts.line_size,
+            line_color: opts.line_color,
+            fill_color: opts.fill_color,
+        });
+    }
+
+    fn draw_text(&self, opts: DrawTextOpts) {
+        self.record(DrawCall::Text {
+            left_top_coord: opts.left_top_coord,
+            width: opts.width,
+            height: opts.height,
+            content: opts.content.to_string(),
+            font_size: opts.font_size,
+            font_space: opts.font_space,
+            font_color: opts.font_color,
+        });
+    }
+
+    fn draw_path(&self, opts: DrawPathOpts) {
+        self.record(DrawCall::Path {
+            coords: opts.coords.to_vec(),
+            line_size: opts.line_size,
+            line_color: opts.line_color,
+            fill_color: opts.fill_color,
+            closed: opts.closed,
+        });
+    }
+
+    fn draw_image(&self, opts: DrawImageOpts) {
+        self.record(DrawCall::Image {
+            left_top_coord: opts.left_top_coord,
+            width: opts.width,
+            height: opts.height,
+            data: opts.data.to_string(),
+        });
+    }
+
+    fn set_opacity(&self, opacity: f64) {
+        self.record(DrawCall::Opacity { opacity });
+    }
+}
+
+#[derive(Debug)]
+struct RecordingHookEvent {
+    recorder: Recorder,
+}
+
+impl IHookEvent for RecordingHookEvent {}
+
+impl HookEvent for RecordingHookEvent {
+    fn flush(&mut self) {
+        *self.recorder.flushes.borrow_mut() += 1;
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.recorder.cursors.borrow_mut().push(cursor);
+    }
+}
+
+/// A scripted sequence of input events, as a binder would send them to
+/// `Panel::trigger_event`. Coordinates are in binder (screen) space.
+#[derive(Debug, Clone, Default)]
+pub struct EventScript {
+    events: Vec<(EventType, Coordinate)>,
+}
+
+impl EventScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn event(mut self, event_type: EventType, x: f64, y: f64) -> Self {
+        self.events.push((event_type, coord! { x: x, y: y }));
+        self
+    }
+
+    pub fn move_to(self, x: f64, y: f64) -> Self {
+        self.event(EventType::Move(EventMouseButton::None), x, y)
+    }
+
+    pub fn push(self, x: f64, y: f64) -> Self {
+        self.event(EventType::Push(EventMouseButton::Left), x, y)
+    }
+
+    pub fn drag(self, x: f64, y: f64) -> Self {
+        self.event(EventType::Drag(EventMouseButton::Left), x, y)
+    }
+
+    pub fn release(self, x: f64, y: f64) -> Self {
+        self.event(EventType::Released(EventMouseButton::Left), x, y)
+    }
+
+    pub fn dblclick(self, x: f64, y: f64) -> Self {
+        self.event(EventType::Dblclick, x, y)
+    }
+
+    pub fn zoom(self, zoom: EventZoom, x: f64, y: f64) -> Self {
+        self.event(EventType::Zoom(zoom), x, y)
+    }
+
+    /// Hover, press and release at the same point.
+    pub fn click(self, x: f64, y: f64) -> Self {
+        self.move_to(x, y).push(x, y).release(x, y)
+    }
+
+    /// Hover at `from`, press, drag to `to` in `steps` moves and release.
+    pub fn drag_path(mut self, from: (f64, f64), to: (f64, f64), steps: usize) -> Self {
+        self = self.move_to(from.0, from.1).push(from.0, from.1);
+        let steps = steps.max(1);
+        for i in 1..=steps {
+            let t = i as f64 / steps as f64;
+            self = self.drag(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        }
+        self.release(to.0, to.1)
+    }
+
+    pub fn events(&self) -> &[(EventType, Coordinate)] {
+        &self.events
+    }
+
+    pub fn run(&self, drawpanel: &Drawpanel) {
+        let panel = drawpanel.panel().upgrade().unwrap();
+        for (event_type, coord) in self.events.iter() {
+            panel.borrow_mut().trigger_event(*event_type, *coord);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use geo::coord;
+
+use crate::{
+    binder::{DrawLineOpts, DrawRectOpts, DrawTextOpts, IDraw},
+    elem::rect::LineStyle,
+    panel::Panel,
+    units::format_number,
+};
+
+/// Rulers along the top and left edges of the binder's region, in the
+/// document's unit, see `Panel::units`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rulers {
+    pub visible: bool,
+    /// Thickness in binder coordinates.
+    pub size: f64,
+    pub background: u32,
+    pub color: u32,
+    /// Least space between labelled ticks, in binder coordinates.
+    pub spacing: f64,
+    /// Color of the cursor's position on the rulers.
+    pub marker_color: u32,
+}
+
+impl Default for Rulers {
+    fn default() -> Self {
+        Rulers {
+            visible: false,
+            size: 20.,
+            background: 0xf0f0f0,
+            color: 0x666666,
+            spacing: 50.,
+            marker_color: 0x1e90ff,
+        }
+    }
+}
+
+/// Ticks between two labelled ones.
+const MINOR_TICKS: i64 = 5;
+
+pub(crate) fn draw_rulers(panel: &Panel, draw: &Box<dyn IDraw>) {
+    let rulers = &panel.rulers;
+    let region = panel.region();
+    let (left, top) = (region.min().x, region.min().y);
+    let size = rulers.size;
+    for (width, height) in [(region.width(), size), (size, region.height())] {
+        draw.draw_rect(DrawRectOpts {
+            left_top_coord: region.min(),
+            width,
+            height,
+            line_size: 1.,
+            line_color: rulers.color,
+            fill_color: Some(rulers.background),
+            line_style: LineStyle::Solid,
+        });
+    }
+
+    let scale = panel.viewport.scale;
+    let step = panel.units.nice_step(rulers.spacing / scale);
+    let step_len = panel.units.from_unit(step);
+    let visible = panel.visible_rect();
+    let font_size = size / 2.;
+    for horizontal in [true, false] {
+        let (min, max, start, end) = if horizontal {
+            (visible.min().x, visible.max().x, left, region.max().x)
+        } else {
+            (visible.min().y, visible.max().y, top, region.max().y)
+        };
+        let first = (min / step_len).floor() as i64;
+        let last = (max / step_len).ceil() as i64;
+        for i in first * MINOR_TICKS..=last * MINOR_TICKS {
+            let world = i as f64 * step_len / MINOR_TICKS as f64;
+            let screen = panel.absolute_coord(coord! { x: world, y: world });
+            let at = if horizontal { screen.x } else { screen.y };
+            // The corner belongs to neither ruler.
+            if at < start + size || at > end {
+                continue;
+            }
+            let major = i % MINOR_TICKS == 0;
+            let length = if major { size } else { size / 3. };
+            let (from_coord, end_coord) = if horizontal {
+                (
+                    coord! { x: at, y: top + size - length },
+                    coord! { x: at, y: top + size },
+                )
+            } else {
+                (
+                    coord! { x: left + size - length, y: at },
+                    coord! { x: left + size, y: at },
+                )
+            };
+            draw.draw_line(DrawLineOpts {
+                from_coord,
+                end_coord,
+                line_size: 1.,
+                line_color: rulers.color,
+            });
+            if major {
+                let label = format_number(i as f64 / MINOR_TICKS as f64 * step);
+                draw.draw_text(DrawTextOpts {
+                    left_top_coord: if horizontal {
+                        coord! { x: at + 2., y: top }
+                    } else {
+                        coord! { x: left + 1., y: at + 2. }
+                    },
+                    width: if horizontal {
+                        step_len * scale - 4.
+                    } else {
+                        size - 2.
+                    },
+                    height: font_size * 1.2,
+                    content: &label,
+                    font_size,
+                    font_space: 0,
+                    font_color: rulers.color,
+                });
+            }
+        }
+    }
+
+    let cursor = panel.mouse_coord;
+    if cursor.x > left + size
+        && cursor.y > top + size
+        && cursor.x < region.max().x
+        && cursor.y < region.max().y
+    {
+        draw.draw_line(DrawLineOpts {
+            from_coord: coord! { x: cursor.x, y: top },
+            end_coord: coord! { x: cursor.x, y: top + size },
+            line_size: 1.,
+            line_color: rulers.marker_color,
+        });
+        draw.draw_line(DrawLineOpts {
+            from_coord: coord! { x: left, y: cursor.y },
+            end_coord: coord! { x: left + size, y: cursor.y },
+            line_size: 1.,
+            line_color: rulers.marker_color,
+        });
+    }
+}
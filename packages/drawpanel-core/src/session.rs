@@ -0,0 +1,267 @@
+use std::time::{Duration, Instant};
+
+use geo::Coordinate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    binder::EventType,
+    drawpanel::Drawpanel,
+    drawpanel::Mode,
+    panel::Panel,
+    serde_helper::CoordinateRef,
+    tool::{CreateTool, DeleteTool, MoveTool, SelectTool, TextEditTool, Tool},
+};
+
+/// Everything a user did on a panel, enough to rebuild the document on
+/// another `Drawpanel`. Serialize it with serde to attach it to a bug report.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Session {
+    /// `Panel::export` when the recording started.
+    pub document: String,
+    /// The view when the recording started, so screen coordinates map to the
+    /// same panel coordinates on replay.
+    #[serde(with = "CoordinateRef")]
+    pub raw_lt_coord: Coordinate,
+    #[serde(with = "CoordinateRef")]
+    pub lt_coord: Coordinate,
+    pub scale: f64,
+    pub entries: Vec<SessionEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionEntry {
+    /// Milliseconds since the recording started.
+    pub time: u64,
+    pub action: SessionAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SessionAction {
+    /// An event passed to `Panel::trigger_event`, in screen coordinates.
+    Event {
+        event_type: EventType,
+        #[serde(with = "CoordinateRef")]
+        coord: Coordinate,
+    },
+    /// A call to `Drawpanel::set_mode` or `Drawpanel::set_tool`.
+    Mode(SessionMode),
+    /// The content an element got from the binder's input when its edit
+    /// state ended.
+    Content { index: usize, content: String },
+    /// A `Panel::tick` that reported a gesture, e.g. a long press.
+    Tick,
+}
+
+/// `Mode` with the element to create in its `type(content)` form.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SessionMode {
+    EditMoving,
+    Creating(Option<String>),
+    /// A `CreateTool::once`.
+    CreatingOnce(String),
+    EditResizing(u8),
+    Deleting,
+    EditState,
+    Select,
+    /// A tool that isn't built in, by its `Tool::name`.
+    Tool(String),
+}
+
+impl SessionMode {
+    pub fn from_mode(mode: &Mode) -> Self {
+        match mode {
+            Mode::EditMoving => SessionMode::EditMoving,
+            Mode::Creating(elem) => SessionMode::Creating(
+                elem.as_ref()
+                    .map(|elem| format!("{}({})", elem.elem_type(), elem.export())),
+            ),
+            Mode::EditResizing(vertex) => SessionMode::EditResizing(*vertex),
+            Mode::Deleting => SessionMode::Deleting,
+            Mode::EditState => SessionMode::EditState,
+            Mode::Select => SessionMode::Select,
+        }
+    }
+
+    /// `None` for tools that aren't built in.
+    pub fn to_tool(&self, panel: &Panel) -> Option<Box<dyn Tool>> {
+        let tool: Box<dyn Tool> = match self {
+            SessionMode::EditMoving => Box::new(MoveTool::default()),
+            SessionMode::Creating(Some(elem)) => Box::new(CreateTool::new(panel.parse_elem(elem))),
+            SessionMode::Creating(None) => Box::new(MoveTool::default()),
+            SessionMode::CreatingOnce(elem) => Box::new(CreateTool::once(panel.parse_elem(elem))),
+            SessionMode::EditResizing(vertex) => Box::new(MoveTool::resizing(*vertex)),
+            SessionMode::Deleting => Box::new(DeleteTool),
+            SessionMode::EditState => Box::new(TextEditTool::new(None)),
+            SessionMode::Select => Box::new(SelectTool),
+            SessionMode::Tool(_) => return None,
+        };
+        Some(tool)
+    }
+}
+
+/// A session being recorded, see `Drawpanel::start_recording`.
+#[derive(Debug)]
+pub struct SessionRecording {
+    start: Instant,
+    session: Session,
+}
+
+impl SessionRecording {
+    pub fn new(panel: &Panel) -> Self {
+        SessionRecording {
+            start: Instant::now(),
+            session: Session {
+                document: panel.export(),
+                raw_lt_coord: panel.raw_lt_coord,
+                lt_coord: panel.viewport.lt_coord,
+                scale: panel.viewport.scale,
+                entries: vec![],
+            },
+        }
+    }
+
+    pub fn record(&mut self, action: SessionAction) {
+        self.session.entries.push(SessionEntry {
+            time: self.start.elapsed().as_millis() as u64,
+            action,
+        });
+    }
+
+    pub fn finish(self) -> Session {
+        self.session
+    }
+}
+
+/// Replays a `Session` onto a `Drawpanel`, all at once, in real time or
+/// one entry at a time.
+#[derive(Debug, Clone)]
+pub struct SessionPlayer {
+    session: Session,
+    index: usize,
+    speed: f64,
+    stop_at: Option<usize>,
+    /// Stands for the start of the recording, gestures are timed from it.
+    origin: Instant,
+}
+
+impl SessionPlayer {
+    pub fn new(session: Session) -> Self {
+        SessionPlayer {
+            session,
+            index: 0,
+            speed: 1.,
+            stop_at: None,
+            origin: Instant::now(),
+        }
+    }
+
+    /// 2 plays twice as fast as recorded, 0 doesn't wait at all.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Stops before the entry at `index`, e.g. right before a crash.
+    pub fn stop_at(mut self, index: usize) -> Self {
+        self.stop_at = Some(index);
+        self
+    }
+
+    /// Index of the next entry.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.index
+            >= self
+                .session
+                .entries
+                .len()
+                .min(self.stop_at.unwrap_or(usize::MAX))
+    }
+
+    /// Restores the document and view the session started with.
+    pub fn reset(&mut self, drawpanel: &mut Drawpanel) {
+        self.index = 0;
+        self.origin = Instant::now();
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let mut panel = panel.borrow_mut();
+        panel.raw_lt_coord = self.session.raw_lt_coord;
+        panel.viewport.lt_coord = self.session.lt_coord;
+        panel.viewport.scale = self.session.scale;
+        panel.set_tool(Box::new(MoveTool::default()));
+        panel.hover_index = -1;
+        panel.drag_vertex = -1;
+        panel.selects.clear();
+        panel.select_box = None;
+        panel.import(&self.session.document);
+    }
+
+    /// How long to wait before the next entry at the current speed. Lets a
+    /// GUI drive `step` from a timer instead of blocking in `play`.
+    pub fn delay(&self) -> Duration {
+        if self.speed <= 0. || self.index == 0 || self.is_finished() {
+            return Duration::ZERO;
+        }
+        let entries = &self.session.entries;
+        let delta = entries[self.index].time - entries[self.index - 1].time;
+        Duration::from_secs_f64(delta as f64 / 1000. / self.speed)
+    }
+
+    /// Applies the next entry, after a `reset` for the first one. Returns
+    /// false when there was nothing left.
+    pub fn step(&mut self, drawpanel: &mut Drawpanel) -> bool {
+        if self.index == 0 {
+            self.reset(drawpanel);
+        }
+        if self.is_finished() {
+            return false;
+        }
+        let panel = drawpanel.panel().upgrade().unwrap();
+        let entry = self.session.entries[self.index].clone();
+        self.index += 1;
+        panel.borrow_mut().clock = Some(self.origin + Duration::from_millis(entry.time));
+        match entry.action {
+            SessionAction::Event { event_type, coord } => {
+                panel.borrow_mut().trigger_event(event_type, coord);
+            }
+            SessionAction::Mode(mode) => {
+                let tool = mode.to_tool(&panel.borrow());
+                // Other tools can't be rebuilt, the current one stays.
+                if let Some(tool) = tool {
+                    drawpanel.set_tool(tool);
+                }
+            }
+            SessionAction::Tick => {
+                panel.borrow_mut().poll_gestures();
+            }
+            SessionAction::Content { index, content } => {
+                let mut panel = panel.borrow_mut();
+                if let Some(elem) = panel.elems.get_mut(index) {
+                    elem.set_content(&content);
+                }
+                panel.flush();
+            }
+        }
+        // Back to the wall clock for whatever the user does next.
+        if self.is_finished() {
+            panel.borrow_mut().clock = None;
+        }
+        true
+    }
+
+    /// Plays the remaining entries, sleeping between them according to the
+    /// speed.
+    pub fn play(&mut self, drawpanel: &mut Drawpanel) {
+        loop {
+            let delay = self.delay();
+            if !delay.is_zero() {
+                std::thread::sleep(delay);
+            }
+            if !self.step(drawpanel) {
+                break;
+            }
+        }
+    }
+}
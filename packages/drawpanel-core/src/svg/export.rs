@@ -0,0 +1,334 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Write,
+};
+
+use geo::{coord, Intersects};
+
+use crate::{
+    binder::{
+        Draw, DrawCircleOpts, DrawImageOpts, DrawLineOpts, DrawPathOpts, DrawRectOpts,
+        DrawTextOpts, IDraw,
+    },
+    elem::rect::LineStyle,
+    panel::{union_rect, Panel},
+};
+
+/// Which part of the panel ends up in the SVG.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SvgScope {
+    /// The current page and every element on it.
+    Document,
+    /// Only the selected elements, cropped to their bounds.
+    Selection,
+    /// Everything currently visible in the binder's region.
+    Viewport,
+}
+
+#[derive(Debug, Clone)]
+pub struct SvgExportOpts {
+    pub scope: SvgScope,
+    /// Embeds `Panel::export` in `<metadata>` so the file can be imported
+    /// again without losing anything. Selection and viewport exports embed
+    /// only the elements they show.
+    pub embed_document: bool,
+}
+
+impl Default for SvgExportOpts {
+    fn default() -> Self {
+        SvgExportOpts {
+            scope: SvgScope::Document,
+            embed_document: false,
+        }
+    }
+}
+
+pub const METADATA_ID: &str = "drawpanel-document";
+
+/// Class of the page backdrop, which `import_svg` leaves out.
+pub const PAGE_CLASS: &str = "drawpanel-page";
+
+pub fn export_svg(panel: &Panel, opts: &SvgExportOpts) -> String {
+    let all: Vec<usize> = (0..panel.elems.len()).collect();
+    let empty = geo::Rect::new(coord! { x: 0., y: 0. }, coord! { x: 0., y: 0. });
+    let (view_box, indices, with_page) = match opts.scope {
+        SvgScope::Document => {
+            let view_box = match (panel.page_rect(), panel.bounds(&all)) {
+                (Some(page), Some(bounds)) => union_rect(page, bounds),
+                (Some(rect), None) | (None, Some(rect)) => rect,
+                (None, None) => empty,
+            };
+            (view_box, all, true)
+        }
+        SvgScope::Selection => {
+            let mut selects: Vec<usize> = panel.selects.iter().map(|i| *i as usize).collect();
+            selects.sort();
+            let view_box = panel.bounds(&selects).unwrap_or(empty);
+            (view_box, selects, false)
+        }
+        SvgScope::Viewport => {
+            let view_box = panel.visible_rect();
+            let shown = (0..panel.elems.len())
+                .filter(|i| {
+                    panel.elems[*i]
+                        .bounding_rect()
+                        .map_or(false, |bounds| bounds.intersects(&view_box))
+                })
+                .collect();
+            (view_box, shown, true)
+        }
+    };
+
+    let metadata = match (opts.embed_document, opts.scope) {
+        (false, _) => None,
+        (true, SvgScope::Document) => Some(panel.export()),
+        (true, _) => Some(panel.export_elems(&indices)),
+    };
+    let svg_draw = SvgDraw::new(view_box, metadata);
+    svg_draw.page.set(with_page && panel.page_rect().is_some());
+    let draw: Box<dyn IDraw> = Box::new(svg_draw);
+    let origin = coord! { x: 0., y: 0. };
+    let svg = if with_page {
+        panel.render(&draw, Box::new(()), origin, 1.)
+    } else {
+        panel.render_elems(&draw, Box::new(()), origin, 1., &indices)
+    };
+    *svg.downcast::<String>().unwrap()
+}
+
+/// Writes every call as an SVG element. Coordinates are used as they are, so
+/// draw through a view with scale 1 to get panel units.
+#[derive(Debug)]
+pub struct SvgDraw {
+    view_box: geo::Rect,
+    metadata: Option<String>,
+    body: RefCell<String>,
+    /// Below 1 while a `<g opacity>` is open.
+    opacity: Cell<f64>,
+    /// Set while the next rect is the page, which `Panel::render` draws
+    /// first.
+    page: Cell<bool>,
+}
+
+impl SvgDraw {
+    pub fn new(view_box: geo::Rect, metadata: Option<String>) -> Self {
+        SvgDraw {
+            view_box,
+            metadata,
+            body: RefCell::new(String::new()),
+            opacity: Cell::new(1.),
+            page: Cell::new(false),
+        }
+    }
+}
+
+impl IDraw for SvgDraw {}
+
+impl Draw for SvgDraw {
+    fn draw_begin(&self, _ctx: Box<dyn std::any::Any>) {
+        self.body.borrow_mut().clear();
+        self.opacity.set(1.);
+    }
+
+    fn set_opacity(&self, opacity: f64) {
+        let mut body = self.body.borrow_mut();
+        if self.opacity.get() < 1. {
+            body.push_str("  </g>\n");
+        }
+        if opacity < 1. {
+            writeln!(body, r#"  <g opacity="{}">"#, opacity).unwrap();
+        }
+        self.opacity.set(opacity);
+    }
+
+    fn draw_line(&self, opts: DrawLineOpts) {
+        let mut body = self.body.borrow_mut();
+        writeln!(
+            body,
+            r#"  <line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-linecap="round"/>"#,
+            opts.from_coord.x,
+            opts.from_coord.y,
+            opts.end_coord.x,
+            opts.end_coord.y,
+            color(opts.line_color),
+            opts.line_size
+        )
+        .unwrap();
+    }
+
+    fn draw_rect(&self, opts: DrawRectOpts) {
+        let mut body = self.body.borrow_mut();
+        let class = if self.page.replace(false) {
+            format!(r#" class="{}""#, PAGE_CLASS)
+        } else {
+            String::new()
+        };
+        writeln!(
+            body,
+            r#"  <rect{} x="{}" y="{}" width="{}" height="{}" fill="{}"{}/>"#,
+            class,
+            opts.left_top_coord.x,
+            opts.left_top_coord.y,
+            opts.width,
+            opts.height,
+            fill(opts.fill_color),
+            stroke(opts.line_size, opts.line_color, &opts.line_style)
+        )
+        .unwrap();
+    }
+
+    fn draw_circle(&self, opts: DrawCircleOpts) {
+        let mut body = self.body.borrow_mut();
+        writeln!(
+            body,
+            r#"  <circle cx="{}" cy="{}" r="{}" fill="{}"{}/>"#,
+            opts.center_coord.x,
+            opts.center_coord.y,
+            opts.r,
+            color(opts.fill_color),
+            stroke(opts.line_size, opts.line_color, &LineStyle::Solid)
+        )
+        .unwrap();
+    }
+
+    fn draw_text(&self, opts: DrawTextOpts) {
+        let mut body = self.body.borrow_mut();
+        let lines: Vec<&str> = opts.content.split('\n').collect();
+        let center = opts.left_top_coord.x + opts.width / 2.;
+        // Centered in the box, like the fltk and egui binders.
+        let first_line = opts.left_top_coord.y + opts.height / 2.
+            - opts.font_size * (lines.len() as f64 - 1.) / 2.;
+        write!(
+            body,
+            r#"  <text x="{}" y="{}" font-size="{}" letter-spacing="{}" fill="{}" text-anchor="middle" dominant-baseline="central">"#,
+            center,
+            first_line,
+            opts.font_size,
+            opts.font_space,
+            color(opts.font_color)
+        )
+        .unwrap();
+        for (i, line) in lines.iter().enumerate() {
+            write!(
+                body,
+                r#"<tspan x="{}" dy="{}">{}</tspan>"#,
+                center,
+                if i == 0 { 0. } else { opts.font_size },
+                escape(line)
+            )
+            .unwrap();
+        }
+        writeln!(body, "</text>").unwrap();
+    }
+
+    fn draw_path(&self, opts: DrawPathOpts) {
+        if opts.coords.is_empty() {
+            return;
+        }
+        let mut body = self.body.borrow_mut();
+        let mut d = String::new();
+        for (i, coord) in opts.coords.iter().enumerate() {
+            write!(
+                d,
+                "{}{} {} ",
+                if i == 0 { "M" } else { "L" },
+                coord.x,
+                coord.y
+            )
+            .unwrap();
+        }
+        if opts.closed {
+            d.push('Z');
+        }
+        writeln!(
+            body,
+            r#"  <path d="{}" fill="{}"{} stroke-linecap="round" stroke-linejoin="round"/>"#,
+            d.trim_end(),
+            fill(opts.fill_color),
+            stroke(opts.line_size, opts.line_color, &LineStyle::Solid)
+        )
+        .unwrap();
+    }
+
+    fn draw_image(&self, opts: DrawImageOpts) {
+        let mut body = self.body.borrow_mut();
+        writeln!(
+            body,
+            r#"  <image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" href="{}"/>"#,
+            opts.left_top_coord.x,
+            opts.left_top_coord.y,
+            opts.width,
+            opts.height,
+            escape(opts.data)
+        )
+        .unwrap();
+    }
+
+    fn draw_end(&self) -> Box<dyn std::any::Any> {
+        self.set_opacity(1.);
+        let min = self.view_box.min();
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+            self.view_box.width(),
+            self.view_box.height(),
+            min.x,
+            min.y,
+            self.view_box.width(),
+            self.view_box.height()
+        )
+        .unwrap();
+        if let Some(metadata) = &self.metadata {
+            writeln!(
+                svg,
+                r#"  <metadata id="{}">{}</metadata>"#,
+                METADATA_ID,
+                escape(metadata)
+            )
+            .unwrap();
+        }
+        svg.push_str(&self.body.borrow());
+        svg.push_str("</svg>\n");
+        Box::new(svg)
+    }
+}
+
+fn color(color: u32) -> String {
+    format!("#{:06x}", color & 0xffffff)
+}
+
+fn fill(fill_color: Option<u32>) -> String {
+    match fill_color {
+        Some(fill_color) => color(fill_color),
+        None => "none".to_string(),
+    }
+}
+
+fn stroke(line_size: f64, line_color: u32, line_style: &LineStyle) -> String {
+    if line_size <= 0. {
+        return r#" stroke="none""#.to_string();
+    }
+    let mut stroke = format!(
+        r#" stroke="{}" stroke-width="{}""#,
+        color(line_color),
+        line_size
+    );
+    if let LineStyle::Dotted = line_style {
+        write!(
+            stroke,
+            r#" stroke-dasharray="{} {}""#,
+            line_size,
+            line_size * 2.
+        )
+        .unwrap();
+    }
+    stroke
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,635 @@
+use std::f64::consts::PI;
+
+use base64::Engine;
+use geo::{coord, Coordinate};
+use roxmltree::{Document, Node};
+use svgtypes::{SimplePathSegment, SimplifyingPathParser};
+
+use crate::{
+    clipboard::PasteError,
+    elem::{
+        ellipse::Ellipse,
+        image::Image,
+        line::Line,
+        pen::Pen,
+        rect::{LineStyle, Rect},
+        text::Text,
+        IElem,
+    },
+    panel::{ImportMode, Panel},
+    viewport::Affine,
+};
+
+use super::export::{METADATA_ID, PAGE_CLASS};
+
+/// Converts an SVG document into native elements and adds them to `panel`.
+/// Group transforms are baked into the element coordinates. Anything that
+/// has no native counterpart (`<use>`, gradients, filters, ...) is kept as an
+/// `Image` holding that part of the SVG. Filled outlines become filled
+/// `Pen`s. An SVG written by `export_svg` with
+/// `embed_document` is imported from its embedded copy instead, unless that
+/// can't be read.
+///
+/// Returns the number of imported elements.
+pub fn import_svg(
+    panel: &mut Panel,
+    data: &str,
+    mode: ImportMode,
+) -> Result<usize, roxmltree::Error> {
+    let doc = Document::parse(data)?;
+
+    let embedded = doc
+        .descendants()
+        .find(|node| node.has_tag_name("metadata") && node.attribute("id") == Some(METADATA_ID))
+        .and_then(|node| node.text());
+    if let Some(Ok(count)) = embedded.map(|document| import_document(panel, document, mode)) {
+        return Ok(count);
+    }
+
+    let root = doc.root_element();
+    let mut importer = Importer {
+        doc: &doc,
+        elems: vec![],
+    };
+    importer.walk(root, root_transform(root));
+    let count = importer.elems.len();
+    panel.add_imported(importer.elems, mode);
+    Ok(count)
+}
+
+/// Imports a document embedded by `export_svg`. Replacing loads all of it,
+/// with its layers, ids and pages. Appending adds its elements to the
+/// active layer, their connectors attached to each other.
+fn import_document(
+    panel: &mut Panel,
+    document: &str,
+    mode: ImportMode,
+) -> Result<usize, PasteError> {
+    let (panel_serialize, elems) = panel.try_parse_elems(document)?;
+    for page in panel_serialize.pages.iter() {
+        if !page.document.is_empty() {
+            panel.try_parse_elems(&page.document)?;
+        }
+    }
+    let count = elems.len();
+    match mode {
+        ImportMode::Replace => {
+            panel.selects.clear();
+            panel.select_box = None;
+            panel.hover_index = -1;
+            panel.import(document);
+        }
+        ImportMode::Append => panel.add_exported(elems, &panel_serialize.elem_ids, mode),
+    }
+    Ok(count)
+}
+
+fn root_transform(root: Node) -> Affine {
+    let view_box = root
+        .attribute("viewBox")
+        .and_then(|v| v.parse::<svgtypes::ViewBox>().ok());
+    let width = root.attribute("width").and_then(parse_length);
+    let height = root.attribute("height").and_then(parse_length);
+    match (view_box, width, height) {
+        (Some(vb), Some(w), Some(h)) if vb.w > 0. && vb.h > 0. => Affine {
+            a: w / vb.w,
+            b: 0.,
+            c: 0.,
+            d: h / vb.h,
+            e: -vb.x * w / vb.w,
+            f: -vb.y * h / vb.h,
+        },
+        (Some(vb), _, _) => Affine {
+            e: -vb.x,
+            f: -vb.y,
+            ..Affine::IDENTITY
+        },
+        _ => Affine::IDENTITY,
+    }
+}
+
+struct Importer<'a, 'input> {
+    doc: &'a Document<'input>,
+    elems: Vec<Box<dyn IElem>>,
+}
+
+impl<'a, 'input> Importer<'a, 'input> {
+    fn walk(&mut self, node: Node, parent: Affine) {
+        for child in node.children().filter(|n| n.is_element()) {
+            let transform = child_transform(child, parent);
+            let page = child.attribute("class").map_or(false, |class| {
+                class.split_whitespace().any(|c| c == PAGE_CLASS)
+            });
+            if style(child, "display") == Some("none") || page {
+                continue;
+            }
+            let tag = child.tag_name().name();
+            match tag {
+                "g" | "a" | "switch" => {
+                    if needs_fallback(child) {
+                        let bounds = self.bounds(child, transform);
+                        self.fallback(child, parent, bounds);
+                    } else {
+                        self.walk(child, transform);
+                    }
+                }
+                "defs" | "metadata" | "title" | "desc" | "style" | "script" | "clipPath"
+                | "mask" | "pattern" | "marker" | "symbol" | "linearGradient"
+                | "radialGradient" | "filter" => {}
+                "rect" | "line" | "polyline" | "polygon" | "path" | "circle" | "ellipse"
+                | "text" | "image" => {
+                    if needs_fallback(child) {
+                        let bounds = self.shape_bounds(child, transform);
+                        self.fallback(child, parent, bounds);
+                    } else {
+                        self.shape(child, transform);
+                    }
+                }
+                _ => {
+                    let bounds = self.bounds(child, transform);
+                    self.fallback(child, parent, bounds);
+                }
+            }
+        }
+    }
+
+    fn shape(&mut self, node: Node, transform: Affine) {
+        match node.tag_name().name() {
+            "rect" => {
+                let (x, y) = (number(node, "x"), number(node, "y"));
+                let (w, h) = (number(node, "width"), number(node, "height"));
+                let corners = vec![
+                    transform.apply(coord! { x: x, y: y }),
+                    transform.apply(coord! { x: x + w, y: y }),
+                    transform.apply(coord! { x: x + w, y: y + h }),
+                    transform.apply(coord! { x: x, y: y + h }),
+                ];
+                if transform.is_axis_aligned() {
+                    let rect = rect_style(node, transform, bounding(&corners));
+                    self.elems.push(Box::new(rect));
+                } else {
+                    self.polyline(node, transform, corners, true);
+                }
+            }
+            "circle" | "ellipse" => {
+                let (cx, cy) = (number(node, "cx"), number(node, "cy"));
+                let (rx, ry) = if node.has_tag_name("circle") {
+                    (number(node, "r"), number(node, "r"))
+                } else {
+                    (number(node, "rx"), number(node, "ry"))
+                };
+                if transform.is_axis_aligned() {
+                    let corners = [
+                        transform.apply(coord! { x: cx - rx, y: cy - ry }),
+                        transform.apply(coord! { x: cx + rx, y: cy + ry }),
+                    ];
+                    let rect = rect_style(node, transform, bounding(&corners));
+                    self.elems.push(Box::new(Ellipse { rect }));
+                } else {
+                    let coords = (0..64)
+                        .map(|i| {
+                            let angle = i as f64 / 64. * 2. * PI;
+                            transform.apply(
+                                coord! { x: cx + rx * angle.cos(), y: cy + ry * angle.sin() },
+                            )
+                        })
+                        .collect();
+                    self.polyline(node, transform, coords, true);
+                }
+            }
+            "line" => {
+                let mut line = Line {
+                    from_coord: transform
+                        .apply(coord! { x: number(node, "x1"), y: number(node, "y1") }),
+                    end_coord: transform
+                        .apply(coord! { x: number(node, "x2"), y: number(node, "y2") }),
+                    ..Default::default()
+                };
+                (line.line_size, line.line_color) = stroke_style(node, transform);
+                self.elems.push(Box::new(line));
+            }
+            "polyline" | "polygon" => {
+                let coords = svgtypes::PointsParser::from(node.attribute("points").unwrap_or(""))
+                    .map(|(x, y)| transform.apply(coord! { x: x, y: y }))
+                    .collect();
+                self.polyline(node, transform, coords, node.has_tag_name("polygon"));
+            }
+            "path" => {
+                for (coords, closed) in flatten_path(node.attribute("d").unwrap_or("")) {
+                    let coords = coords.iter().map(|coord| transform.apply(*coord)).collect();
+                    self.polyline(node, transform, coords, closed);
+                }
+            }
+            "text" => {
+                let lines: Vec<String> = {
+                    let spans: Vec<String> = node
+                        .children()
+                        .filter(|n| n.has_tag_name("tspan"))
+                        .map(text_content)
+                        .collect();
+                    if spans.len() > 1 {
+                        spans
+                    } else {
+                        vec![text_content(node)]
+                    }
+                };
+                let font_size = style(node, "font-size")
+                    .and_then(parse_length)
+                    .unwrap_or(16.);
+                let chars = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+                let width = font_size * 0.6 * chars as f64;
+                let height = font_size * lines.len() as f64;
+                let mut x = number(node, "x");
+                match style(node, "text-anchor") {
+                    Some("middle") => x -= width / 2.,
+                    Some("end") => x -= width,
+                    _ => {}
+                }
+                // `y` is the first baseline, or its middle for the
+                // centered text `export_svg` writes.
+                let y = match style(node, "dominant-baseline") {
+                    Some("central") | Some("middle") => number(node, "y") - font_size / 2.,
+                    _ => number(node, "y") - font_size * 0.8,
+                };
+                let corners = [
+                    transform.apply(coord! { x: x, y: y }),
+                    transform.apply(coord! { x: x + width, y: y + height }),
+                ];
+                let bounds = bounding(&corners);
+                self.elems.push(Box::new(Text {
+                    lt_coord: bounds.min(),
+                    width: bounds.width(),
+                    height: bounds.height(),
+                    content: lines.join("\n"),
+                    font_color: paint(style(node, "fill")).flatten().unwrap_or(0x000000),
+                }));
+            }
+            "image" => {
+                let (x, y) = (number(node, "x"), number(node, "y"));
+                let (w, h) = (number(node, "width"), number(node, "height"));
+                let corners = [
+                    transform.apply(coord! { x: x, y: y }),
+                    transform.apply(coord! { x: x + w, y: y + h }),
+                ];
+                let bounds = bounding(&corners);
+                let data = node
+                    .attribute(("http://www.w3.org/1999/xlink", "href"))
+                    .or(node.attribute("href"))
+                    .unwrap_or("");
+                self.elems.push(Box::new(Image {
+                    rect: Rect {
+                        lt_coord: bounds.min(),
+                        width: bounds.width(),
+                        height: bounds.height(),
+                        ..Default::default()
+                    },
+                    data: data.to_string(),
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    /// Polylines and curves become `Pen`s, or a `Line` for a single segment.
+    fn polyline(
+        &mut self,
+        node: Node,
+        transform: Affine,
+        mut coords: Vec<Coordinate>,
+        closed: bool,
+    ) {
+        if coords.len() < 2 {
+            return;
+        }
+        if closed && coords.first() != coords.last() {
+            coords.push(coords[0]);
+        }
+        let (line_size, line_color) = stroke_style(node, transform);
+        if coords.len() == 2 {
+            self.elems.push(Box::new(Line {
+                from_coord: coords[0],
+                end_coord: coords[1],
+                line_size,
+                line_color,
+            }));
+        } else {
+            self.elems.push(Box::new(Pen {
+                coords,
+                line_size,
+                line_color,
+                // SVG fills open outlines too, black unless told otherwise.
+                fill_color: paint(style(node, "fill")).unwrap_or(Some(0x000000)),
+            }));
+        }
+    }
+
+    fn shape_bounds(&self, node: Node, transform: Affine) -> Option<geo::Rect> {
+        let mut importer = Importer {
+            doc: self.doc,
+            elems: vec![],
+        };
+        importer.shape(node, transform);
+        importer
+            .elems
+            .iter()
+            .filter_map(|elem| elem.bounding_rect())
+            .reduce(crate::panel::union_rect)
+    }
+
+    /// Where `node` draws, with `transform` already applied to it: the
+    /// shapes of a group, or what a `<use>` refers to.
+    fn bounds(&self, node: Node, transform: Affine) -> Option<geo::Rect> {
+        match node.tag_name().name() {
+            "g" | "a" | "switch" | "symbol" => node
+                .children()
+                .filter(|n| n.is_element() && style(*n, "display") != Some("none"))
+                .filter_map(|child| self.bounds(child, child_transform(child, transform)))
+                .reduce(crate::panel::union_rect),
+            "rect" | "line" | "polyline" | "polygon" | "path" | "circle" | "ellipse" | "text"
+            | "image" => self.shape_bounds(node, transform),
+            "use" => match use_target(node) {
+                Some(target) => {
+                    let offset = Affine::translate(coord! {
+                        x: number(node, "x"),
+                        y: number(node, "y"),
+                    });
+                    let transform = transform.then_after(&offset);
+                    self.bounds(target, child_transform(target, transform))
+                }
+                None => attribute_bounds(node, transform),
+            },
+            _ => attribute_bounds(node, transform),
+        }
+    }
+
+    /// Keeps `node` as an SVG image covering `bounds`.
+    fn fallback(&mut self, node: Node, parent: Affine, bounds: Option<geo::Rect>) {
+        let bounds = match bounds {
+            Some(bounds) if bounds.width() > 0. && bounds.height() > 0. => bounds,
+            _ => return,
+        };
+        let input = self.doc.input_text();
+        let mut defs: String = self
+            .doc
+            .descendants()
+            .filter(|n| n.has_tag_name("defs"))
+            .map(|n| &input[n.range()])
+            .collect();
+        // `<use>` may refer to something drawn elsewhere in the document.
+        let used: String = node
+            .descendants()
+            .filter_map(use_target)
+            .filter(|target| !target.ancestors().any(|n| n.has_tag_name("defs")))
+            .map(|target| &input[target.range()])
+            .collect();
+        if !used.is_empty() {
+            defs += &format!("<defs>{}</defs>", used);
+        }
+        let min = bounds.min();
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{}" height="{}" viewBox="{} {} {} {}">{}<g transform="matrix({} {} {} {} {} {})">{}</g></svg>"#,
+            bounds.width(),
+            bounds.height(),
+            min.x,
+            min.y,
+            bounds.width(),
+            bounds.height(),
+            defs,
+            parent.a,
+            parent.b,
+            parent.c,
+            parent.d,
+            parent.e,
+            parent.f,
+            &input[node.range()]
+        );
+        self.elems.push(Box::new(Image {
+            rect: Rect {
+                lt_coord: min,
+                width: bounds.width(),
+                height: bounds.height(),
+                ..Default::default()
+            },
+            data: format!(
+                "data:image/svg+xml;base64,{}",
+                base64::engine::general_purpose::STANDARD.encode(svg)
+            ),
+        }));
+    }
+}
+
+/// Paint servers, filters, clips and masks cannot be expressed natively.
+fn needs_fallback(node: Node) -> bool {
+    let url = |name| style(node, name).map_or(false, |v| v.starts_with("url("));
+    url("fill")
+        || url("stroke")
+        || ["filter", "clip-path", "mask"]
+            .iter()
+            .any(|name| node.attribute(*name).map_or(false, |v| v != "none"))
+}
+
+/// `parent` with the `transform` attribute of `node` applied.
+fn child_transform(node: Node, parent: Affine) -> Affine {
+    match node
+        .attribute("transform")
+        .and_then(|t| t.parse::<svgtypes::Transform>().ok())
+    {
+        Some(t) => parent.then_after(&Affine {
+            a: t.a,
+            b: t.b,
+            c: t.c,
+            d: t.d,
+            e: t.e,
+            f: t.f,
+        }),
+        None => parent,
+    }
+}
+
+/// The element a `<use>` refers to, unless it contains the `<use>` itself.
+fn use_target<'a, 'input>(node: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    if !node.has_tag_name("use") {
+        return None;
+    }
+    let id = node
+        .attribute(("http://www.w3.org/1999/xlink", "href"))
+        .or(node.attribute("href"))?
+        .strip_prefix('#')?;
+    node.document()
+        .descendants()
+        .find(|n| n.attribute("id") == Some(id))
+        .filter(|target| !node.ancestors().any(|n| n == *target))
+}
+
+fn attribute_bounds(node: Node, transform: Affine) -> Option<geo::Rect> {
+    let (x, y) = (number(node, "x"), number(node, "y"));
+    let (w, h) = (number(node, "width"), number(node, "height"));
+    let corners = [
+        transform.apply(coord! { x: x, y: y }),
+        transform.apply(coord! { x: x + w, y: y }),
+        transform.apply(coord! { x: x + w, y: y + h }),
+        transform.apply(coord! { x: x, y: y + h }),
+    ];
+    Some(bounding(&corners))
+}
+
+fn bounding(coords: &[Coordinate]) -> geo::Rect {
+    let mut min = coords[0];
+    let mut max = coords[0];
+    for coord in coords {
+        min.x = min.x.min(coord.x);
+        min.y = min.y.min(coord.y);
+        max.x = max.x.max(coord.x);
+        max.y = max.y.max(coord.y);
+    }
+    geo::Rect::new(min, max)
+}
+
+fn rect_style(node: Node, transform: Affine, bounds: geo::Rect) -> Rect {
+    let (line_size, line_color) = stroke_style(node, transform);
+    let dashed = style(node, "stroke-dasharray").map_or(false, |v| v != "none");
+    Rect {
+        lt_coord: bounds.min(),
+        width: bounds.width(),
+        height: bounds.height(),
+        line_size,
+        line_color,
+        // SVG fills shapes black unless told otherwise.
+        fill_color: paint(style(node, "fill")).unwrap_or(Some(0x000000)),
+        line_style: if dashed {
+            LineStyle::Dotted
+        } else {
+            LineStyle::Solid
+        },
+    }
+}
+
+/// Stroke width and color. A `<line>` that is only filled keeps its fill
+/// color so it stays visible; other shapes keep the fill as their fill.
+fn stroke_style(node: Node, transform: Affine) -> (f64, u32) {
+    let width = style(node, "stroke-width")
+        .and_then(parse_length)
+        .unwrap_or(1.)
+        * transform.length_scale();
+    match paint(style(node, "stroke")).flatten() {
+        Some(color) => (width, color),
+        None => match paint(style(node, "fill")).unwrap_or(Some(0x000000)) {
+            Some(color) if node.has_tag_name("line") => (1., color),
+            _ => (0., 0x000000),
+        },
+    }
+}
+
+/// `None` when unset, `Some(None)` for `none`.
+fn paint(value: Option<&str>) -> Option<Option<u32>> {
+    let value = value?;
+    if value == "none" {
+        return Some(None);
+    }
+    let color = value.parse::<svgtypes::Color>().ok()?;
+    Some(Some(
+        (color.red as u32) << 16 | (color.green as u32) << 8 | color.blue as u32,
+    ))
+}
+
+/// Looks `name` up in the `style` attribute, then as a presentation
+/// attribute, walking up to inherit it from ancestors.
+fn style<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    for node in node.ancestors().filter(|n| n.is_element()) {
+        if let Some(style) = node.attribute("style") {
+            for declaration in style.split(';') {
+                if let Some((key, value)) = declaration.split_once(':') {
+                    if key.trim() == name {
+                        return Some(value.trim());
+                    }
+                }
+            }
+        }
+        if let Some(value) = node.attribute(name) {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn parse_length(value: &str) -> Option<f64> {
+    value.parse::<svgtypes::Length>().ok().map(|l| l.number)
+}
+
+fn number(node: Node, name: &str) -> f64 {
+    node.attribute(name).and_then(parse_length).unwrap_or(0.)
+}
+
+fn text_content(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Splits path data into subpaths, flattening curves into short segments.
+fn flatten_path(d: &str) -> Vec<(Vec<Coordinate>, bool)> {
+    let mut subpaths = vec![];
+    let mut coords: Vec<Coordinate> = vec![];
+    let mut current = coord! { x: 0., y: 0. };
+    for segment in SimplifyingPathParser::from(d).flatten() {
+        match segment {
+            SimplePathSegment::MoveTo { x, y } => {
+                if coords.len() > 1 {
+                    subpaths.push((std::mem::take(&mut coords), false));
+                }
+                current = coord! { x: x, y: y };
+                coords = vec![current];
+            }
+            SimplePathSegment::LineTo { x, y } => {
+                current = coord! { x: x, y: y };
+                coords.push(current);
+            }
+            SimplePathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let p0 = current;
+                for i in 1..=16 {
+                    let t = i as f64 / 16.;
+                    let mt = 1. - t;
+                    coords.push(coord! {
+                        x: mt * mt * mt * p0.x + 3. * mt * mt * t * x1 + 3. * mt * t * t * x2 + t * t * t * x,
+                        y: mt * mt * mt * p0.y + 3. * mt * mt * t * y1 + 3. * mt * t * t * y2 + t * t * t * y,
+                    });
+                }
+                current = coord! { x: x, y: y };
+            }
+            SimplePathSegment::Quadratic { x1, y1, x, y } => {
+                let p0 = current;
+                for i in 1..=16 {
+                    let t = i as f64 / 16.;
+                    let mt = 1. - t;
+                    coords.push(coord! {
+                        x: mt * mt * p0.x + 2. * mt * t * x1 + t * t * x,
+                        y: mt * mt * p0.y + 2. * mt * t * y1 + t * t * y,
+                    });
+                }
+                current = coord! { x: x, y: y };
+            }
+            SimplePathSegment::ClosePath => {
+                if coords.len() > 1 {
+                    let start = coords[0];
+                    subpaths.push((std::mem::take(&mut coords), true));
+                    current = start;
+                    coords = vec![current];
+                }
+            }
+        }
+    }
+    if coords.len() > 1 {
+        subpaths.push((coords, false));
+    }
+    subpaths
+}
@@ -0,0 +1,42 @@
+use crate::elem::rect::LineStyle;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HandleShape {
+    Circle,
+    Square,
+}
+
+/// How the panel and the built-in elements draw everything that isn't
+/// content: the canvas, hover and selection highlights and resize handles.
+/// Sizes are in binder coordinates, so they look the same at any zoom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Fills the page, or the whole region on an infinite canvas.
+    pub canvas_background: u32,
+    /// Around the pages in `CanvasMode::Paged`.
+    pub backdrop: u32,
+    /// Handles, outlines of hovered boxes and the select box.
+    pub selection_color: u32,
+    pub handle_shape: HandleShape,
+    /// Radius of a circle handle, half the side of a square one.
+    pub handle_size: f64,
+    pub handle_fill: u32,
+    /// Added to the line width of hovered elements.
+    pub hover_outline: f64,
+    pub select_box_dash: LineStyle,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            canvas_background: 0xffffff,
+            backdrop: 0xe0e0e0,
+            selection_color: 0xff0000,
+            handle_shape: HandleShape::Circle,
+            handle_size: 5.,
+            handle_fill: 0x000000,
+            hover_outline: 2.,
+            select_box_dash: LineStyle::Dotted,
+        }
+    }
+}
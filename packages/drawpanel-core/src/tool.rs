@@ -0,0 +1,528 @@
+use std::fmt::Debug;
+
+use geo::{point, Coordinate, EuclideanDistance, Intersects, Point};
+
+use crate::{
+    binder::{Cursor, Draw, EventKey, EventMouseButton},
+    elem::{
+        rect::{LineStyle, Rect},
+        Elem, ElemId, IElem, Status,
+    },
+    panel::Panel,
+    session::{SessionAction, SessionMode},
+};
+
+/// A mouse event as a tool gets it.
+#[derive(Debug, Clone, Copy)]
+pub struct ToolEvent {
+    pub button: EventMouseButton,
+    /// In panel coordinates.
+    pub coord: Coordinate,
+    /// As the binder sent it.
+    pub raw_coord: Coordinate,
+}
+
+/// Decides what mouse and keyboard input does to the panel. The built-in
+/// tools cover the modes of `Mode`, others can be installed with
+/// `Drawpanel::set_tool`. A tool can hand over to another one by calling
+/// `Panel::set_tool` from any of its methods.
+pub trait Tool: Debug {
+    fn name(&self) -> &str;
+    fn on_push(&mut self, _panel: &mut Panel, _event: &ToolEvent) {}
+    fn on_drag(&mut self, _panel: &mut Panel, _event: &ToolEvent) {}
+    fn on_release(&mut self, _panel: &mut Panel, _event: &ToolEvent) {}
+    fn on_key(&mut self, _panel: &mut Panel, _key: EventKey, _pressed: bool) {}
+    /// Updates the hovered element. The connectors aren't routed after a
+    /// move, a tool that changes elements here calls
+    /// `Panel::route_connectors` itself.
+    fn on_move(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        panel.update_hover(event.coord);
+    }
+    /// Enters the hovered group or opens the binder's input on the hovered
+    /// element.
+    fn on_dblclick(&mut self, panel: &mut Panel, _event: &ToolEvent) {
+        if panel.hover_index > -1 {
+            let index = panel.hover_index as usize;
+            if !panel.enter_group(index) {
+                panel.begin_edit_state(index);
+            }
+        }
+    }
+    /// Drawn on top of the elements, in panel coordinates.
+    fn draw_overlay(&self, _panel: &Panel, _draw: &dyn Draw) {}
+    /// The cursor to show at `Panel::mouse_coord`.
+    fn cursor(&self, panel: &Panel) -> Cursor {
+        default_cursor(panel)
+    }
+    fn elem_status(&self, panel: &Panel, index: usize) -> Status {
+        default_status(panel, index)
+    }
+    /// How sessions and change events refer to the tool. Only the built-in
+    /// tools can be restored from it.
+    fn mode(&self) -> SessionMode {
+        SessionMode::Tool(self.name().to_string())
+    }
+}
+
+/// Hovered and selected elements are highlighted.
+pub fn default_status(panel: &Panel, index: usize) -> Status {
+    if index as isize == panel.hover_index || panel.selects.contains(&(index as u32)) {
+        Status::Hover
+    } else {
+        Status::Default
+    }
+}
+
+/// Elements on locked layers refuse the mouse.
+pub fn default_cursor(panel: &Panel) -> Cursor {
+    if panel.hover_index == -1 && panel.is_locked_at(panel.relative_coord(panel.mouse_coord)) {
+        Cursor::NotAllowed
+    } else {
+        Cursor::Default
+    }
+}
+
+/// Moves the hovered element, the selection or the view. Pressing near a
+/// vertex of the hovered element resizes it instead.
+#[derive(Debug, Default)]
+pub struct MoveTool {
+    resizing: Option<u8>,
+    /// Between a press and its release.
+    dragging: bool,
+}
+
+impl MoveTool {
+    pub fn resizing(vertex: u8) -> Self {
+        MoveTool {
+            resizing: Some(vertex),
+            ..Default::default()
+        }
+    }
+}
+
+impl Tool for MoveTool {
+    fn name(&self) -> &str {
+        "move"
+    }
+
+    fn on_push(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        self.dragging = true;
+        let mouse_point = point!(event.coord);
+        // Pressing outside the children closes an entered group.
+        if panel.hover_index == -1 && !panel.entered.is_empty() {
+            panel.leave_group();
+        }
+        if let Some(elem) = panel.elems.get(panel.hover_index as usize) {
+            for (i, coord) in elem.get_vertex().iter().enumerate() {
+                let point = Point::new(coord.x, coord.y);
+                if mouse_point.euclidean_distance(&point) < 10. {
+                    self.resizing = Some(i as u8);
+                    panel.drag_vertex = i as isize;
+                }
+            }
+        }
+        if let Some(select_box) = &panel.select_box {
+            let select_box_ver = select_box.get_vertex();
+            let box_rect = geo::Rect::new(select_box_ver[0], select_box_ver[2]);
+            if box_rect.to_polygon().euclidean_distance(&mouse_point) > 0. {
+                panel.selects.clear();
+                panel.select_box = None;
+            }
+        }
+    }
+
+    fn on_drag(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        let (prev_coord, coord) = (panel.prev_coord, event.coord);
+        let hook_event = panel.hook_event.as_mut().unwrap();
+
+        if let Some(vertex) = self.resizing {
+            if let Some(elem) = panel.elems.get_mut(panel.hover_index as usize) {
+                if hook_event.before_resize(elem, vertex, prev_coord, coord) {
+                    elem.edit_resizing(prev_coord, coord, panel.drag_vertex as i32);
+                }
+            }
+            return;
+        }
+
+        let mut is_move_elem = false;
+        if panel.selects.is_empty() {
+            if let Some(elem) = panel.elems.get_mut(panel.hover_index as usize) {
+                if hook_event.before_move(elem, prev_coord, coord) {
+                    elem.edit_moving(prev_coord, coord);
+                }
+                is_move_elem = true;
+            }
+        } else {
+            let allowed = panel
+                .selects
+                .iter()
+                .all(|idx| match panel.elems.get(*idx as usize) {
+                    Some(elem) => hook_event.before_move(elem, prev_coord, coord),
+                    None => true,
+                });
+            for idx in panel.selects.iter() {
+                if let Some(elem) = panel.elems.get_mut(*idx as usize) {
+                    if allowed {
+                        elem.edit_moving(prev_coord, coord);
+                    }
+                    is_move_elem = true;
+                }
+            }
+            if allowed {
+                let select_box = panel.select_box.as_mut().unwrap();
+                select_box.edit_moving(prev_coord, coord);
+            }
+        }
+
+        if !is_move_elem {
+            panel.move_pos(panel.raw_prev_coord, event.raw_coord);
+        }
+
+        panel.raw_prev_coord = event.raw_coord;
+        panel.prev_coord = coord;
+    }
+
+    fn on_release(&mut self, _panel: &mut Panel, _event: &ToolEvent) {
+        self.resizing = None;
+        self.dragging = false;
+    }
+
+    fn on_key(&mut self, panel: &mut Panel, key: EventKey, pressed: bool) {
+        if key == EventKey::Escape && pressed {
+            panel.leave_group();
+        }
+    }
+
+    fn elem_status(&self, panel: &Panel, index: usize) -> Status {
+        match self.resizing {
+            Some(vertex) if index as isize == panel.hover_index => Status::Resizing(vertex),
+            _ => default_status(panel, index),
+        }
+    }
+
+    /// A resize cursor near a vertex of the hovered element, grabbing while
+    /// dragging the view.
+    fn cursor(&self, panel: &Panel) -> Cursor {
+        let elem = match panel.elems.get(panel.hover_index as usize) {
+            Some(elem) => elem,
+            None if self.dragging && panel.selects.is_empty() => return Cursor::Grabbing,
+            None => return default_cursor(panel),
+        };
+        let coord = panel.relative_coord(panel.mouse_coord);
+        let vertex = elem.get_vertex();
+        let near = match self.resizing {
+            Some(vertex) => Some(vertex as usize),
+            None if self.dragging => None,
+            None => vertex
+                .iter()
+                .position(|v| point!(*v).euclidean_distance(&point!(coord)) < 10.),
+        };
+        match (near.and_then(|i| vertex.get(i)), elem.bounding_rect()) {
+            (Some(vertex), Some(bounds)) => Cursor::resize_towards(*vertex - bounds.center()),
+            _ => Cursor::Move,
+        }
+    }
+
+    fn mode(&self) -> SessionMode {
+        match self.resizing {
+            Some(vertex) => SessionMode::EditResizing(vertex),
+            None => SessionMode::EditMoving,
+        }
+    }
+}
+
+/// Adds a copy of `prototype` on press, shapes it while dragging and
+/// finishes it on release. A sticky tool stays active for the next one,
+/// otherwise it hands over to `MoveTool`. Escape or a right click cancel
+/// the element being drawn.
+#[derive(Debug)]
+pub struct CreateTool {
+    prototype: Box<dyn IElem>,
+    sticky: bool,
+    creating: Option<ElemId>,
+}
+
+impl CreateTool {
+    pub fn new(prototype: Box<dyn IElem>) -> Self {
+        CreateTool {
+            prototype,
+            sticky: true,
+            creating: None,
+        }
+    }
+
+    /// Creates a single element.
+    pub fn once(prototype: Box<dyn IElem>) -> Self {
+        CreateTool {
+            sticky: false,
+            ..Self::new(prototype)
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn IElem> {
+        self.prototype.import(&self.prototype.export())
+    }
+
+    /// Removes the element being drawn.
+    pub fn cancel(&mut self, panel: &mut Panel) {
+        if let Some(index) = self.creating.take().and_then(|id| panel.elem_index(id)) {
+            panel.remove_elem(index);
+            panel.hover_index = -1;
+        }
+    }
+
+    /// What comes after a finished element.
+    fn next_tool(&self) -> Box<dyn Tool> {
+        if self.sticky {
+            Box::new(CreateTool::new(self.fresh()))
+        } else {
+            Box::new(MoveTool::default())
+        }
+    }
+}
+
+impl Tool for CreateTool {
+    fn name(&self) -> &str {
+        "create"
+    }
+
+    fn on_push(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        if event.button == EventMouseButton::Right {
+            self.cancel(panel);
+            return;
+        }
+        let elem = self.fresh();
+        let hook_event = panel.hook_event.as_mut().unwrap();
+        if hook_event.before_create(&elem, event.coord) {
+            hook_event.begin_create(&elem, event.coord);
+            self.creating = Some(panel.push_elem(elem));
+        }
+    }
+
+    fn on_drag(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        let index = match self.creating.and_then(|id| panel.elem_index(id)) {
+            Some(index) => index,
+            None => return,
+        };
+        let elem = &mut panel.elems[index];
+        elem.creating(panel.prev_coord, event.coord);
+        panel
+            .hook_event
+            .as_mut()
+            .unwrap()
+            .doing_create(elem, event.coord);
+    }
+
+    fn on_release(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        let index = match self.creating.take().and_then(|id| panel.elem_index(id)) {
+            Some(index) => index,
+            None => return,
+        };
+        let elem = &mut panel.elems[index];
+        panel
+            .hook_event
+            .as_mut()
+            .unwrap()
+            .end_create(elem, event.coord);
+        if elem.need_input() && panel.begin_edit_state(index) {
+            // Come back here once the input is done.
+            let next = self.next_tool();
+            panel.set_tool(Box::new(TextEditTool::new(Some(index)).then(next)));
+        } else if !self.sticky {
+            panel.set_tool(self.next_tool());
+        }
+    }
+
+    fn on_key(&mut self, panel: &mut Panel, key: EventKey, pressed: bool) {
+        if key != EventKey::Escape || !pressed {
+            return;
+        }
+        if self.creating.is_some() {
+            self.cancel(panel);
+        } else {
+            panel.set_tool(Box::new(MoveTool::default()));
+        }
+    }
+
+    fn elem_status(&self, panel: &Panel, index: usize) -> Status {
+        if index as isize == panel.hover_index {
+            Status::Hover
+        } else {
+            Status::Creating
+        }
+    }
+
+    /// A text cursor for elements typed into, a crosshair for the others.
+    fn cursor(&self, _panel: &Panel) -> Cursor {
+        if self.prototype.need_input() {
+            Cursor::Text
+        } else {
+            Cursor::Crosshair
+        }
+    }
+
+    fn mode(&self) -> SessionMode {
+        let elem = format!(
+            "{}({})",
+            self.prototype.elem_type(),
+            self.prototype.export()
+        );
+        if self.sticky {
+            SessionMode::Creating(Some(elem))
+        } else {
+            SessionMode::CreatingOnce(elem)
+        }
+    }
+}
+
+/// Deletes the element under the mouse, or the selection when pressing
+/// inside the select box.
+#[derive(Debug, Default)]
+pub struct DeleteTool;
+
+impl Tool for DeleteTool {
+    fn name(&self) -> &str {
+        "delete"
+    }
+
+    fn on_push(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        let idx = panel.hover_index;
+        if idx > -1 {
+            let elem = &panel.elems[idx as usize];
+            if panel.hook_event.as_mut().unwrap().before_delete(elem) {
+                panel.remove_elem(idx as usize);
+                panel.hover_index = -1;
+            }
+            panel.selects.clear();
+            panel.select_box = None;
+        } else if let Some(select_box) = &panel.select_box {
+            if select_box.hover_condition(point!(event.coord)) {
+                let selects: Vec<usize> = panel.selects.iter().map(|i| *i as usize).collect();
+                panel.delete_elems(&selects);
+            }
+        }
+    }
+
+    fn mode(&self) -> SessionMode {
+        SessionMode::Deleting
+    }
+}
+
+/// Drags a box and selects the elements that lie completely inside it.
+#[derive(Debug, Default)]
+pub struct SelectTool;
+
+impl Tool for SelectTool {
+    fn name(&self) -> &str {
+        "select"
+    }
+
+    fn on_push(&mut self, panel: &mut Panel, _event: &ToolEvent) {
+        panel.selects.clear();
+        panel.select_box = Some(Rect {
+            line_color: 0x000000,
+            line_size: 1.,
+            line_style: LineStyle::Dotted,
+            ..Rect::default()
+        });
+    }
+
+    fn on_drag(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        if let Some(select_box) = &mut panel.select_box {
+            select_box.creating(panel.prev_coord, event.coord);
+        }
+    }
+
+    fn on_release(&mut self, panel: &mut Panel, _event: &ToolEvent) {
+        let select_box = panel.select_box.as_ref().unwrap();
+        let select_box_ver = select_box.get_vertex();
+        let box_rect = geo::Rect::new(select_box_ver[0], select_box_ver[2]);
+        for (i, elem) in panel.elems.iter().enumerate() {
+            if !panel.is_editable(i) {
+                continue;
+            }
+            let is_select = elem
+                .get_vertex()
+                .into_iter()
+                .all(|coord| box_rect.intersects(&geo::Point::from(coord)));
+            if is_select {
+                panel.selects.insert(i as u32);
+            }
+        }
+        panel.set_tool(Box::new(MoveTool::default()));
+    }
+
+    fn cursor(&self, _panel: &Panel) -> Cursor {
+        Cursor::Crosshair
+    }
+
+    fn mode(&self) -> SessionMode {
+        SessionMode::Select
+    }
+}
+
+/// Active while the binder's input edits an element, see
+/// `Panel::begin_edit_state`. The next press ends the edit.
+#[derive(Debug, Default)]
+pub struct TextEditTool {
+    /// `None` edits the top element.
+    index: Option<usize>,
+    next: Option<Box<dyn Tool>>,
+}
+
+impl TextEditTool {
+    pub fn new(index: Option<usize>) -> Self {
+        TextEditTool { index, next: None }
+    }
+
+    /// The tool to switch to when the edit ends, `MoveTool` by default.
+    pub fn then(mut self, next: Box<dyn Tool>) -> Self {
+        self.next = Some(next);
+        self
+    }
+}
+
+impl Tool for TextEditTool {
+    fn name(&self) -> &str {
+        "text_edit"
+    }
+
+    fn on_move(&mut self, _panel: &mut Panel, _event: &ToolEvent) {}
+
+    fn on_push(&mut self, panel: &mut Panel, event: &ToolEvent) {
+        let next = self
+            .next
+            .take()
+            .unwrap_or_else(|| Box::new(MoveTool::default()));
+        panel.set_tool(next);
+        // The element may be gone by now, e.g. undone while it was edited.
+        let index = match self.index.or_else(|| panel.elems.len().checked_sub(1)) {
+            Some(index) if index < panel.elems.len() => index,
+            _ => return,
+        };
+        let elem = &mut panel.elems[index];
+        panel
+            .hook_event
+            .as_mut()
+            .unwrap()
+            .end_edit_state(elem, event.coord);
+        // The content comes from the binder's input, which a replay doesn't
+        // have.
+        if let Some(session) = &mut panel.session {
+            if elem.need_input() {
+                session.record(SessionAction::Content {
+                    index,
+                    content: elem.get_content().to_string(),
+                });
+            }
+        }
+    }
+
+    fn cursor(&self, _panel: &Panel) -> Cursor {
+        Cursor::Text
+    }
+
+    fn mode(&self) -> SessionMode {
+        SessionMode::EditState
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// What lengths are shown in, see `Units`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    #[default]
+    Px,
+    Mm,
+    Cm,
+    In,
+    Pt,
+}
+
+impl Unit {
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::Mm => "mm",
+            Unit::Cm => "cm",
+            Unit::In => "in",
+            Unit::Pt => "pt",
+        }
+    }
+}
+
+/// The document's unit. Panel coordinates are pixels at `dpi`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Units {
+    pub unit: Unit,
+    /// Panel units per inch.
+    pub dpi: f64,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units {
+            unit: Unit::Px,
+            dpi: 96.,
+        }
+    }
+}
+
+impl Units {
+    pub fn is_default(&self) -> bool {
+        *self == Units::default()
+    }
+
+    /// Panel units in one `unit`.
+    pub fn unit_size(&self) -> f64 {
+        match self.unit {
+            Unit::Px => 1.,
+            Unit::Mm => self.dpi / 25.4,
+            Unit::Cm => self.dpi / 2.54,
+            Unit::In => self.dpi,
+            Unit::Pt => self.dpi / 72.,
+        }
+    }
+
+    /// From panel units to `unit`.
+    pub fn to_unit(&self, len: f64) -> f64 {
+        len / self.unit_size()
+    }
+
+    /// From `unit` to panel units.
+    pub fn from_unit(&self, value: f64) -> f64 {
+        value * self.unit_size()
+    }
+
+    /// `len` (panel units) in `unit` with its suffix, e.g. `12.5 mm`.
+    pub fn format(&self, len: f64) -> String {
+        format!(
+            "{} {}",
+            format_number(self.to_unit(len)),
+            self.unit.suffix()
+        )
+    }
+
+    /// The shortest of 1, 2 or 5 times a power of ten, in `unit`, that is
+    /// at least `min_len` panel units long. Used to space ruler ticks.
+    pub fn nice_step(&self, min_len: f64) -> f64 {
+        let min = self.to_unit(min_len).max(1e-9);
+        let power = 10f64.powf(min.log10().floor());
+        [1., 2., 5., 10.]
+            .into_iter()
+            .map(|n| n * power)
+            .find(|step| *step >= min)
+            .unwrap_or(10. * power)
+    }
+}
+
+/// At most two decimals, without trailing zeros.
+pub(crate) fn format_number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+/// Where the cursor is and how large the selection is, in the document's
+/// unit, see `Panel::readout`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Readout {
+    pub unit: Unit,
+    pub x: f64,
+    pub y: f64,
+    /// The size of the selection, or of the hovered element.
+    pub size: Option<(f64, f64)>,
+}
+
+impl fmt::Display for Readout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = self.unit.suffix();
+        write!(
+            f,
+            "x {} {suffix}, y {} {suffix}",
+            format_number(self.x),
+            format_number(self.y)
+        )?;
+        if let Some((width, height)) = self.size {
+            write!(
+                f,
+                ", w {} {suffix}, h {} {suffix}",
+                format_number(width),
+                format_number(height)
+            )?;
+        }
+        Ok(())
+    }
+}
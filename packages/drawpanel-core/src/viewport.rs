@@ -0,0 +1,231 @@
+use geo::{coord, Coordinate};
+
+use crate::binder::EventMouseButton;
+
+/// Space left around the content by `Panel::zoom_to_fit` and
+/// `Panel::zoom_to_selection`, in binder coordinates.
+pub const FIT_PADDING: f64 = 20.;
+
+/// Which input pans the view, whatever the tool, see
+/// `Panel::pan_bindings`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanBindings {
+    /// Dragging with any of these pans.
+    pub buttons: Vec<EventMouseButton>,
+    /// Dragging with the left button while space is held pans.
+    pub space_drag: bool,
+    /// Whether the view keeps gliding after a pan, see `Panel::tick`.
+    pub inertia: bool,
+    /// Part of the gliding speed left after one second.
+    pub friction: f64,
+}
+
+impl Default for PanBindings {
+    fn default() -> Self {
+        PanBindings {
+            buttons: vec![EventMouseButton::Middle],
+            space_drag: true,
+            inertia: true,
+            friction: 0.05,
+        }
+    }
+}
+
+/// A 2D affine transform, mapping `(x, y)` to
+/// `(a * x + c * y + e, b * x + d * y + f)` like an SVG `matrix()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine {
+        a: 1.,
+        b: 0.,
+        c: 0.,
+        d: 1.,
+        e: 0.,
+        f: 0.,
+    };
+
+    pub fn translate(offset: Coordinate) -> Self {
+        Affine {
+            e: offset.x,
+            f: offset.y,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(scale: f64) -> Self {
+        Affine {
+            a: scale,
+            d: scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Applies `self` after `other`.
+    pub fn then_after(&self, other: &Affine) -> Affine {
+        Affine {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    /// `None` when the transform collapses the plane, e.g. a zero scale.
+    pub fn invert(&self) -> Option<Affine> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0. || !det.is_finite() {
+            return None;
+        }
+        Some(Affine {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+
+    pub fn is_axis_aligned(&self) -> bool {
+        self.b.abs() < 1e-9 && self.c.abs() < 1e-9
+    }
+
+    /// How much lengths such as stroke widths grow, on average.
+    pub fn length_scale(&self) -> f64 {
+        ((self.a * self.d - self.b * self.c).abs()).sqrt()
+    }
+
+    pub fn apply(&self, coord: Coordinate) -> Coordinate {
+        coord! {
+            x: self.a * coord.x + self.c * coord.y + self.e,
+            y: self.b * coord.x + self.d * coord.y + self.f,
+        }
+    }
+}
+
+/// Which part of the panel the binder shows. Panel coordinates map to the
+/// binder's through `matrix`, a scale by `scale` followed by a move to
+/// `lt_coord`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Where the panel's origin, the page's left top corner, is drawn.
+    pub lt_coord: Coordinate,
+    pub scale: f64,
+    pub min_scale: f64,
+    pub max_scale: f64,
+    /// `zoom_in` multiplies the scale by it, `zoom_out` divides.
+    pub zoom_step: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport {
+            lt_coord: coord! { x: 0., y: 0. },
+            scale: 1.,
+            min_scale: 0.1,
+            max_scale: 10.,
+            zoom_step: 1.1,
+        }
+    }
+}
+
+impl Viewport {
+    pub fn new(lt_coord: Coordinate) -> Self {
+        Viewport {
+            lt_coord,
+            ..Default::default()
+        }
+    }
+
+    /// From panel to binder coordinates.
+    pub fn matrix(&self) -> Affine {
+        Affine::translate(self.lt_coord).then_after(&Affine::scale(self.scale))
+    }
+
+    /// From binder to panel coordinates. The scale is never 0, see
+    /// `clamp_scale`.
+    pub fn inverse(&self) -> Affine {
+        self.matrix().invert().unwrap_or(Affine::IDENTITY)
+    }
+
+    fn set_matrix(&mut self, matrix: Affine) {
+        self.scale = matrix.a;
+        self.lt_coord = coord! { x: matrix.e, y: matrix.f };
+    }
+
+    /// From binder to panel coordinates.
+    pub fn to_world(&self, screen: Coordinate) -> Coordinate {
+        self.inverse().apply(screen)
+    }
+
+    /// From panel to binder coordinates.
+    pub fn to_screen(&self, world: Coordinate) -> Coordinate {
+        self.matrix().apply(world)
+    }
+
+    /// Keeps the scale within the limits, and above 0 whatever they are.
+    pub fn clamp_scale(&self, scale: f64) -> f64 {
+        let scale = scale.clamp(self.min_scale, self.max_scale);
+        if scale > 0. && scale.is_finite() {
+            scale
+        } else {
+            self.scale
+        }
+    }
+
+    /// Changes the scale, within the limits, keeping what is under
+    /// `anchor` (binder coordinates) in place.
+    pub fn zoom_to(&mut self, scale: f64, anchor: Coordinate) {
+        let factor = self.clamp_scale(scale) / self.scale;
+        // Scale around the anchor, on top of the current view.
+        let around = Affine::translate(anchor)
+            .then_after(&Affine::scale(factor))
+            .then_after(&Affine::translate(-anchor));
+        self.set_matrix(around.then_after(&self.matrix()));
+    }
+
+    pub fn zoom_in(&mut self, anchor: Coordinate) {
+        self.zoom_to(self.scale * self.zoom_step, anchor);
+    }
+
+    pub fn zoom_out(&mut self, anchor: Coordinate) {
+        self.zoom_to(self.scale / self.zoom_step, anchor);
+    }
+
+    /// Moves the view by `delta` in binder coordinates.
+    pub fn pan_by(&mut self, delta: Coordinate) {
+        self.set_matrix(Affine::translate(delta).then_after(&self.matrix()));
+    }
+
+    /// Shows `world` (panel coordinates) as large as the limits allow,
+    /// centered in `screen` (binder coordinates) with `padding` around it.
+    pub fn fit(&mut self, world: geo::Rect, screen: geo::Rect, padding: f64) {
+        let width = (screen.width() - padding * 2.).max(1.);
+        let height = (screen.height() - padding * 2.).max(1.);
+        let scale = match (world.width() > 0., world.height() > 0.) {
+            (true, true) => (width / world.width()).min(height / world.height()),
+            (true, false) => width / world.width(),
+            (false, true) => height / world.height(),
+            (false, false) => self.scale,
+        };
+        self.scale = self.clamp_scale(scale);
+        self.lt_coord = screen.center() - world.center() * self.scale;
+    }
+
+    /// Back to scale 1 with the panel's origin at `lt_coord`.
+    pub fn reset(&mut self, lt_coord: Coordinate) {
+        self.lt_coord = lt_coord;
+        self.scale = 1.;
+    }
+}